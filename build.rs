@@ -39,6 +39,9 @@ fn compile() -> Result<Library> {
     cmake.define("CMAKE_INSTALL_DEFAULT_LIBDIR", "lib");
     if cfg!(feature = "simd") {
         cmake.configure_arg("-DREQUIRE_SIMD=ON");
+    } else {
+        // without the SIMD extensions, the output does not depend on the CPU
+        cmake.configure_arg("-DWITH_SIMD=0");
     }
 
     let dst_path = cmake.build();
@@ -49,6 +52,18 @@ fn compile() -> Result<Library> {
     println!("cargo:rustc-link-search=native={}", lib_path.display());
     println!("cargo:rustc-link-lib=dylib=turbojpeg");
 
+    // The incremental compression of rows uses the libjpeg API, which is not exported by the
+    // TurboJPEG library
+    if cfg!(feature = "libjpeg") {
+        cc::Build::new()
+            .file("ffi/rows.c")
+            .include(&include_path)
+            .compile("turbojpeg_rows");
+        println!("cargo:rustc-link-lib=dylib=jpeg");
+        println!("cargo:rerun-if-changed=ffi/rows.c");
+        println!("cargo:rerun-if-changed=ffi/rows.h");
+    }
+
     Ok(Library {
         include_paths: vec![include_path],
         defines: HashMap::new(),
@@ -0,0 +1,265 @@
+/*
+ * Incremental compression of images whose rows are supplied one at a time (see rows.h).
+ *
+ * The settings are applied in the same way as by setCompDefaults() in turbojpeg.c, so that the
+ * output is identical to the output of tj3Compress8() with the same parameters.
+ */
+
+#include <setjmp.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "jpeglib.h"
+#include "jerror.h"
+#include "turbojpeg.h"
+#include "rows.h"
+
+#define INITIAL_BUF_SIZE  65536
+
+struct error_mgr {
+  struct jpeg_error_mgr pub;
+  jmp_buf setjmp_buffer;
+  char message[JMSG_LENGTH_MAX];
+};
+
+struct dest_mgr {
+  struct jpeg_destination_mgr pub;
+  unsigned char *buf;
+  size_t size;
+  int wipe;
+  tjrs_write_fn write;
+  void *write_ctx;
+};
+
+struct tjrs_rows {
+  struct jpeg_compress_struct cinfo;
+  struct error_mgr jerr;
+  struct dest_mgr dest;
+};
+
+static const J_COLOR_SPACE pf2cs[TJ_NUMPF] = {
+  JCS_EXT_RGB, JCS_EXT_BGR, JCS_EXT_RGBX, JCS_EXT_BGRX, JCS_EXT_XBGR,
+  JCS_EXT_XRGB, JCS_GRAYSCALE, JCS_EXT_RGBA, JCS_EXT_BGRA, JCS_EXT_ABGR,
+  JCS_EXT_ARGB, JCS_CMYK
+};
+
+static const J_COLOR_SPACE tjcs2cs[TJ_NUMCS] = {
+  JCS_RGB, JCS_YCbCr, JCS_GRAYSCALE, JCS_CMYK, JCS_YCCK
+};
+
+static void wipe_free(unsigned char *buf, size_t size, int wipe)
+{
+  if (buf != NULL && wipe) {
+    volatile unsigned char *ptr = buf;
+
+    while (size-- > 0) *ptr++ = 0;
+  }
+  free(buf);
+}
+
+static void error_exit(j_common_ptr cinfo)
+{
+  struct error_mgr *err = (struct error_mgr *)cinfo->err;
+
+  (*cinfo->err->format_message) (cinfo, err->message);
+  longjmp(err->setjmp_buffer, 1);
+}
+
+static void output_message(j_common_ptr cinfo)
+{
+  /* warnings are not fatal, keep them only as the last message */
+  struct error_mgr *err = (struct error_mgr *)cinfo->err;
+
+  (*cinfo->err->format_message) (cinfo, err->message);
+}
+
+static void init_destination(j_compress_ptr cinfo)
+{
+  struct dest_mgr *dest = (struct dest_mgr *)cinfo->dest;
+
+  dest->pub.next_output_byte = dest->buf;
+  dest->pub.free_in_buffer = dest->size;
+}
+
+static void flush_output(j_compress_ptr cinfo, size_t len)
+{
+  struct dest_mgr *dest = (struct dest_mgr *)cinfo->dest;
+
+  if (len > 0 && dest->write(dest->write_ctx, dest->buf, len) != 0)
+    ERREXIT(cinfo, JERR_FILE_WRITE);
+  dest->pub.next_output_byte = dest->buf;
+  dest->pub.free_in_buffer = dest->size;
+}
+
+static boolean empty_output_buffer(j_compress_ptr cinfo)
+{
+  struct dest_mgr *dest = (struct dest_mgr *)cinfo->dest;
+  size_t size = dest->size * 2;
+  unsigned char *buf;
+
+  if (dest->write != NULL) {
+    /* the whole buffer must be emptied, regardless of free_in_buffer */
+    flush_output(cinfo, dest->size);
+    return TRUE;
+  }
+
+  buf = (unsigned char *)malloc(size);
+
+  if (buf == NULL)
+    ERREXIT1(cinfo, JERR_OUT_OF_MEMORY, 10);
+  memcpy(buf, dest->buf, dest->size);
+  wipe_free(dest->buf, dest->size, dest->wipe);
+
+  dest->pub.next_output_byte = buf + dest->size;
+  dest->pub.free_in_buffer = size - dest->size;
+  dest->buf = buf;
+  dest->size = size;
+  return TRUE;
+}
+
+static void term_destination(j_compress_ptr cinfo)
+{
+  struct dest_mgr *dest = (struct dest_mgr *)cinfo->dest;
+
+  if (dest->write != NULL)
+    flush_output(cinfo, dest->size - dest->pub.free_in_buffer);
+}
+
+tjrs_rows *tjrs_rows_new(void)
+{
+  tjrs_rows *rows = (tjrs_rows *)calloc(1, sizeof(tjrs_rows));
+
+  if (rows == NULL)
+    return NULL;
+  rows->cinfo.err = jpeg_std_error(&rows->jerr.pub);
+  rows->jerr.pub.error_exit = error_exit;
+  rows->jerr.pub.output_message = output_message;
+  if (setjmp(rows->jerr.setjmp_buffer)) {
+    free(rows);
+    return NULL;
+  }
+  jpeg_create_compress(&rows->cinfo);
+
+  rows->dest.pub.init_destination = init_destination;
+  rows->dest.pub.empty_output_buffer = empty_output_buffer;
+  rows->dest.pub.term_destination = term_destination;
+  rows->cinfo.dest = &rows->dest.pub;
+  return rows;
+}
+
+int tjrs_rows_start(tjrs_rows *rows, const tjrs_rows_params *params)
+{
+  j_compress_ptr cinfo = &rows->cinfo;
+  int subsamp = params->subsamp;
+  size_t i;
+
+  if (params->width <= 0 || params->height <= 0 || params->pixel_format < 0 ||
+      params->pixel_format >= TJ_NUMPF || subsamp < 0 || subsamp >= TJ_NUMSAMP ||
+      params->colorspace >= TJ_NUMCS) {
+    strcpy(rows->jerr.message, "Invalid argument");
+    return -1;
+  }
+  if (rows->dest.buf == NULL) {
+    rows->dest.buf = (unsigned char *)malloc(INITIAL_BUF_SIZE);
+    if (rows->dest.buf == NULL) {
+      strcpy(rows->jerr.message, "Memory allocation failure");
+      return -1;
+    }
+    rows->dest.size = INITIAL_BUF_SIZE;
+  }
+  rows->dest.wipe = params->wipe;
+
+  if (setjmp(rows->jerr.setjmp_buffer))
+    return -1;
+
+  cinfo->image_width = params->width;
+  cinfo->image_height = params->height;
+  cinfo->in_color_space = pf2cs[params->pixel_format];
+  cinfo->input_components = tjPixelSize[params->pixel_format];
+  jpeg_set_defaults(cinfo);
+
+  cinfo->restart_interval = params->restart_blocks;
+  cinfo->restart_in_rows = params->restart_rows;
+  cinfo->X_density = (UINT16)params->x_density;
+  cinfo->Y_density = (UINT16)params->y_density;
+  cinfo->density_unit = (UINT8)params->density_unit;
+  cinfo->smoothing_factor = params->smoothing;
+
+  jpeg_set_quality(cinfo, params->quality, TRUE);
+  cinfo->dct_method = JDCT_ISLOW;
+
+  if (params->colorspace >= 0)
+    jpeg_set_colorspace(cinfo, tjcs2cs[params->colorspace]);
+  else if (subsamp == TJSAMP_GRAY)
+    jpeg_set_colorspace(cinfo, JCS_GRAYSCALE);
+  else if (params->pixel_format == TJPF_CMYK)
+    jpeg_set_colorspace(cinfo, JCS_YCCK);
+  else
+    jpeg_set_colorspace(cinfo, JCS_YCbCr);
+
+  cinfo->optimize_coding = params->optimize;
+  if (params->progressive) jpeg_simple_progression(cinfo);
+  cinfo->arith_code = params->arithmetic;
+
+  cinfo->comp_info[0].h_samp_factor = tjMCUWidth[subsamp] / 8;
+  cinfo->comp_info[1].h_samp_factor = 1;
+  cinfo->comp_info[2].h_samp_factor = 1;
+  if (cinfo->num_components > 3)
+    cinfo->comp_info[3].h_samp_factor = tjMCUWidth[subsamp] / 8;
+  cinfo->comp_info[0].v_samp_factor = tjMCUHeight[subsamp] / 8;
+  cinfo->comp_info[1].v_samp_factor = 1;
+  cinfo->comp_info[2].v_samp_factor = 1;
+  if (cinfo->num_components > 3)
+    cinfo->comp_info[3].v_samp_factor = tjMCUHeight[subsamp] / 8;
+
+  jpeg_start_compress(cinfo, TRUE);
+  for (i = 0; i < params->num_markers; i++)
+    jpeg_write_marker(cinfo, params->markers[i].marker, params->markers[i].data,
+                      (unsigned int)params->markers[i].len);
+  if (params->icc_profile != NULL && params->icc_profile_len != 0)
+    jpeg_write_icc_profile(cinfo, params->icc_profile,
+                           (unsigned int)params->icc_profile_len);
+  return 0;
+}
+
+void tjrs_rows_set_writer(tjrs_rows *rows, tjrs_write_fn write, void *ctx)
+{
+  rows->dest.write = write;
+  rows->dest.write_ctx = ctx;
+}
+
+int tjrs_rows_write(tjrs_rows *rows, const unsigned char *row)
+{
+  JSAMPROW row_pointer = (JSAMPROW)row;
+
+  if (setjmp(rows->jerr.setjmp_buffer))
+    return -1;
+  jpeg_write_scanlines(&rows->cinfo, &row_pointer, 1);
+  return 0;
+}
+
+int tjrs_rows_finish(tjrs_rows *rows, const unsigned char **jpeg_buf, size_t *jpeg_size)
+{
+  if (setjmp(rows->jerr.setjmp_buffer))
+    return -1;
+  jpeg_finish_compress(&rows->cinfo);
+  *jpeg_buf = rows->dest.buf;
+  *jpeg_size = rows->dest.write != NULL ? 0 :
+               rows->dest.size - rows->dest.pub.free_in_buffer;
+  return 0;
+}
+
+const char *tjrs_rows_error(const tjrs_rows *rows)
+{
+  return rows->jerr.message;
+}
+
+void tjrs_rows_free(tjrs_rows *rows)
+{
+  if (rows == NULL)
+    return;
+  jpeg_destroy_compress(&rows->cinfo);
+  wipe_free(rows->dest.buf, rows->dest.size, rows->dest.wipe);
+  free(rows);
+}
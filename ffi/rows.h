@@ -0,0 +1,81 @@
+/*
+ * Incremental compression of images whose rows are supplied one at a time.
+ *
+ * The TurboJPEG API can only compress complete frames, so this small wrapper drives the
+ * underlying libjpeg API (jpeg_write_scanlines()) with the same settings as a TurboJPEG
+ * compressor. libjpeg reports errors by calling error_exit(), which must not return, so every
+ * entry point catches the errors with setjmp() and returns -1 instead.
+ */
+
+#ifndef TJRS_ROWS_H
+#define TJRS_ROWS_H
+
+#include <stddef.h>
+
+typedef struct tjrs_rows tjrs_rows;
+
+/* Receives a chunk of compressed data, returns 0 on success and -1 on failure. */
+typedef int (*tjrs_write_fn)(void *ctx, const unsigned char *data, size_t len);
+
+typedef struct tjrs_marker {
+  /* JPEG_APP0 + n or JPEG_COM */
+  int marker;
+  const unsigned char *data;
+  size_t len;
+} tjrs_marker;
+
+typedef struct tjrs_rows_params {
+  int width;
+  int height;
+  /* TJPF_* pixel format of the rows */
+  int pixel_format;
+  int quality;
+  /* TJSAMP_* chrominance subsampling */
+  int subsamp;
+  /* TJCS_* colorspace, or -1 to derive it from the pixel format and subsampling */
+  int colorspace;
+  int optimize;
+  int progressive;
+  int arithmetic;
+  int restart_blocks;
+  int restart_rows;
+  int x_density;
+  int y_density;
+  int density_unit;
+  /* input smoothing factor from 0 to 100 */
+  int smoothing;
+  /* segments written after the headers and before the ICC profile */
+  const tjrs_marker *markers;
+  size_t num_markers;
+  const unsigned char *icc_profile;
+  size_t icc_profile_len;
+  /* overwrite the output buffers with zeros before they are freed */
+  int wipe;
+} tjrs_rows_params;
+
+/* Allocates a compressor, returns NULL on failure. */
+tjrs_rows *tjrs_rows_new(void);
+
+/* Writes the headers of an image, returns -1 on error. */
+int tjrs_rows_start(tjrs_rows *rows, const tjrs_rows_params *params);
+
+/* Passes the compressed data to write() in chunks of at most the buffer size (64 KiB) as soon as
+ * they are produced, instead of collecting them (tjrs_rows_finish() then returns no data), so it
+ * must be set before the first image. A failure of write() is reported as an error of the current
+ * call. */
+void tjrs_rows_set_writer(tjrs_rows *rows, tjrs_write_fn write, void *ctx);
+
+/* Compresses the next row of the image, returns -1 on error. */
+int tjrs_rows_write(tjrs_rows *rows, const unsigned char *row);
+
+/* Finishes the image after all rows have been written, returns -1 on error. The compressed image
+ * stays valid until the compressor is freed. */
+int tjrs_rows_finish(tjrs_rows *rows, const unsigned char **jpeg_buf, size_t *jpeg_size);
+
+/* Returns the message of the last error. */
+const char *tjrs_rows_error(const tjrs_rows *rows);
+
+/* Frees the compressor and its output. */
+void tjrs_rows_free(tjrs_rows *rows);
+
+#endif
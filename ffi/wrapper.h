@@ -1 +1,2 @@
-#include "libjpeg-turbo/src/turbojpeg.h"
\ No newline at end of file
+#include "libjpeg-turbo/src/turbojpeg.h"
+#include "rows.h"
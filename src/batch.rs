@@ -0,0 +1,226 @@
+use crate::buf::{OutputArena, OwnedBuf};
+use crate::common::Result;
+use crate::compress::{CompressParams, Compressor, TileGrid, TileLayout};
+use crate::transform::{max_transformed_len, Transform, Transformer};
+use crate::Image;
+use rayon::prelude::*;
+use std::sync::Mutex;
+
+/// Compress many images in parallel.
+///
+/// The images are compressed concurrently on the [rayon] thread pool, with every image using the
+/// same `params`. Compressor instances are pooled, so at most one instance is created per worker
+/// thread. The returned buffers are in the same order as `images`. If any image fails to compress,
+/// an error is returned.
+///
+/// # Example
+///
+/// ```
+/// let frames: Vec<_> = (1..=8)
+///     .map(|i| turbojpeg::Image::mandelbrot(64 * i, 48 * i, turbojpeg::PixelFormat::RGB))
+///     .collect();
+/// let images: Vec<_> = frames.iter().map(|frame| frame.as_deref()).collect();
+///
+/// let mut params = turbojpeg::CompressParams::default();
+/// params.quality = 80;
+/// let jpegs = turbojpeg::compress_batch(&images, &params)?;
+///
+/// assert_eq!(jpegs.len(), 8);
+/// let header = turbojpeg::read_header(&jpegs[2])?;
+/// assert_eq!((header.width, header.height), (192, 144));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn compress_batch(images: &[Image<&[u8]>], params: &CompressParams) -> Result<Vec<OwnedBuf>> {
+    params.validate()?;
+    let pool = Mutex::new(Vec::new());
+    images
+        .par_iter()
+        .map(|image| {
+            let pooled = pool.lock().unwrap().pop();
+            let mut compressor = match pooled {
+                Some(compressor) => compressor,
+                None => {
+                    let mut compressor = Compressor::new()?;
+                    compressor.set_params(params)?;
+                    compressor
+                }
+            };
+            let res = compressor.compress_to_owned(*image);
+            pool.lock().unwrap().push(compressor);
+            res
+        })
+        .collect()
+}
+
+/// Compress an image as a grid of independent JPEG tiles in parallel.
+///
+/// This is the parallel version of [`Compressor::compress_tiles()`]: the tiles are compressed
+/// concurrently on the [rayon] thread pool using [`compress_batch()`], every tile with the same
+/// `params`.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(2000, 1500, turbojpeg::PixelFormat::RGB);
+/// let params = turbojpeg::CompressParams::default();
+/// let grid = turbojpeg::compress_tiles_parallel(image.as_deref(), 512, 512, &params)?;
+///
+/// assert_eq!((grid.columns, grid.rows), (4, 3));
+/// assert_eq!(grid.tiles.len(), 12);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn compress_tiles_parallel(
+    image: Image<&[u8]>,
+    tile_width: usize,
+    tile_height: usize,
+    params: &CompressParams,
+) -> Result<TileGrid> {
+    let layout = TileLayout::new(image.width, image.height, tile_width, tile_height)?;
+    let regions: Vec<_> = layout
+        .tiles()
+        .map(|tile| tile.region(image, false))
+        .collect();
+    let data = compress_batch(&regions, params)?;
+    Ok(layout.into_grid(data))
+}
+
+/// Apply the same lossless transform to many JPEG images in parallel.
+///
+/// The images are transformed concurrently on the [rayon] thread pool. Transformer instances are
+/// pooled, so at most one instance is created per worker thread. The returned buffers are in the
+/// same order as `jpegs`. If any image fails to transform, an error is returned.
+///
+/// # Example
+///
+/// ```
+/// # use turbojpeg::{Transform, TransformOp};
+/// let jpegs: Vec<Vec<u8>> = (1..=4)
+///     .map(|i| {
+///         let image = turbojpeg::Image::mandelbrot(64 * i, 48 * i, turbojpeg::PixelFormat::RGB);
+///         turbojpeg::compress(image.as_deref(), 80, turbojpeg::Subsamp::Sub2x2).map(|j| j.to_vec())
+///     })
+///     .collect::<Result<_, _>>()?;
+///
+/// let rotated = turbojpeg::transform_batch(&Transform::op(TransformOp::Rot90), &jpegs)?;
+/// let header = turbojpeg::read_header(&rotated[1])?;
+/// assert_eq!((header.width, header.height), (96, 128));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn transform_batch<D>(transform: &Transform, jpegs: &[D]) -> Result<Vec<OwnedBuf>>
+where
+    D: AsRef<[u8]> + Sync,
+{
+    let pool = Mutex::new(Vec::new());
+    jpegs
+        .par_iter()
+        .map(|jpeg_data| {
+            let pooled = pool.lock().unwrap().pop();
+            let mut transformer = match pooled {
+                Some(transformer) => transformer,
+                None => Transformer::new()?,
+            };
+            let res = transformer.transform_to_owned(transform, jpeg_data.as_ref());
+            pool.lock().unwrap().push(transformer);
+            res
+        })
+        .collect()
+}
+
+/// Compress many images in parallel into one [`OutputArena`].
+///
+/// This works like [`compress_batch()`], but instead of allocating a buffer for every image, the
+/// images are split into chunks, every chunk is compressed into its own arena on the [rayon]
+/// thread pool, and the arenas are finally concatenated. The number of allocations therefore does
+/// not grow with the number of images. The outputs in the arena are in the same order as
+/// `images`.
+///
+/// # Example
+///
+/// ```
+/// let frames: Vec<_> = (1..=100)
+///     .map(|i| turbojpeg::Image::mandelbrot(16 + i, 16 + i, turbojpeg::PixelFormat::RGB))
+///     .collect();
+/// let images: Vec<_> = frames.iter().map(|frame| frame.as_deref()).collect();
+///
+/// let params = turbojpeg::CompressParams::default();
+/// let arena = turbojpeg::compress_batch_arena(&images, &params)?;
+///
+/// assert_eq!(arena.len(), 100);
+/// let header = turbojpeg::read_header(arena.get(9).unwrap())?;
+/// assert_eq!((header.width, header.height), (26, 26));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn compress_batch_arena(
+    images: &[Image<&[u8]>],
+    params: &CompressParams,
+) -> Result<OutputArena> {
+    params.validate()?;
+    let arenas = images
+        .par_chunks(arena_chunk_len(images.len()))
+        .map(|chunk| {
+            let mut compressor = Compressor::new()?;
+            compressor.set_params(params)?;
+            let mut arena = OutputArena::new();
+            for image in chunk {
+                arena.reserve(compressor.max_buf_len(image.width, image.height, 8)?);
+                arena.push_with(|output| compressor.compress(*image, output))?;
+            }
+            Ok(arena)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(OutputArena::concat(arenas))
+}
+
+/// Apply the same lossless transform to many JPEG images in parallel into one [`OutputArena`].
+///
+/// This works like [`transform_batch()`], but the outputs are stored in an arena (see
+/// [`compress_batch_arena()`]).
+///
+/// # Example
+///
+/// ```
+/// # use turbojpeg::{Transform, TransformOp};
+/// let jpegs: Vec<Vec<u8>> = (1..=20)
+///     .map(|i| {
+///         let image = turbojpeg::Image::mandelbrot(16 * i, 8 * i, turbojpeg::PixelFormat::RGB);
+///         turbojpeg::compress(image.as_deref(), 80, turbojpeg::Subsamp::Sub2x2).map(|j| j.to_vec())
+///     })
+///     .collect::<Result<_, _>>()?;
+///
+/// let rotated = turbojpeg::transform_batch_arena(&Transform::op(TransformOp::Rot90), &jpegs)?;
+/// assert_eq!(rotated.len(), 20);
+/// let header = turbojpeg::read_header(rotated.get(1).unwrap())?;
+/// assert_eq!((header.width, header.height), (16, 32));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "rayon")))]
+pub fn transform_batch_arena<D>(transform: &Transform, jpegs: &[D]) -> Result<OutputArena>
+where
+    D: AsRef<[u8]> + Sync,
+{
+    let arenas = jpegs
+        .par_chunks(arena_chunk_len(jpegs.len()))
+        .map(|chunk| {
+            let mut transformer = Transformer::new()?;
+            let mut arena = OutputArena::new();
+            for jpeg_data in chunk {
+                arena.reserve(max_transformed_len(jpeg_data.as_ref())?);
+                arena.push_with(|output| {
+                    transformer.transform(transform, jpeg_data.as_ref(), output)
+                })?;
+            }
+            Ok(arena)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(OutputArena::concat(arenas))
+}
+
+/// Returns the number of items in one chunk of a batch that is processed into an arena, so that
+/// there are a few chunks for every thread.
+fn arena_chunk_len(len: usize) -> usize {
+    len.div_ceil(4 * rayon::current_num_threads()).max(1)
+}
@@ -0,0 +1,250 @@
+use crate::common::{Error, PixelFormat, Result};
+use crate::handle::Handle;
+use crate::image_internal::Image16;
+use crate::{ffi, Image};
+use std::ffi::CString;
+use std::path::Path;
+
+type LoadFn<S> = unsafe extern "C" fn(
+    ffi::tjhandle,
+    *const libc::c_char,
+    *mut libc::c_int,
+    libc::c_int,
+    *mut libc::c_int,
+    *mut libc::c_int,
+) -> *mut S;
+
+type SaveFn<S> = unsafe extern "C" fn(
+    ffi::tjhandle,
+    *const libc::c_char,
+    *const S,
+    libc::c_int,
+    libc::c_int,
+    libc::c_int,
+    libc::c_int,
+) -> libc::c_int;
+
+/// Load an image from a Windows BMP or PBMPLUS (PPM/PGM) file.
+///
+/// If `format` is `None`, the image uses the pixel format that is the most suitable for the
+/// file ([`PixelFormat::GRAY`] for PGM files and grayscale BMP files, otherwise
+/// [`PixelFormat::BGR`] or [`PixelFormat::RGB`]). Otherwise, the pixels are converted into the
+/// given format. The returned image has tightly packed rows. PBMPLUS files with more than 8 bits
+/// per sample are scaled to 8 bits; use [`load_image16()`] to load them with full precision.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let path = std::env::temp_dir().join("tj_load_image.ppm");
+/// turbojpeg::save_image(&path, image.as_deref())?;
+///
+/// let loaded = turbojpeg::load_image(&path, Some(turbojpeg::PixelFormat::RGB))?;
+/// assert_eq!(loaded.pixels, image.pixels);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3LoadImage8")]
+pub fn load_image<P: AsRef<Path>>(path: P, format: Option<PixelFormat>) -> Result<Image<Vec<u8>>> {
+    load(path.as_ref(), format, None, ffi::tj3LoadImage8)
+}
+
+/// Save an image into a Windows BMP or PBMPLUS (PPM/PGM) file.
+///
+/// The file format is chosen by the extension of `path` (`.bmp` for BMP, anything else for
+/// PBMPLUS). Grayscale images are stored in PGM or 8-bit (indexed) BMP files, other images in
+/// PPM or 24-bit BMP files. If the file already exists, it is replaced.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::BGRA);
+/// let path = std::env::temp_dir().join("tj_save_image.bmp");
+/// turbojpeg::save_image(&path, image.as_deref())?;
+///
+/// let loaded = turbojpeg::load_image(&path, Some(turbojpeg::PixelFormat::BGRA))?;
+/// assert_eq!(loaded.pixel(10, 20), image.pixel(10, 20));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3SaveImage8")]
+pub fn save_image<P: AsRef<Path>>(path: P, image: Image<&[u8]>) -> Result<()> {
+    save(path.as_ref(), image, None, ffi::tj3SaveImage8)
+}
+
+/// Load an image with 9 to 16 bits per sample from a PBMPLUS (PPM/PGM) file.
+///
+/// `precision` is the number of bits per sample of the returned image (from 9 to 16); if the file
+/// uses a different precision, the samples are scaled. Images with a precision of 12 bits can be
+/// compressed using [`Compressor::compress_12bit()`][crate::Compressor::compress_12bit], images
+/// with a precision of 16 bits using
+/// [`Compressor::compress_16bit()`][crate::Compressor::compress_16bit]. Returns
+/// [`Error::InvalidParam`] if `precision` is out of range. See [`load_image()`] for the meaning
+/// of `format`.
+///
+/// # Example
+///
+/// ```
+/// let mut image = turbojpeg::Image::alloc16(256, 256, turbojpeg::PixelFormat::GRAY);
+/// for (i, sample) in image.pixels.iter_mut().enumerate() {
+///     *sample = i as u16;
+/// }
+/// let path = std::env::temp_dir().join("tj_load_image16.pgm");
+/// turbojpeg::save_image16(&path, image.as_deref(), 16)?;
+///
+/// let loaded = turbojpeg::load_image16(&path, None, 16)?;
+/// assert_eq!(loaded.format, turbojpeg::PixelFormat::GRAY);
+/// assert_eq!(loaded.pixels, image.pixels);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3LoadImage12")]
+#[doc(alias = "tj3LoadImage16")]
+pub fn load_image16<P: AsRef<Path>>(
+    path: P,
+    format: Option<PixelFormat>,
+    precision: u8,
+) -> Result<Image16> {
+    match precision {
+        9..=12 => {
+            let image = load(path.as_ref(), format, Some(precision), ffi::tj3LoadImage12)?;
+            Ok(image.map_pixels(|pixels| pixels.into_iter().map(|x| x as u16).collect()))
+        }
+        13..=16 => load(path.as_ref(), format, Some(precision), ffi::tj3LoadImage16),
+        _ => Err(Error::InvalidParam("precision")),
+    }
+}
+
+/// Save an image with 9 to 16 bits per sample into a PBMPLUS (PPM/PGM) file.
+///
+/// `precision` is the number of bits per sample of the image (from 9 to 16), and all samples must
+/// be smaller than `1 << precision`. Returns [`Error::InvalidParam`] if `precision` is out of
+/// range or if a sample is too large. Grayscale images are stored in PGM files, other images in
+/// PPM files. If the file already exists, it is replaced.
+///
+/// See [`load_image16()`] for an example.
+#[doc(alias = "tj3SaveImage12")]
+#[doc(alias = "tj3SaveImage16")]
+pub fn save_image16<P: AsRef<Path>>(path: P, image: Image<&[u16]>, precision: u8) -> Result<()> {
+    if !(9..=16).contains(&precision) {
+        return Err(Error::InvalidParam("precision"));
+    }
+    image.assert_valid(image.pixels.len());
+    let row_len = image.width * image.format.size();
+    let max = (1u32 << precision) - 1;
+    let mut rows = image.pixels.chunks(image.pitch.max(1)).take(image.height);
+    if rows.any(|row| row[..row_len].iter().any(|&sample| sample as u32 > max)) {
+        return Err(Error::InvalidParam("pixels"));
+    }
+
+    if precision <= 12 {
+        let samples = unsafe {
+            std::slice::from_raw_parts(
+                image.pixels.as_ptr() as *const libc::c_short,
+                image.pixels.len(),
+            )
+        };
+        let image = Image {
+            pixels: samples,
+            width: image.width,
+            pitch: image.pitch,
+            height: image.height,
+            format: image.format,
+        };
+        save(path.as_ref(), image, Some(precision), ffi::tj3SaveImage12)
+    } else {
+        save(path.as_ref(), image, Some(precision), ffi::tj3SaveImage16)
+    }
+}
+
+fn load<S: Copy>(
+    path: &Path,
+    format: Option<PixelFormat>,
+    precision: Option<u8>,
+    load_fn: LoadFn<S>,
+) -> Result<Image<Vec<S>>> {
+    let path = c_path(path)?;
+    let mut handle = Handle::new(ffi::TJINIT_TJINIT_COMPRESS)?;
+    if let Some(precision) = precision {
+        handle.set(ffi::TJPARAM_TJPARAM_PRECISION, precision as libc::c_int)?;
+    }
+
+    let mut width = 0;
+    let mut height = 0;
+    let mut c_format = format.map_or(ffi::TJPF_TJPF_UNKNOWN, |format| format as libc::c_int);
+    let ptr = unsafe {
+        load_fn(
+            handle.as_ptr(),
+            path.as_ptr(),
+            &mut width,
+            1,
+            &mut height,
+            &mut c_format,
+        )
+    };
+    if ptr.is_null() {
+        return Err(handle.get_error());
+    }
+
+    let image = PixelFormat::from_int(c_format).map(|format| {
+        let pitch = width as usize * format.size();
+        let pixels = unsafe { std::slice::from_raw_parts(ptr, pitch * height as usize) };
+        Image {
+            pixels: pixels.to_vec(),
+            width: width as usize,
+            pitch,
+            height: height as usize,
+            format,
+        }
+    });
+    unsafe { ffi::tj3Free(ptr as *mut libc::c_void) };
+    image
+}
+
+fn save<S>(
+    path: &Path,
+    image: Image<&[S]>,
+    precision: Option<u8>,
+    save_fn: SaveFn<S>,
+) -> Result<()> {
+    image.assert_valid(image.pixels.len());
+    let path = c_path(path)?;
+    let Image {
+        pixels,
+        width,
+        pitch,
+        height,
+        format,
+    } = image;
+    let width = width
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("width"))?;
+    let pitch = pitch
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("pitch"))?;
+    let height = height
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("height"))?;
+
+    let mut handle = Handle::new(ffi::TJINIT_TJINIT_COMPRESS)?;
+    if let Some(precision) = precision {
+        handle.set(ffi::TJPARAM_TJPARAM_PRECISION, precision as libc::c_int)?;
+    }
+    let res = unsafe {
+        save_fn(
+            handle.as_ptr(),
+            path.as_ptr(),
+            pixels.as_ptr(),
+            width,
+            pitch,
+            height,
+            format as libc::c_int,
+        )
+    };
+    if res != 0 {
+        return Err(handle.get_error());
+    }
+    Ok(())
+}
+
+fn c_path(path: &Path) -> Result<CString> {
+    let path = path.to_str().ok_or(Error::InvalidParam("path"))?;
+    CString::new(path).map_err(|_| Error::InvalidParam("path"))
+}
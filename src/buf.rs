@@ -1,8 +1,11 @@
 use std::convert::{AsMut, AsRef};
 use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
-use std::{ptr, slice};
+use std::mem::MaybeUninit;
+use std::ops::{Deref, DerefMut, Range};
+use std::sync::{Arc, Mutex};
+use std::{fmt, io, ptr, slice};
 
+use crate::common::{Error, Result};
 use crate::ffi;
 
 /// Owned buffer with JPEG data.
@@ -10,10 +13,175 @@ use crate::ffi;
 /// This represents a memory slice which is owned by TurboJPEG and can be automatically resized
 /// when used as an output buffer. You can get a `&[u8]` or `&mut [u8]` from this type, or you can
 /// convert it into [`OutputBuf`] using `.into()`.
+///
+/// The buffer keeps track of its [capacity][Self::capacity] separately from its length, so a
+/// buffer that once held a large image can be reused for smaller images without reallocating.
+///
+/// By default, the memory is allocated by `tj3Alloc()`. Use [`OwnedBuf::new_in()`] or
+/// [`OutputBuf::new_owned_in()`] to allocate it with a custom [`BufAllocator`].
+///
+/// With the `zeroize` feature, the memory is wiped before it is freed, and TurboJPEG is not
+/// allowed to reallocate it on its own, so no copies of sensitive data are left behind in freed
+/// memory. The temporary copies that are made when the metadata of an output are edited (by
+/// [`KeepMarkers`][crate::KeepMarkers], [`Transform::update_exif`][crate::Transform::update_exif]
+/// or [`Transform::xmp`][crate::Transform::xmp]) are wiped as well, but not the memory left
+/// behind when such a copy grows. Data returned in a `Vec<u8>` (for example by
+/// [`into_vec()`][Self::into_vec] or by
+/// [`Compressor::compress_to_vec()`][crate::Compressor::compress_to_vec]) live in memory of the
+/// global allocator, which is not wiped; wrap them in `zeroize::Zeroizing` if needed.
+///
+/// The buffer is `Send` and `Sync`, so it can be moved to other threads or shared between them.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = std::thread::spawn(move || {
+///     turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)
+/// }).join().unwrap()?;
+///
+/// // share the compressed image between threads
+/// let jpeg_data = Arc::new(jpeg_data);
+/// let readers = (0..4).map(|_| {
+///     let jpeg_data = Arc::clone(&jpeg_data);
+///     std::thread::spawn(move || turbojpeg::read_header(&jpeg_data).map(|header| header.width))
+/// }).collect::<Vec<_>>();
+/// for reader in readers {
+///     assert_eq!(reader.join().unwrap()?, 64);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[derive(Debug)]
 pub struct OwnedBuf {
     ptr: *mut u8,
     len: usize,
+    cap: usize,
+    alloc: Option<Allocator>,
+}
+
+/// Custom allocator for the memory of owned buffers.
+///
+/// When an owned buffer uses a custom allocator, TurboJPEG is never allowed to allocate its
+/// memory: unless the buffer is already large enough, it is allocated with the maximal size of
+/// the output before TurboJPEG writes into it. This is useful if you need to track the memory
+/// used by the outputs, or to place them into special memory.
+///
+/// # Safety
+///
+/// [`allocate()`][Self::allocate] must return null or a pointer to `len` bytes that are valid for
+/// reads and writes until the pointer is passed to [`deallocate()`][Self::deallocate].
+///
+/// # Example
+///
+/// Count the bytes that are allocated for compressed images:
+///
+/// ```
+/// use std::alloc::Layout;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// #[derive(Default)]
+/// struct CountingAllocator {
+///     allocated: AtomicUsize,
+/// }
+///
+/// unsafe impl turbojpeg::BufAllocator for CountingAllocator {
+///     fn allocate(&self, len: usize) -> *mut u8 {
+///         self.allocated.fetch_add(len, Ordering::Relaxed);
+///         unsafe { std::alloc::alloc(Layout::array::<u8>(len).unwrap()) }
+///     }
+///
+///     unsafe fn deallocate(&self, ptr: *mut u8, len: usize) {
+///         self.allocated.fetch_sub(len, Ordering::Relaxed);
+///         std::alloc::dealloc(ptr, Layout::array::<u8>(len).unwrap());
+///     }
+/// }
+///
+/// let allocator = Arc::new(CountingAllocator::default());
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let mut compressor = turbojpeg::Compressor::new()?;
+///
+/// let mut output = turbojpeg::OutputBuf::new_owned_in(allocator.clone());
+/// compressor.compress(image.as_deref(), &mut output)?;
+/// assert!(allocator.allocated.load(Ordering::Relaxed) >= output.len());
+///
+/// drop(output);
+/// assert_eq!(allocator.allocated.load(Ordering::Relaxed), 0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub unsafe trait BufAllocator: Send + Sync {
+    /// Allocates `len` bytes (`len` is never zero), returns null on failure.
+    fn allocate(&self, len: usize) -> *mut u8;
+
+    /// Frees memory that was allocated by [`allocate()`][Self::allocate] with the same `len`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by `allocate(len)` of this allocator and must not be used
+    /// after this call.
+    unsafe fn deallocate(&self, ptr: *mut u8, len: usize);
+}
+
+/// Shared handle to a [`BufAllocator`].
+#[derive(Clone)]
+pub(crate) struct Allocator(Arc<dyn BufAllocator>);
+
+impl fmt::Debug for Allocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Allocator")
+    }
+}
+
+/// Temporary copy of output data, which is wiped when dropped with the `zeroize` feature.
+#[cfg(feature = "zeroize")]
+pub(crate) type ScratchVec = zeroize::Zeroizing<Vec<u8>>;
+#[cfg(not(feature = "zeroize"))]
+pub(crate) type ScratchVec = Vec<u8>;
+
+/// Turns `vec` into a [`ScratchVec`].
+#[cfg(feature = "zeroize")]
+pub(crate) fn scratch_vec(vec: Vec<u8>) -> ScratchVec {
+    zeroize::Zeroizing::new(vec)
+}
+#[cfg(not(feature = "zeroize"))]
+pub(crate) fn scratch_vec(vec: Vec<u8>) -> ScratchVec {
+    vec
+}
+
+/// Allocates `len` bytes with the `alloc` or with `tj3Alloc()`.
+///
+/// Panics if the memory cannot be allocated.
+fn allocate_raw(alloc: Option<&Allocator>, len: usize) -> *mut u8 {
+    if len == 0 {
+        // `tj3Alloc(0)` may return null
+        return ptr::null_mut();
+    }
+    let ptr = match alloc {
+        Some(Allocator(alloc)) => alloc.allocate(len),
+        None => unsafe { ffi::tj3Alloc(len as ffi::size_t) as *mut u8 },
+    };
+    assert!(!ptr.is_null(), "allocation of {} bytes failed", len);
+    ptr
+}
+
+/// Frees memory allocated by [`allocate_raw()`] with `cap` bytes.
+unsafe fn free_raw(alloc: Option<&Allocator>, ptr: *mut u8, cap: usize) {
+    #[cfg(feature = "zeroize")]
+    if !ptr.is_null() {
+        zeroize::Zeroize::zeroize(&mut *ptr::slice_from_raw_parts_mut(
+            ptr as *mut std::mem::MaybeUninit<u8>,
+            cap,
+        ));
+    }
+    // `tj3Free(NULL)` does nothing, skipping it keeps buffers without memory free of FFI calls
+    // (so that moving them around can be checked by Miri)
+    match alloc {
+        _ if ptr.is_null() => {}
+        Some(Allocator(alloc)) => alloc.deallocate(ptr, cap),
+        None => ffi::tj3Free(ptr as *mut libc::c_void),
+    }
 }
 
 impl Deref for OwnedBuf {
@@ -44,6 +212,16 @@ impl OwnedBuf {
         OwnedBuf {
             ptr: ptr::null_mut(),
             len: 0,
+            cap: 0,
+            alloc: None,
+        }
+    }
+
+    /// Creates an empty buffer that allocates its memory with `allocator`.
+    pub fn new_in(allocator: Arc<dyn BufAllocator>) -> OwnedBuf {
+        OwnedBuf {
+            alloc: Some(Allocator(allocator)),
+            ..OwnedBuf::new()
         }
     }
 
@@ -51,11 +229,22 @@ impl OwnedBuf {
     ///
     /// Panics if `len` overflows or if the memory cannot be allocated.
     pub fn allocate(len: usize) -> OwnedBuf {
-        let ptr = unsafe { ffi::tj3Alloc(len as ffi::size_t) };
-        assert!(!ptr.is_null(), "tj3Alloc() returned null");
+        Self::allocate_with(None, len)
+    }
+
+    /// Allocates a buffer with given length with `allocator`.
+    ///
+    /// Panics if the memory cannot be allocated.
+    pub fn allocate_in(len: usize, allocator: Arc<dyn BufAllocator>) -> OwnedBuf {
+        Self::allocate_with(Some(Allocator(allocator)), len)
+    }
+
+    pub(crate) fn allocate_with(alloc: Option<Allocator>, len: usize) -> OwnedBuf {
         OwnedBuf {
-            ptr: ptr as *mut u8,
+            ptr: allocate_raw(alloc.as_ref(), len),
             len,
+            cap: len,
+            alloc,
         }
     }
 
@@ -70,37 +259,738 @@ impl OwnedBuf {
     pub fn len(&self) -> usize {
         self.len
     }
+
+    /// Returns true if the buffer has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes that the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Returns a reader over the data in the buffer.
+    ///
+    /// The reader implements [`Read`][std::io::Read], [`BufRead`][std::io::BufRead] and
+    /// [`Seek`][std::io::Seek] and borrows the buffer, so the data is not copied. Use
+    /// [`into_reader()`][Self::into_reader] to obtain a reader that owns the buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::Read as _;
+    ///
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    ///
+    /// let mut soi = [0; 2];
+    /// jpeg_data.reader().read_exact(&mut soi)?;
+    /// assert_eq!(soi, [0xff, 0xd8]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reader(&self) -> io::Cursor<&[u8]> {
+        io::Cursor::new(self)
+    }
+
+    /// Converts the buffer into a reader that owns it.
+    ///
+    /// This is useful for APIs that take a `Read + 'static` value, such as upload bodies. The
+    /// data is not copied and the buffer is freed when the reader is dropped; it can be obtained
+    /// back using [`Cursor::into_inner()`][io::Cursor::into_inner].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let len = jpeg_data.len();
+    ///
+    /// let mut reader = jpeg_data.into_reader();
+    /// let mut copy = Vec::new();
+    /// std::io::copy(&mut reader, &mut copy)?;
+    /// assert_eq!(copy.len(), len);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn into_reader(self) -> io::Cursor<OwnedBuf> {
+        io::Cursor::new(self)
+    }
+
+    /// Shortens the buffer to `len` bytes, without reallocating the memory.
+    ///
+    /// If `len` is greater than the current length, this has no effect.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut buf = turbojpeg::OwnedBuf::copy_from_slice(b"JPEG\0\0\0");
+    /// buf.truncate(4);
+    /// assert_eq!(&buf[..], b"JPEG");
+    /// ```
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Splits the buffer into two at the given index.
+    ///
+    /// Returns a newly allocated buffer with the bytes `[at, len)`, and `self` is left with the
+    /// bytes `[0, at)` (its memory is not reallocated).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // split two concatenated JPEG images
+    /// let image = turbojpeg::Image::mandelbrot(32, 32, turbojpeg::PixelFormat::RGB);
+    /// let first = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::None)?;
+    /// let second = turbojpeg::compress(image.as_deref(), 50, turbojpeg::Subsamp::None)?;
+    ///
+    /// let mut buf = turbojpeg::OwnedBuf::copy_from_slice(&[&first[..], &second[..]].concat());
+    /// let tail = buf.split_off(first.len());
+    /// assert_eq!(&buf[..], &first[..]);
+    /// assert_eq!(&tail[..], &second[..]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn split_off(&mut self, at: usize) -> OwnedBuf {
+        assert!(
+            at <= self.len,
+            "`at` split index (is {}) should be <= len (is {})",
+            at,
+            self.len
+        );
+        let mut tail = OwnedBuf::allocate_with(self.alloc.clone(), self.len - at);
+        tail.copy_from_slice(&self[at..]);
+        self.len = at;
+        tail
+    }
+
+    /// Consumes the buffer and returns a pointer to its data and its length.
+    ///
+    /// The memory is allocated by `tj3Alloc()`, so it can be handed over to C code, which must
+    /// free it with `tj3Free()` (or `tjFree()`). If the buffer uses a custom [`BufAllocator`], the
+    /// data is first copied into memory from `tj3Alloc()`. An empty buffer may return a null
+    /// pointer. The pointer can be converted back with [`from_raw()`][Self::from_raw].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let copy = jpeg_data.to_vec();
+    ///
+    /// let (ptr, len) = jpeg_data.into_raw();
+    /// // ... the pointer is passed through C code, which returns it or frees it with tj3Free() ...
+    /// let jpeg_data = unsafe { turbojpeg::OwnedBuf::from_raw(ptr, len) };
+    /// assert_eq!(&jpeg_data[..], &copy[..]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn into_raw(self) -> (*mut u8, usize) {
+        let mut buf = if self.alloc.is_some() {
+            OwnedBuf::copy_from_slice(&self)
+        } else {
+            self
+        };
+        let (ptr, len) = (buf.ptr, buf.len);
+        buf.ptr = ptr::null_mut(); // do not free the pointer in the destructor
+        (ptr, len)
+    }
+
+    /// Creates a buffer from a pointer returned by `tj3Alloc()` (or by
+    /// [`into_raw()`][Self::into_raw]) and the length of its data.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null (then `len` must be 0) or a pointer to at least `len` initialized bytes
+    /// allocated by `tj3Alloc()`, and it must not be used or freed by anything else afterwards.
+    pub unsafe fn from_raw(ptr: *mut u8, len: usize) -> OwnedBuf {
+        debug_assert!(!ptr.is_null() || len == 0);
+        OwnedBuf {
+            ptr,
+            len,
+            cap: len,
+            alloc: None,
+        }
+    }
+
+    /// Ensures that the buffer can hold at least `additional` more bytes than its length.
+    ///
+    /// Like `Vec::reserve()`, this takes the number of additional bytes. If the buffer is
+    /// smaller, its data is copied into a new allocation with exactly the required capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the required capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let cap = self.len.checked_add(additional).expect("capacity overflow");
+        if self.cap < cap {
+            let mut buf = OwnedBuf::allocate_with(self.alloc.clone(), cap);
+            buf[..self.len].copy_from_slice(self);
+            buf.len = self.len;
+            *self = buf;
+        }
+    }
+
+    /// Shrinks the capacity of the buffer to its length.
+    ///
+    /// When the length is smaller than the capacity, the data is copied into a new allocation of
+    /// the exact size and the old memory is freed. This is useful for buffers that are kept for a
+    /// long time after they have been written, for example in a cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let mut output = turbojpeg::OutputBuf::allocate_owned(1 << 20);
+    /// compressor.compress(image.as_deref(), &mut output)?;
+    ///
+    /// let mut jpeg_data = output.into_owned();
+    /// assert_eq!(jpeg_data.capacity(), 1 << 20);
+    /// jpeg_data.shrink_to_fit();
+    /// assert_eq!(jpeg_data.capacity(), jpeg_data.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn shrink_to_fit(&mut self) {
+        if self.cap > self.len {
+            let mut buf = OwnedBuf::allocate_with(self.alloc.clone(), self.len);
+            buf.copy_from_slice(self);
+            *self = buf;
+        }
+    }
+
+    /// Copies the data into a `Vec<u8>` and frees the buffer.
+    ///
+    /// The memory of the buffer is allocated by TurboJPEG (or by a custom [`BufAllocator`]), not
+    /// by the Rust global allocator, so it cannot be handed over to a `Vec` without copying. This
+    /// method performs a single copy into an allocation that is sized for the data (use
+    /// [`into_boxed_slice()`][Self::into_boxed_slice] if you need a guarantee that there is no
+    /// spare capacity).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let len = jpeg_data.len();
+    ///
+    /// let vec = jpeg_data.into_vec();
+    /// assert_eq!(vec.len(), len);
+    /// assert!(vec.capacity() >= vec.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn into_vec(self) -> Vec<u8> {
+        self.to_vec()
+    }
+
+    /// Copies the data into a `Box<[u8]>` and frees the buffer.
+    ///
+    /// Like [`into_vec()`][Self::into_vec], this has to copy the data once, because the memory is
+    /// not allocated by the Rust global allocator.
+    pub fn into_boxed_slice(self) -> Box<[u8]> {
+        Box::from(&self[..])
+    }
+}
+
+impl Default for OwnedBuf {
+    fn default() -> OwnedBuf {
+        OwnedBuf::new()
+    }
+}
+
+impl Clone for OwnedBuf {
+    /// Allocates a new buffer and copies the data into it.
+    fn clone(&self) -> OwnedBuf {
+        let mut buf = OwnedBuf::allocate_with(self.alloc.clone(), self.len);
+        buf.copy_from_slice(self);
+        buf
+    }
 }
 
+/// Wraps the buffer into [`Bytes`][bytes::Bytes] without copying the data.
+///
+/// The memory is freed when the last `Bytes` that refers to it is dropped.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+/// let ptr = jpeg_data.as_ptr();
+///
+/// let bytes = bytes::Bytes::from(jpeg_data);
+/// assert_eq!(bytes.as_ptr(), ptr);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "bytes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "bytes")))]
+impl From<OwnedBuf> for bytes::Bytes {
+    fn from(buf: OwnedBuf) -> bytes::Bytes {
+        bytes::Bytes::from_owner(buf)
+    }
+}
+
+/// Serializes the buffer as a byte sequence.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+/// use serde::de::value::{BytesDeserializer, Error};
+///
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+///
+/// let deserializer = BytesDeserializer::<Error>::new(&jpeg_data);
+/// let buf = turbojpeg::OwnedBuf::deserialize(deserializer)?;
+/// assert_eq!(&buf[..], &jpeg_data[..]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for OwnedBuf {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self)
+    }
+}
+
+/// Deserializes the buffer from a byte sequence into memory allocated by TurboJPEG.
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl<'de> serde::Deserialize<'de> for OwnedBuf {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<OwnedBuf, D::Error> {
+        deserializer.deserialize_bytes(OwnedBufVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct OwnedBufVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for OwnedBufVisitor {
+    type Value = OwnedBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte array")
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, data: &[u8]) -> std::result::Result<OwnedBuf, E> {
+        Ok(OwnedBuf::copy_from_slice(data))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> std::result::Result<OwnedBuf, A::Error> {
+        // formats without native byte arrays store the bytes as a sequence of integers
+        let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(1 << 20));
+        while let Some(byte) = seq.next_element::<u8>()? {
+            data.push(byte);
+        }
+        Ok(OwnedBuf::copy_from_slice(&data))
+    }
+}
+
+/// Overwrites the data in the buffer with zeros.
+///
+/// With the `zeroize` feature, the whole memory of the buffer is also wiped before it is freed
+/// (this applies to [`OutputBuf`] as well).
+///
+/// # Example
+///
+/// ```
+/// use zeroize::Zeroize;
+///
+/// let mut buf = turbojpeg::OwnedBuf::copy_from_slice(b"secret");
+/// buf.zeroize();
+/// assert_eq!(&buf[..], &[0; 6]);
+/// ```
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl zeroize::Zeroize for OwnedBuf {
+    fn zeroize(&mut self) {
+        self[..].zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl zeroize::ZeroizeOnDrop for OwnedBuf {}
+
 impl Drop for OwnedBuf {
     fn drop(&mut self) {
-        unsafe { ffi::tj3Free(self.ptr as *mut libc::c_void) };
+        unsafe { free_raw(self.alloc.as_ref(), self.ptr, self.cap) };
+    }
+}
+
+/// Pool of reusable buffers for JPEG data and pixels.
+///
+/// The pool hands out [`OutputBuf`]s that keep the memory of previously returned buffers, so
+/// compressing or transforming many images does not allocate new memory for every image (see
+/// [`OutputBuf::capacity()`]). It also recycles `Vec<u8>` buffers for pixel data. The pool can be
+/// shared between threads, for example in a server that handles every request on a different
+/// thread.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// let pool = Arc::new(turbojpeg::BufPool::with_limit(4));
+/// let handles = (0..4).map(|i| {
+///     let pool = Arc::clone(&pool);
+///     std::thread::spawn(move || -> Result<usize, turbojpeg::Error> {
+///         let image = turbojpeg::Image::mandelbrot(64 + 16 * i, 64, turbojpeg::PixelFormat::RGB);
+///         let mut compressor = turbojpeg::Compressor::new()?;
+///
+///         let mut output = pool.get();
+///         compressor.compress(image.as_deref(), &mut output)?;
+///         let len = output.len();
+///         // ... send the JPEG data somewhere ...
+///         pool.put(output.into_owned());
+///         Ok(len)
+///     })
+/// }).collect::<Vec<_>>();
+///
+/// for handle in handles {
+///     assert!(handle.join().unwrap()? > 0);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct BufPool {
+    bufs: Mutex<Vec<OwnedBuf>>,
+    pixels: Mutex<Vec<Vec<u8>>>,
+    limit: Option<usize>,
+}
+
+impl BufPool {
+    /// Creates an empty pool that keeps all returned buffers.
+    pub fn new() -> BufPool {
+        BufPool::default()
+    }
+
+    /// Creates an empty pool that keeps at most `limit` JPEG buffers and `limit` pixel buffers.
+    ///
+    /// Buffers that are returned to a full pool are freed.
+    pub fn with_limit(limit: usize) -> BufPool {
+        BufPool {
+            limit: Some(limit),
+            ..BufPool::default()
+        }
+    }
+
+    /// Takes an owned output buffer from the pool.
+    ///
+    /// The buffer keeps the memory of the buffer that was returned to the pool most recently, or
+    /// it is empty if the pool is empty.
+    pub fn get(&self) -> OutputBuf<'static> {
+        match lock(&self.bufs).pop() {
+            Some(buf) => OutputBuf::owned(buf),
+            None => OutputBuf::new_owned(),
+        }
+    }
+
+    /// Returns a buffer to the pool, so that its memory can be reused by [`get()`][Self::get].
+    pub fn put(&self, buf: OwnedBuf) {
+        if buf.capacity() == 0 {
+            return;
+        }
+        let mut bufs = lock(&self.bufs);
+        if self.limit.map_or(true, |limit| bufs.len() < limit) {
+            bufs.push(buf);
+        }
+    }
+
+    /// Takes a pixel buffer of length `len` from the pool.
+    ///
+    /// A returned buffer with sufficient capacity is reused if there is any, otherwise a new
+    /// buffer is allocated. The content of the buffer is unspecified.
+    pub fn get_pixels(&self, len: usize) -> Vec<u8> {
+        let mut vec = {
+            let mut pixels = lock(&self.pixels);
+            match pixels.iter().position(|vec| vec.capacity() >= len) {
+                Some(i) => pixels.swap_remove(i),
+                None => pixels.pop().unwrap_or_default(),
+            }
+        };
+        vec.resize(len, 0);
+        vec
+    }
+
+    /// Returns a pixel buffer to the pool, so that its memory can be reused by
+    /// [`get_pixels()`][Self::get_pixels].
+    pub fn put_pixels(&self, vec: Vec<u8>) {
+        if vec.capacity() == 0 {
+            return;
+        }
+        let mut pixels = lock(&self.pixels);
+        if self.limit.map_or(true, |limit| pixels.len() < limit) {
+            pixels.push(vec);
+        }
+    }
+}
+
+/// Locks the mutex, ignoring poisoning (the buffers in the pool are always valid).
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Arena that stores many compressed images in one allocation.
+///
+/// Every output is written directly after the previous one, so producing thousands of small
+/// images (such as thumbnails) needs only a handful of allocations: when the free space runs out,
+/// the arena is reallocated with at least twice the capacity and the operation is repeated. Every
+/// output is identified by its index, and its position in the arena by a range of offsets.
+///
+/// The batch functions [`compress_batch_arena()`][crate::compress_batch_arena()] and
+/// [`transform_batch_arena()`][crate::transform_batch_arena()] (with the `rayon` feature) produce
+/// an arena for many images in parallel.
+///
+/// # Example
+///
+/// ```
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// let mut arena = turbojpeg::OutputArena::with_capacity(16 * 1024);
+///
+/// for i in 1..=10 {
+///     let image = turbojpeg::Image::mandelbrot(16 * i, 16 * i, turbojpeg::PixelFormat::RGB);
+///     arena.reserve(compressor.max_buf_len(image.width, image.height, 8)?);
+///     arena.push_with(|output| compressor.compress(image.as_deref(), output))?;
+/// }
+///
+/// assert_eq!(arena.len(), 10);
+/// let header = turbojpeg::read_header(arena.get(3).unwrap())?;
+/// assert_eq!((header.width, header.height), (64, 64));
+/// assert_eq!(arena.ranges()[0].start, 0);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct OutputArena {
+    buf: OwnedBuf,
+    ranges: Vec<Range<usize>>,
+}
+
+impl OutputArena {
+    /// Creates an empty arena.
+    pub fn new() -> OutputArena {
+        OutputArena::default()
+    }
+
+    /// Creates an empty arena with `cap` bytes of memory.
+    pub fn with_capacity(cap: usize) -> OutputArena {
+        let mut buf = OwnedBuf::allocate(cap);
+        buf.len = 0;
+        OutputArena {
+            buf,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Ensures that at least `additional` bytes are free, reallocating the arena if needed.
+    ///
+    /// Like `Vec::reserve()` and [`OutputBuf::reserve()`], this takes the number of additional
+    /// bytes. The arena grows to at least twice its capacity, so that
+    /// reserving space before every output does not reallocate it every time.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.capacity() - self.buf.len < additional {
+            self.grow(additional);
+        }
+    }
+
+    /// Writes an output into the free space of the arena and returns its index.
+    ///
+    /// The function `f` receives a borrowed [`OutputBuf`] that covers the free space and should
+    /// write the output into it, typically using [`Compressor::compress()`][crate::Compressor::compress]
+    /// or [`Transformer::transform()`][crate::Transformer::transform]. If it fails with
+    /// [`Error::OutputBufTooSmall`], the arena grows and `f` is called again.
+    ///
+    /// TurboJPEG writes directly into the arena only if the free space can hold the maximal size
+    /// of the output (such as [`Compressor::max_buf_len()`][crate::Compressor::max_buf_len]),
+    /// otherwise the output is written into a temporary buffer and copied. [Reserve][Self::reserve]
+    /// this size before pushing the output to avoid the copy.
+    pub fn push_with<F>(&mut self, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&mut OutputBuf) -> Result<()>,
+    {
+        const MIN_FREE_SPACE: usize = 4096;
+        self.reserve(MIN_FREE_SPACE);
+        loop {
+            let start = self.buf.len;
+            let free_space = self.capacity() - start;
+            let free_ptr = unsafe { self.buf.ptr.add(start) };
+            let mut output = unsafe { OutputBuf::from_raw_parts(free_ptr, free_space) };
+            let res = f(&mut output);
+            assert!(
+                output.ptr == free_ptr && !output.is_owned,
+                "the output buffer of the arena was replaced"
+            );
+            let len = output.len;
+            match res {
+                Ok(()) => {
+                    self.buf.len = start + len;
+                    self.ranges.push(start..start + len);
+                    return Ok(self.ranges.len() - 1);
+                }
+                Err(Error::OutputBufTooSmall(required_len)) if required_len > free_space => {
+                    self.grow(required_len)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Reallocates the arena, so that at least `len` bytes are free.
+    fn grow(&mut self, len: usize) {
+        let cap = usize::max(2 * self.capacity(), self.buf.len + len);
+        self.buf.reserve(cap - self.buf.len);
+    }
+
+    /// Returns the number of outputs in the arena.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns true if the arena contains no outputs.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the number of bytes that the arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// Returns the output with given index.
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        let range = self.ranges.get(index)?;
+        Some(&self.buf[range.clone()])
+    }
+
+    /// Returns an iterator over the outputs in the arena.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ {
+        self.ranges.iter().map(|range| &self.buf[range.clone()])
+    }
+
+    /// Returns the ranges of offsets of the outputs in [`data()`][Self::data].
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Returns all outputs, stored one after another.
+    pub fn data(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Removes all outputs, keeping the memory of the arena.
+    pub fn clear(&mut self) {
+        self.buf.len = 0;
+        self.ranges.clear();
+    }
+
+    /// Converts the arena into the buffer with all outputs and the ranges of the outputs.
+    pub fn into_parts(self) -> (OwnedBuf, Vec<Range<usize>>) {
+        (self.buf, self.ranges)
+    }
+
+    /// Concatenates multiple arenas into one.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn concat(arenas: Vec<OutputArena>) -> OutputArena {
+        let total_len = arenas.iter().map(|arena| arena.buf.len).sum();
+        let mut result = OutputArena::with_capacity(total_len);
+        for arena in arenas {
+            let offset = result.buf.len;
+            result.buf.len += arena.buf.len;
+            result.buf[offset..].copy_from_slice(&arena.buf);
+            let ranges = arena.ranges.into_iter();
+            result
+                .ranges
+                .extend(ranges.map(|range| offset + range.start..offset + range.end));
+        }
+        result
     }
 }
 
 /// Output buffer for JPEG data (borrowed or owned).
 ///
 /// When compressing or transforming images, we need a memory buffer to store the compressed JPEG
-/// data. This buffer comes in two variants, which are similar to `Cow::Borrowed` and `Cow::Owned`
-/// from the standard library:
+/// data. This buffer comes in three variants, the first two are similar to `Cow::Borrowed` and
+/// `Cow::Owned` from the standard library:
 ///
-/// - Borrowed buffer wraps a `&mut [u8]`, preallocated slice of fixed size provided by you. When
-/// using a borrowed buffer, TurboJPEG cannot resize the buffer, so the operation will fail if the
-/// output does not fit into the buffer.
+/// - Borrowed buffer wraps a `&mut [u8]` (or `&mut [MaybeUninit<u8>]`), preallocated slice of
+///   fixed size provided by you. When using a borrowed buffer, TurboJPEG cannot resize the
+///   buffer, so the operation will fail if the output does not fit into the buffer.
 ///
 /// - Owned buffer wraps an [`OwnedBuf`], memory buffer owned by TurboJPEG. This buffer can be
 /// automatically resized to contain the compressed data, so you don't have to worry about its size.
 ///
+/// - Vec buffer wraps a `&mut Vec<u8>` provided by you. The output is written directly into the
+///   spare capacity of the vector, which is grown as needed, and it is appended to the vector
+///   when the `OutputBuf` is dropped (see [`OutputBuf::vec()`]).
+///
 /// The lifetime parameter `'a` tracks the lifetime of the borrowed slice. In the case of owned
 /// buffer, the lifetime can be `'static`.
+///
+/// The buffer is `Send`, so an owned buffer can be moved to another thread.
+///
+/// The [length][Self::len] of the buffer is the size of the last output, and it is tracked
+/// separately from the [capacity][Self::capacity]. When the buffer is reused for another output,
+/// the whole capacity is available: a borrowed buffer can always hold as many bytes as the
+/// original slice, and an owned buffer is written in place if the output fits, so reusing the same
+/// `OutputBuf` in a loop avoids reallocating the memory for every image.
+///
+/// # Example
+///
+/// ```
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// let mut output = turbojpeg::OutputBuf::new_owned();
+///
+/// let large = turbojpeg::Image::mandelbrot(256, 256, turbojpeg::PixelFormat::RGB);
+/// compressor.compress(large.as_deref(), &mut output)?;
+/// let capacity = output.capacity();
+///
+/// // the smaller image is written into the memory that is already allocated
+/// let small = turbojpeg::Image::mandelbrot(64, 64, turbojpeg::PixelFormat::RGB);
+/// compressor.compress(small.as_deref(), &mut output)?;
+/// assert!(output.len() < capacity);
+/// assert_eq!(output.capacity(), capacity);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[derive(Debug)]
 pub struct OutputBuf<'a> {
     pub(crate) ptr: *mut u8,
     pub(crate) len: usize,
+    pub(crate) cap: usize,
     pub(crate) is_owned: bool,
+    pub(crate) alloc: Option<Allocator>,
+    pub(crate) vec: Option<&'a mut Vec<u8>>,
     pub(crate) _phantom: PhantomData<&'a mut [u8]>,
 }
 
+// Thread safety of the buffers. They only hold raw pointers because of the FFI, and neither of them
+// has interior mutability. `tests/send_sync.rs` moves and shares them between threads, also under
+// Miri.
+
+// SAFETY: The buffer is plain memory allocated by `tj3Alloc()` or by a `BufAllocator` (which is
+// `Send` and `Sync`), which can be freed from any thread.
+unsafe impl Send for OwnedBuf {}
+
+// SAFETY: A shared reference only gives access to the bytes as `&[u8]`, like a shared `Vec<u8>`.
+unsafe impl Sync for OwnedBuf {}
+
+// SAFETY: An owned buffer is sent like an `OwnedBuf`, and a borrowed buffer like the `&'a mut [u8]`
+// (or `&'a mut Vec<u8>`) that it was created from.
+unsafe impl Send for OutputBuf<'_> {}
+
 impl<'a> Deref for OutputBuf<'a> {
     type Target = [u8];
     fn deref(&self) -> &[u8] {
@@ -129,28 +1019,210 @@ impl<'a> OutputBuf<'a> {
         OutputBuf {
             ptr: slice.as_mut_ptr(),
             len: slice.len(),
+            cap: slice.len(),
+            is_owned: false,
+            alloc: None,
+            vec: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Converts an uninitialized slice into a borrowed `OutputBuf`.
+    ///
+    /// This works like [`borrowed()`][Self::borrowed], but the memory does not have to be
+    /// initialized, so a preallocated arena can be used as the destination without zeroing it
+    /// first. The buffer starts empty; after an operation, only the written output is accessible.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::mem::MaybeUninit;
+    ///
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let mut arena = vec![MaybeUninit::<u8>::uninit(); 64 * 1024];
+    /// let mut output = turbojpeg::OutputBuf::borrowed_uninit(&mut arena);
+    /// assert!(output.is_empty());
+    ///
+    /// compressor.compress(image.as_deref(), &mut output)?;
+    /// let header = turbojpeg::read_header(&output)?;
+    /// assert_eq!((header.width, header.height), (64, 48));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn borrowed_uninit(slice: &'a mut [MaybeUninit<u8>]) -> OutputBuf<'a> {
+        OutputBuf {
+            ptr: slice.as_mut_ptr() as *mut u8,
+            len: 0,
+            cap: slice.len(),
+            is_owned: false,
+            alloc: None,
+            vec: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Creates a borrowed `OutputBuf` that writes into a raw memory region.
+    ///
+    /// This is intended for memory that is owned by something other than Rust, such as a POSIX
+    /// shared memory segment or a slot of a ring buffer shared with another process. The output
+    /// is written into the first [`len()`][Self::len] bytes of the region, so the caller can
+    /// advance its own write offset by this length after the operation. Like a
+    /// [borrowed][Self::borrowed] buffer, the region is never reallocated, and the operation fails
+    /// with [`Error::OutputBufTooSmall`][crate::Error::OutputBufTooSmall] if the output does not
+    /// fit.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for writes of `cap` bytes for the lifetime `'a` (the memory does not
+    /// have to be initialized), and no other thread or process may read or write the region while
+    /// the `OutputBuf` exists. If `cap` is 0, `ptr` may be null.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// // a region that is shared with a consumer, with the JPEG written after a 16-byte header
+    /// let mut region = vec![0u8; 64 * 1024];
+    /// let (base, region_len) = (region.as_mut_ptr(), region.len());
+    /// let offset = 16;
+    ///
+    /// let mut output = unsafe {
+    ///     turbojpeg::OutputBuf::from_raw_parts(base.add(offset), region_len - offset)
+    /// };
+    /// compressor.compress(image.as_deref(), &mut output)?;
+    /// let jpeg_len = output.len();
+    /// drop(output);
+    ///
+    /// let jpeg_data = &region[offset..][..jpeg_len];
+    /// let header = turbojpeg::read_header(jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (64, 48));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub unsafe fn from_raw_parts(ptr: *mut u8, cap: usize) -> OutputBuf<'a> {
+        OutputBuf {
+            ptr: if cap == 0 { ptr::null_mut() } else { ptr },
+            len: 0,
+            cap,
             is_owned: false,
+            alloc: None,
+            vec: None,
             _phantom: PhantomData,
         }
     }
 
     /// Converts an `OwnedBuf` into an owned `OutputBuf`.
     pub fn owned(mut buf: OwnedBuf) -> OutputBuf<'a> {
-        let OwnedBuf { ptr, len } = buf;
+        let (ptr, len, cap, alloc) = (buf.ptr, buf.len, buf.cap, buf.alloc.take());
         buf.ptr = ptr::null_mut(); // do not free the pointer in the OwnedBuf destructor
         OutputBuf {
             ptr,
             len,
+            cap,
             is_owned: true,
+            alloc,
+            vec: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Creates an output buffer that appends its data to `vec`.
+    ///
+    /// The output is written directly into the spare capacity of `vec`, after its current
+    /// content, so no data is copied. Unlike a [borrowed][Self::borrowed] buffer, the vector is
+    /// grown as needed before an operation writes into it. When the `OutputBuf` is dropped, the
+    /// content of the buffer (the output of the last operation, see [`len()`][Self::len]) is
+    /// appended to `vec`. The previous content of `vec` is never modified, so if no operation
+    /// succeeded, `vec` keeps its original length. This is convenient if you need the result in a
+    /// `Vec<u8>` and want to reuse the same vector (after [clearing][Vec::clear] it) for many
+    /// images.
+    ///
+    /// For [`Transformer::transform_multi()`][crate::Transformer::transform_multi], such a buffer
+    /// counts as borrowed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let mut jpeg_data = Vec::new();
+    /// compressor.compress(image.as_deref(), &mut turbojpeg::OutputBuf::vec(&mut jpeg_data))?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (64, 48));
+    ///
+    /// // the vector is left untouched if the operation fails
+    /// let len = jpeg_data.len();
+    /// let invalid = turbojpeg::Image { width: 0, ..image.as_deref() };
+    /// let mut output = turbojpeg::OutputBuf::vec(&mut jpeg_data);
+    /// assert!(compressor.compress(invalid, &mut output).is_err());
+    /// drop(output);
+    /// assert_eq!(jpeg_data.len(), len);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn vec(vec: &'a mut Vec<u8>) -> OutputBuf<'a> {
+        let mut buf = OutputBuf {
+            ptr: ptr::null_mut(),
+            len: 0,
+            cap: 0,
+            is_owned: false,
+            alloc: None,
+            vec: Some(vec),
+            _phantom: PhantomData,
+        };
+        buf.use_spare_capacity();
+        buf
+    }
+
+    /// Points a buffer created by [`vec()`][Self::vec] to the spare capacity of the vector.
+    fn use_spare_capacity(&mut self) {
+        if let Some(ref mut vec) = self.vec {
+            let spare = vec.spare_capacity_mut();
+            self.ptr = spare.as_mut_ptr() as *mut u8;
+            self.cap = spare.len();
+        }
+    }
+
+    /// Grows the vector of a buffer created by [`vec()`][Self::vec] so that the buffer can hold
+    /// `cap` bytes, the data of the buffer is preserved. Other buffers are not modified.
+    pub(crate) fn grow_vec(&mut self, cap: usize) {
+        if let Some(ref mut vec) = self.vec {
+            if self.cap < cap {
+                // the data is moved together with the content of the vector
+                let base = vec.len();
+                unsafe { vec.set_len(base + self.len) };
+                vec.reserve(cap - self.len);
+                vec.truncate(base);
+                self.use_spare_capacity();
+            }
+        }
+    }
+
+    /// Allocates an owned buffer with the same allocator as this buffer.
+    pub(crate) fn allocate_like(&self, len: usize) -> OwnedBuf {
+        OwnedBuf::allocate_with(self.alloc.clone(), len)
+    }
+
+    /// Replaces the data of an owned buffer with `buf`.
+    pub(crate) fn set_owned(&mut self, buf: OwnedBuf) {
+        debug_assert!(self.is_owned);
+        *self = OutputBuf::owned(buf);
+    }
+
     /// Creates an empty owned buffer.
     pub fn new_owned() -> OutputBuf<'a> {
         Self::owned(OwnedBuf::new())
     }
 
+    /// Creates an empty owned buffer that allocates its memory with `allocator` (see
+    /// [`BufAllocator`]).
+    pub fn new_owned_in(allocator: Arc<dyn BufAllocator>) -> OutputBuf<'a> {
+        Self::owned(OwnedBuf::new_in(allocator))
+    }
+
     /// Allocates an owned buffer with given capacity.
     pub fn allocate_owned(cap: usize) -> OutputBuf<'a> {
         Self::owned(OwnedBuf::allocate(cap))
@@ -161,17 +1233,199 @@ impl<'a> OutputBuf<'a> {
         self.len
     }
 
+    /// Returns true if the buffer has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes that can be written into the buffer without reallocating.
+    ///
+    /// For a borrowed buffer, this is the length of the slice.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Ensures that an owned buffer can hold at least `additional` more bytes than its length
+    /// without reallocating.
+    ///
+    /// Like `Vec::reserve()`, this takes the number of additional bytes. An output is always
+    /// written from the start of the buffer, so reserve the expected size of the output in an
+    /// empty (or [cleared][Self::clear]) buffer.
+    ///
+    /// If you know the expected size of the output (for example from
+    /// [`Compressor::max_buf_len()`][crate::Compressor::max_buf_len] or
+    /// [`compressed_buf_len()`][crate::compressed_buf_len]), reserving it up front allocates the
+    /// memory once, and the output is then written in place instead of reallocating the buffer
+    /// while it is written. The data in the buffer is preserved. Borrowed buffers cannot be
+    /// resized, so this has no effect on them, but the vector of a buffer created by
+    /// [`vec()`][Self::vec] is grown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(256, 256, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let mut output = turbojpeg::OutputBuf::new_owned();
+    /// output.reserve(compressor.max_buf_len(256, 256, 8)?);
+    /// let capacity = output.capacity();
+    ///
+    /// compressor.compress(image.as_deref(), &mut output)?;
+    /// assert_eq!(output.capacity(), capacity);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the required capacity overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let cap = self.len.checked_add(additional).expect("capacity overflow");
+        self.grow_vec(cap);
+        if self.is_owned && self.cap < cap {
+            let mut buf = self.allocate_like(cap);
+            buf[..self.len].copy_from_slice(self);
+            buf.truncate(self.len);
+            self.set_owned(buf);
+        }
+    }
+
+    /// Sets the length of the buffer to 0, keeping its memory and capacity.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut output = turbojpeg::OutputBuf::allocate_owned(1024);
+    /// output.clear();
+    /// assert!(output.is_empty());
+    /// assert_eq!(output.capacity(), 1024);
+    /// ```
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Returns true if TurboJPEG must not reallocate the buffer.
+    ///
+    /// TurboJPEG allocates only the memory of an owned buffer that has none: an owned buffer with
+    /// memory is written in place, because TurboJPEG may free a stale pointer when it grows a
+    /// buffer that it did not allocate in the same call.
+    pub(crate) fn no_realloc(&self) -> bool {
+        !self.is_owned || !self.ptr.is_null()
+    }
+
+    /// Returns true if a borrowed buffer holds less than `max_len` bytes.
+    ///
+    /// With NOREALLOC, TurboJPEG replaces the size of the buffer with its own bound of the output
+    /// size, so it may write up to `max_len` bytes into the buffer: such a buffer must not be
+    /// passed to TurboJPEG.
+    pub(crate) fn is_too_small(&self, max_len: usize) -> bool {
+        !self.is_owned && self.vec.is_none() && self.cap < max_len
+    }
+
+    /// Prepares an owned buffer for an output of at most `max_len` bytes.
+    ///
+    /// A buffer with enough memory is written in place. Otherwise, its memory is freed, so that
+    /// TurboJPEG allocates and grows a new buffer while it writes the output, unless the memory
+    /// must be allocated by us (with a custom allocator, or with the `zeroize` feature, so that
+    /// TurboJPEG never leaves unwiped copies of the data): then `max_len` bytes are allocated.
+    ///
+    /// A buffer created by [`vec()`][Self::vec] is emptied and grown to `max_len` bytes.
+    pub(crate) fn prepare(&mut self, max_len: usize) {
+        if self.vec.is_some() {
+            self.len = 0;
+            self.grow_vec(max_len);
+        } else if self.is_owned && self.cap < max_len {
+            if self.allocates_itself() {
+                self.replace_memory(max_len);
+            } else {
+                self.release();
+            }
+        }
+    }
+
+    /// Allocates at least `len` bytes for an owned buffer, whatever its allocator, so that
+    /// TurboJPEG writes into it in place.
+    ///
+    /// This is used when several owned buffers are written by one call, and one of them must not
+    /// be allocated by TurboJPEG: the NOREALLOC parameter applies to all of them.
+    pub(crate) fn prepare_in_place(&mut self, len: usize) {
+        if self.vec.is_some() {
+            self.prepare(len);
+        } else if self.is_owned && self.cap < len {
+            self.replace_memory(len);
+        }
+    }
+
+    /// Returns true if the memory of an owned buffer must be allocated by us, not by TurboJPEG.
+    pub(crate) fn allocates_itself(&self) -> bool {
+        self.alloc.is_some() || cfg!(feature = "zeroize")
+    }
+
+    /// Frees the memory of an owned buffer, so that TurboJPEG allocates a new buffer.
+    pub(crate) fn release(&mut self) {
+        self.replace_memory(0);
+    }
+
+    /// Replaces the memory of an owned buffer with `cap` new bytes, the data is not preserved.
+    fn replace_memory(&mut self, cap: usize) {
+        debug_assert!(self.is_owned);
+        unsafe { free_raw(self.alloc.as_ref(), self.ptr, self.cap) };
+        // reset the buffer first, so that a panic in the allocator does not cause a double free
+        self.ptr = ptr::null_mut();
+        self.len = 0;
+        self.cap = 0;
+        self.ptr = allocate_raw(self.alloc.as_ref(), cap);
+        self.cap = cap;
+    }
+
+    /// Sets the length of the buffer after TurboJPEG wrote `len` bytes into it.
+    pub(crate) fn set_written(&mut self, len: usize) {
+        self.len = len;
+        self.cap = self.cap.max(len);
+    }
+
+    /// Replaces the content of the buffer with `data`, which must fit into its capacity.
+    ///
+    /// The memory after the current length may be uninitialized, so it is written through the raw
+    /// pointer.
+    pub(crate) fn write_data(&mut self, data: &[u8]) {
+        assert!(data.len() <= self.cap);
+        if !data.is_empty() {
+            unsafe { ptr::copy(data.as_ptr(), self.ptr, data.len()) };
+        }
+        self.len = data.len();
+    }
+
+    /// Inserts `data` at offset `at`, which must fit into the capacity of the buffer.
+    pub(crate) fn insert_data(&mut self, at: usize, data: &[u8]) {
+        assert!(at <= self.len && self.len + data.len() <= self.cap);
+        if !data.is_empty() {
+            unsafe {
+                ptr::copy(
+                    self.ptr.add(at),
+                    self.ptr.add(at + data.len()),
+                    self.len - at,
+                );
+                ptr::copy_nonoverlapping(data.as_ptr(), self.ptr.add(at), data.len());
+            }
+        }
+        self.len += data.len();
+    }
+
     /// Converts this buffer into an owned buffer.
     ///
     /// If `self` is owned, this is a trivial operation, otherwise we must copy the data from the
-    /// borrowed slice into a new owned buffer.
+    /// borrowed slice into a new owned buffer. If `self` was created by [`vec()`][Self::vec], the
+    /// data is copied as well, and it is still appended to the vector.
     pub fn into_owned(mut self) -> OwnedBuf {
-        let OutputBuf {
-            ptr, len, is_owned, ..
-        } = self;
+        let (ptr, len, cap, is_owned) = (self.ptr, self.len, self.cap, self.is_owned);
         self.ptr = ptr::null_mut(); // do not free the pointer in OutputBuf destructor
         if is_owned {
-            OwnedBuf { ptr, len }
+            OwnedBuf {
+                ptr,
+                len,
+                cap,
+                alloc: self.alloc.take(),
+            }
         } else {
             unsafe { OwnedBuf::copy_from_slice(slice::from_raw_parts(ptr, len)) }
         }
@@ -180,12 +1434,23 @@ impl<'a> OutputBuf<'a> {
 
 impl<'a> Drop for OutputBuf<'a> {
     fn drop(&mut self) {
+        if let Some(vec) = self.vec.take() {
+            // the data was written into the spare capacity of the vector
+            unsafe { vec.set_len(vec.len() + self.len) };
+        }
         if self.is_owned {
-            unsafe { ffi::tj3Free(self.ptr as *mut libc::c_void) };
+            unsafe { free_raw(self.alloc.as_ref(), self.ptr, self.cap) };
         }
     }
 }
 
+impl Default for OutputBuf<'_> {
+    /// Creates an empty owned buffer (see [`OutputBuf::new_owned()`]).
+    fn default() -> Self {
+        OutputBuf::new_owned()
+    }
+}
+
 impl<'a> From<&'a mut [u8]> for OutputBuf<'a> {
     fn from(slice: &'a mut [u8]) -> OutputBuf<'a> {
         OutputBuf::borrowed(slice)
@@ -1,8 +1,11 @@
 use crate::ffi;
+use std::fmt;
+use std::str::FromStr;
 
 /// Pixel format determines the layout of pixels in memory.
 #[doc(alias = "TJPF")]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 pub enum PixelFormat {
     /// RGB pixel format.
@@ -102,7 +105,14 @@ pub enum PixelFormat {
 }
 
 impl PixelFormat {
+    pub(crate) fn from_int(format: libc::c_int) -> Result<PixelFormat> {
+        PixelFormat::all()
+            .find(|&pixel_format| pixel_format as libc::c_int == format)
+            .ok_or(Error::BadPixelFormat(format))
+    }
+
     /// The size of a pixel in bytes.
+    #[doc(alias = "pixel_size", alias = "tjPixelSize")]
     pub fn size(&self) -> usize {
         match self {
             PixelFormat::RGB => 3,
@@ -119,6 +129,228 @@ impl PixelFormat {
             PixelFormat::CMYK => 4,
         }
     }
+
+    /// The offset of the red component in a pixel, in bytes.
+    ///
+    /// Returns `None` for [`PixelFormat::GRAY`] and [`PixelFormat::CMYK`], which have no red
+    /// component.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::PixelFormat;
+    /// assert_eq!(PixelFormat::BGRA.red_offset(), Some(2));
+    /// assert_eq!(PixelFormat::XRGB.red_offset(), Some(1));
+    /// assert_eq!(PixelFormat::GRAY.red_offset(), None);
+    /// ```
+    #[doc(alias = "tjRedOffset")]
+    pub fn red_offset(&self) -> Option<usize> {
+        match self {
+            PixelFormat::RGB | PixelFormat::RGBX | PixelFormat::RGBA => Some(0),
+            PixelFormat::BGR | PixelFormat::BGRX | PixelFormat::BGRA => Some(2),
+            PixelFormat::XRGB | PixelFormat::ARGB => Some(1),
+            PixelFormat::XBGR | PixelFormat::ABGR => Some(3),
+            PixelFormat::GRAY | PixelFormat::CMYK => None,
+        }
+    }
+
+    /// The offset of the green component in a pixel, in bytes.
+    ///
+    /// Returns `None` for [`PixelFormat::GRAY`] and [`PixelFormat::CMYK`], which have no green
+    /// component.
+    #[doc(alias = "tjGreenOffset")]
+    pub fn green_offset(&self) -> Option<usize> {
+        match self {
+            PixelFormat::RGB | PixelFormat::RGBX | PixelFormat::RGBA => Some(1),
+            PixelFormat::BGR | PixelFormat::BGRX | PixelFormat::BGRA => Some(1),
+            PixelFormat::XRGB | PixelFormat::ARGB => Some(2),
+            PixelFormat::XBGR | PixelFormat::ABGR => Some(2),
+            PixelFormat::GRAY | PixelFormat::CMYK => None,
+        }
+    }
+
+    /// The offset of the blue component in a pixel, in bytes.
+    ///
+    /// Returns `None` for [`PixelFormat::GRAY`] and [`PixelFormat::CMYK`], which have no blue
+    /// component.
+    #[doc(alias = "tjBlueOffset")]
+    pub fn blue_offset(&self) -> Option<usize> {
+        match self {
+            PixelFormat::RGB | PixelFormat::RGBX | PixelFormat::RGBA => Some(2),
+            PixelFormat::BGR | PixelFormat::BGRX | PixelFormat::BGRA => Some(0),
+            PixelFormat::XRGB | PixelFormat::ARGB => Some(3),
+            PixelFormat::XBGR | PixelFormat::ABGR => Some(1),
+            PixelFormat::GRAY | PixelFormat::CMYK => None,
+        }
+    }
+
+    /// The offset of the alpha component in a pixel, in bytes.
+    ///
+    /// Returns `None` for formats without an alpha component. Note that the X component of the
+    /// RGBX-like formats is not considered to be an alpha component.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::PixelFormat;
+    /// assert_eq!(PixelFormat::ARGB.alpha_offset(), Some(0));
+    /// assert_eq!(PixelFormat::RGBA.alpha_offset(), Some(3));
+    /// assert_eq!(PixelFormat::RGBX.alpha_offset(), None);
+    /// ```
+    #[doc(alias = "tjAlphaOffset")]
+    pub fn alpha_offset(&self) -> Option<usize> {
+        match self {
+            PixelFormat::RGBA | PixelFormat::BGRA => Some(3),
+            PixelFormat::ARGB | PixelFormat::ABGR => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over all pixel formats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 95, turbojpeg::Subsamp::None)?;
+    /// for format in turbojpeg::PixelFormat::all() {
+    ///     if format != turbojpeg::PixelFormat::CMYK {
+    ///         let image = turbojpeg::decompress(&jpeg_data, format)?;
+    ///         assert_eq!(image.pitch, 64 * format.size());
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn all() -> impl ExactSizeIterator<Item = PixelFormat> {
+        [
+            PixelFormat::RGB,
+            PixelFormat::BGR,
+            PixelFormat::RGBX,
+            PixelFormat::BGRX,
+            PixelFormat::XBGR,
+            PixelFormat::XRGB,
+            PixelFormat::GRAY,
+            PixelFormat::RGBA,
+            PixelFormat::BGRA,
+            PixelFormat::ABGR,
+            PixelFormat::ARGB,
+            PixelFormat::CMYK,
+        ]
+        .into_iter()
+    }
+}
+
+impl fmt::Display for PixelFormat {
+    /// Formats the pixel format as its uppercase name, such as `RGB` or `BGRA`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PixelFormat::RGB => "RGB",
+            PixelFormat::BGR => "BGR",
+            PixelFormat::RGBX => "RGBX",
+            PixelFormat::BGRX => "BGRX",
+            PixelFormat::XBGR => "XBGR",
+            PixelFormat::XRGB => "XRGB",
+            PixelFormat::GRAY => "GRAY",
+            PixelFormat::RGBA => "RGBA",
+            PixelFormat::BGRA => "BGRA",
+            PixelFormat::ABGR => "ABGR",
+            PixelFormat::ARGB => "ARGB",
+            PixelFormat::CMYK => "CMYK",
+        })
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = Error;
+
+    /// Parses the name of a pixel format, as formatted by [`Display`][fmt::Display].
+    ///
+    /// The name is case insensitive. Returns [`Error::InvalidParam`] for unknown names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::PixelFormat;
+    /// assert_eq!("rgba".parse::<PixelFormat>()?, PixelFormat::RGBA);
+    /// assert_eq!(PixelFormat::XBGR.to_string(), "XBGR");
+    /// assert!("RGB565".parse::<PixelFormat>().is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<PixelFormat> {
+        PixelFormat::all()
+            .find(|format| format.to_string().eq_ignore_ascii_case(s))
+            .ok_or(Error::InvalidParam("format"))
+    }
+}
+
+/// Pixel format of packed YUV 4:2:2 images.
+///
+/// In these formats, each pair of horizontally adjacent pixels is stored in a 4-byte macropixel
+/// that contains the luminance (Y) samples of both pixels and one chrominance (U and V) sample
+/// shared by them. This is the layout commonly produced by webcams (for example, V4L2 devices).
+///
+/// Images in these formats can be compressed using
+/// [`Compressor::compress_packed_yuv()`][crate::Compressor::compress_packed_yuv].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PackedYuvFormat {
+    /// YUYV (YUY2) pixel format.
+    ///
+    /// The components are stored in the order Y0, U, Y1, V from lowest to highest byte address
+    /// within each macropixel.
+    #[doc(alias = "YUY2")]
+    YUYV,
+
+    /// UYVY pixel format.
+    ///
+    /// The components are stored in the order U, Y0, V, Y1 from lowest to highest byte address
+    /// within each macropixel.
+    UYVY,
+}
+
+impl PackedYuvFormat {
+    /// The size of a macropixel (two pixels) in bytes.
+    pub fn size(&self) -> usize {
+        4
+    }
+
+    /// Offsets of the Y0, U, Y1 and V components in a macropixel.
+    pub(crate) fn offsets(&self) -> [usize; 4] {
+        match self {
+            PackedYuvFormat::YUYV => [0, 1, 2, 3],
+            PackedYuvFormat::UYVY => [1, 0, 3, 2],
+        }
+    }
+}
+
+/// Pixel format of semi-planar YUV 4:2:0 images.
+///
+/// These images consist of a full-resolution luminance (Y) plane followed by a single plane with
+/// interleaved chrominance (U and V) samples, one pair for every 2x2 block of pixels. This is the
+/// layout commonly produced by hardware video decoders, GPUs and Android cameras.
+///
+/// Images in these formats can be compressed using
+/// [`Compressor::compress_semi_planar()`][crate::Compressor::compress_semi_planar].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SemiPlanarFormat {
+    /// NV12 pixel format.
+    ///
+    /// The chrominance plane stores the components in the order U, V.
+    NV12,
+
+    /// NV21 pixel format.
+    ///
+    /// The chrominance plane stores the components in the order V, U.
+    NV21,
+}
+
+impl SemiPlanarFormat {
+    /// Offsets of the U and V components in an interleaved chrominance pair.
+    pub(crate) fn offsets(&self) -> [usize; 2] {
+        match self {
+            SemiPlanarFormat::NV12 => [0, 1],
+            SemiPlanarFormat::NV21 => [1, 0],
+        }
+    }
 }
 
 /// Chrominance subsampling options.
@@ -128,8 +360,13 @@ impl PixelFormat {
 /// averaged together to produce a smaller image with little perceptible loss of image clarity (the
 /// human eye is more sensitive to small changes in brightness than to small changes in color).
 /// This is called "chrominance subsampling".
+///
+/// With the `serde` feature, the subsampling is serialized as a string in the notation of
+/// [`Display`][fmt::Display] (such as `"4:2:0"`); the names of the variants are also accepted
+/// when deserializing.
 #[doc(alias = "TJSAMP")]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(i32)]
 #[non_exhaustive]
 pub enum Subsamp {
@@ -138,6 +375,7 @@ pub enum Subsamp {
     /// The JPEG or YUV image will contain one chrominance component for every pixel in the source
     /// image.
     #[doc(alias = "TJSAMP_444")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:4:4", alias = "None"))]
     None = ffi::TJSAMP_TJSAMP_444,
 
     /// 2x1 chrominance subsampling (4:2:2).
@@ -145,6 +383,7 @@ pub enum Subsamp {
     /// The JPEG or YUV image will contain one chrominance component for every 2x1 block of pixels
     /// in the source image.
     #[doc(alias = "TJSAMP_422")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:2:2", alias = "Sub2x1"))]
     Sub2x1 = ffi::TJSAMP_TJSAMP_422,
 
     /// 2x2 chrominance subsampling (4:2:0).
@@ -152,12 +391,14 @@ pub enum Subsamp {
     /// The JPEG or YUV image will contain one chrominance component for every 2x2 block of pixels
     /// in the source image.
     #[doc(alias = "TJSAMP_420")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:2:0", alias = "Sub2x2"))]
     Sub2x2 = ffi::TJSAMP_TJSAMP_420,
 
     /// Grayscale.
     ///
     /// The JPEG or YUV image will contain no chrominance components.
     #[doc(alias = "TJSAMP_GRAY")]
+    #[cfg_attr(feature = "serde", serde(rename = "gray", alias = "Gray"))]
     Gray = ffi::TJSAMP_TJSAMP_GRAY,
 
     /// 1x2 chrominance subsampling (4:4:0).
@@ -169,6 +410,7 @@ pub enum Subsamp {
     ///
     /// 4:4:0 subsampling is not fully accelerated in libjpeg-turbo.
     #[doc(alias = "TJSAMP_440")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:4:0", alias = "Sub1x2"))]
     Sub1x2 = ffi::TJSAMP_TJSAMP_440,
 
     /// 4x1 chrominance subsampling (4:1:1).
@@ -183,6 +425,7 @@ pub enum Subsamp {
     ///
     /// 4:1:1 subsampling is not fully accelerated in libjpeg-turbo.
     #[doc(alias = "TJSAMP_411")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:1:1", alias = "Sub4x1"))]
     Sub4x1 = ffi::TJSAMP_TJSAMP_411,
 
     /// 1x4 chrominance subsampling (4:4:1).
@@ -197,6 +440,7 @@ pub enum Subsamp {
     ///
     /// 4:4:1 subsampling is not fully accelerated in libjpeg-turbo.
     #[doc(alias = "TJSAMP_441")]
+    #[cfg_attr(feature = "serde", serde(rename = "4:4:1", alias = "Sub1x4"))]
     Sub1x4 = ffi::TJSAMP_TJSAMP_441,
 
     /// Unknown subsampling.
@@ -209,6 +453,7 @@ pub enum Subsamp {
     /// [`Transform::gray`][crate::Transform::gray] is not specified, or
     /// - partially decompressed using a cropping region.
     #[doc(alias = "TJSAMP_UNKNOWN")]
+    #[cfg_attr(feature = "serde", serde(rename = "unknown", alias = "Unknown"))]
     Unknown = ffi::TJSAMP_TJSAMP_UNKNOWN,
 }
 
@@ -314,6 +559,84 @@ impl Subsamp {
             Self::Unknown => (1, 1),
         }
     }
+
+    /// Returns an iterator over all levels of chrominance subsampling, except for
+    /// [`Subsamp::Unknown`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// for subsamp in turbojpeg::Subsamp::all() {
+    ///     let jpeg_data = turbojpeg::compress(image.as_deref(), 90, subsamp)?;
+    ///     assert_eq!(turbojpeg::read_header(&jpeg_data)?.subsamp, subsamp);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn all() -> impl ExactSizeIterator<Item = Subsamp> {
+        [
+            Subsamp::None,
+            Subsamp::Sub2x1,
+            Subsamp::Sub2x2,
+            Subsamp::Gray,
+            Subsamp::Sub1x2,
+            Subsamp::Sub4x1,
+            Subsamp::Sub1x4,
+        ]
+        .into_iter()
+    }
+}
+
+impl fmt::Display for Subsamp {
+    /// Formats the subsampling in the usual J:a:b notation, such as `4:2:0`, or as `gray` or
+    /// `unknown`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Subsamp::None => "4:4:4",
+            Subsamp::Sub2x1 => "4:2:2",
+            Subsamp::Sub2x2 => "4:2:0",
+            Subsamp::Gray => "gray",
+            Subsamp::Sub1x2 => "4:4:0",
+            Subsamp::Sub4x1 => "4:1:1",
+            Subsamp::Sub1x4 => "4:4:1",
+            Subsamp::Unknown => "unknown",
+        })
+    }
+}
+
+impl FromStr for Subsamp {
+    type Err = Error;
+
+    /// Parses the subsampling, as formatted by [`Display`][fmt::Display].
+    ///
+    /// The colons may be omitted (`420`), and the names of the variants (such as `sub2x2` or
+    /// `none`) are also accepted. The string is case insensitive. Returns
+    /// [`Error::InvalidParam`] for unknown strings.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::Subsamp;
+    /// assert_eq!("4:2:0".parse::<Subsamp>()?, Subsamp::Sub2x2);
+    /// assert_eq!("422".parse::<Subsamp>()?, Subsamp::Sub2x1);
+    /// assert_eq!("Gray".parse::<Subsamp>()?, Subsamp::Gray);
+    /// assert_eq!(Subsamp::Sub1x2.to_string(), "4:4:0");
+    /// assert!("4:2:1".parse::<Subsamp>().is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<Subsamp> {
+        match s.to_ascii_lowercase().replace(':', "").as_str() {
+            "444" | "none" => Ok(Subsamp::None),
+            "422" | "sub2x1" => Ok(Subsamp::Sub2x1),
+            "420" | "sub2x2" => Ok(Subsamp::Sub2x2),
+            "gray" => Ok(Subsamp::Gray),
+            "440" | "sub1x2" => Ok(Subsamp::Sub1x2),
+            "411" | "sub4x1" => Ok(Subsamp::Sub4x1),
+            "441" | "sub1x4" => Ok(Subsamp::Sub1x4),
+            "unknown" => Ok(Subsamp::Unknown),
+            _ => Err(Error::InvalidParam("subsamp")),
+        }
+    }
 }
 
 /// JPEG colorspaces.
@@ -387,6 +710,22 @@ impl Colorspace {
     }
 }
 
+/// Units of the pixel density stored in the JFIF header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(i32)]
+pub enum DensityUnit {
+    /// The density is given in unknown units, so it only describes the pixel aspect ratio.
+    #[default]
+    Unknown = 0,
+
+    /// The density is given in pixels per inch (DPI).
+    PixelsPerInch = 1,
+
+    /// The density is given in pixels per centimeter.
+    PixelsPerCm = 2,
+}
+
 /// Specialized `Result` type for TurboJPEG.
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -410,10 +749,60 @@ pub enum Error {
     #[error("TurboJPEG returned unknown colorspace: {0}")]
     BadColorspace(u32),
 
+    /// TurboJPEG returned a pixel format variant that is not known by this crate.
+    #[error("TurboJPEG returned unknown pixel format: {0}")]
+    BadPixelFormat(i32),
+
     /// The given integer value overflowed when converted into type expected by TurboJPEG.
     #[error("integer value {0:?} overflowed")]
     IntegerOverflow(&'static str),
 
+    /// An invalid value was given for a parameter.
+    #[error("invalid value for parameter {0:?}")]
+    InvalidParam(&'static str),
+
+    /// Two parameters were given values that cannot be combined.
+    #[error("parameters {0:?} and {1:?} cannot be combined")]
+    IncompatibleParams(&'static str, &'static str),
+
+    /// The image cannot be compressed into the given number of bytes, even with the lowest
+    /// quality.
+    #[error("image cannot be compressed into {0} bytes")]
+    TargetSizeUnreachable(usize),
+
+    /// The borrowed output buffer is too small for the compressed or transformed image, the given
+    /// number of bytes is required.
+    ///
+    /// TurboJPEG may write up to the maximal size of the output into a buffer that it must not
+    /// reallocate, so a borrowed buffer that is smaller than this size (see
+    /// [`Compressor::max_buf_len()`][crate::Compressor::max_buf_len]) is never passed to
+    /// TurboJPEG: the image is written into a temporary owned buffer instead, and then copied
+    /// into the borrowed buffer if it fits.
+    #[error("output buffer is too small, {0} bytes are required")]
+    OutputBufTooSmall(usize),
+
+    /// The transformed image is larger than the limit given by
+    /// [`Transform::max_output_len`][crate::Transform::max_output_len], its size is given in bytes.
+    #[error("transformed image is too large, it has {0} bytes")]
+    OutputTooLarge(usize),
+
+    /// Two JPEG images cannot be combined, because the given property differs.
+    #[error("JPEG images cannot be combined, they use different {0}")]
+    IncompatibleImages(&'static str),
+
+    /// The JPEG data is malformed.
+    #[error("invalid JPEG data: {0}")]
+    InvalidJpeg(&'static str),
+
+    /// An abbreviated JPEG datastream cannot be produced, because the image defines different
+    /// tables than the given [`JpegTables`][crate::JpegTables].
+    #[error("JPEG image does not use the given tables")]
+    TablesMismatch,
+
+    /// Reading or writing data failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     /// When decompressing, the output image is too small for the input JPEG image.
     #[error("output image is too small for image of size {0}x{1}")]
     OutputTooSmall(i32, i32),
@@ -1,15 +1,50 @@
 use crate::buf::{OutputBuf, OwnedBuf};
-use crate::common::{Error, Result, Subsamp};
+use crate::common::{Colorspace, DensityUnit, Error, PixelFormat, Result, Subsamp};
+use crate::decompress::{yuv_pixels_len, Decompressor};
 use crate::handle::Handle;
-use crate::{ffi, Image, YuvImage};
+#[cfg(feature = "libjpeg")]
+use crate::handle::RowsHandle;
+use crate::{
+    ffi, markers, metrics, xmp, Image, PackedYuvImage, SemiPlanarImage, YuvImage, YuvPlanes,
+};
 use std::convert::TryInto as _;
+use std::io::Write as _;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 /// Compresses ffi pixel data into JPEG.
+///
+/// By default, libjpeg-turbo selects SIMD code for the CPU at runtime, so the output may differ
+/// between machines, see [`set_deterministic()`][Self::set_deterministic].
 #[derive(Debug)]
 #[doc(alias = "tjhandle")]
 pub struct Compressor {
     handle: Handle,
     subsamp: Subsamp,
+    icc_profile: Vec<u8>,
+    markers: Vec<(u8, Vec<u8>)>,
+    xmp: Option<Vec<u8>>,
+    comment: Option<Vec<u8>>,
+    smoothing: u8,
+    auto_subsamp: bool,
+    colorspace: Option<Colorspace>,
+}
+
+/// Perceptual quality target for [`Compressor::compress_to_quality_target()`].
+///
+/// The quality is measured by decompressing the compressed image and comparing it with the
+/// original image.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub enum QualityTarget {
+    /// Minimal peak signal-to-noise ratio (PSNR) in decibels.
+    ///
+    /// Typical values range from 30 dB (visible artifacts) to 45 dB (visually lossless).
+    Psnr(f64),
+
+    /// Minimal structural similarity index (SSIM).
+    ///
+    /// The index ranges up to 1 (identical images); typical values range from 0.9 (visible
+    /// artifacts) to 0.99 (visually lossless).
+    Ssim(f64),
 }
 
 static DEFAULT_QUALITY: i32 = 95;
@@ -30,9 +65,32 @@ impl Compressor {
         Ok(Compressor {
             handle,
             subsamp: DEFAULT_SUBSAMP,
+            icc_profile: Vec::new(),
+            markers: Vec::new(),
+            xmp: None,
+            comment: None,
+            smoothing: 0,
+            auto_subsamp: false,
+            colorspace: None,
         })
     }
 
+    /// Create a [`CompressorBuilder`] to configure a new compressor instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let compressor = turbojpeg::Compressor::builder()
+    ///     .quality(85)
+    ///     .subsamp(turbojpeg::Subsamp::Sub2x2)
+    ///     .progressive(true)
+    ///     .build()?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn builder() -> CompressorBuilder {
+        CompressorBuilder::new()
+    }
+
     /// Set the quality of the compressed JPEG images.
     ///
     /// The quality ranges from 1 (worst) to 100 (best).
@@ -65,14 +123,182 @@ impl Compressor {
     #[doc(alias = "TJPARAM_SUBSAMP")]
     pub fn set_subsamp(&mut self, subsamp: Subsamp) -> Result<()> {
         self.handle
-            .set(ffi::TJPARAM_TJPARAM_SUBSAMP, subsamp as i32 as libc::c_int)
+            .set(ffi::TJPARAM_TJPARAM_SUBSAMP, subsamp as i32 as libc::c_int)?;
+        self.subsamp = subsamp;
+        Ok(())
+    }
+
+    /// Enable/disable automatic selection of chrominance subsampling.
+    ///
+    /// When enabled, [`compress()`][Self::compress] and its wrappers analyze every image with
+    /// [`choose_subsamp()`][crate::choose_subsamp] and compress it with either
+    /// [`Subsamp::None`] (images with sharp color edges, such as screenshots or text) or
+    /// [`Subsamp::Sub2x2`] (photographic content), ignoring the [subsampling][Self::set_subsamp]
+    /// setting. YUV images are already subsampled, so they are not affected. Automatic selection
+    /// is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // red text on white background
+    /// let mut image = turbojpeg::Image {
+    ///     pixels: vec![255; 64 * 64 * 3],
+    ///     width: 64,
+    ///     pitch: 64 * 3,
+    ///     height: 64,
+    ///     format: turbojpeg::PixelFormat::RGB,
+    /// };
+    /// for (i, pixel) in image.pixels.chunks_exact_mut(3).enumerate() {
+    ///     if (i % 64) % 4 == 0 {
+    ///         pixel.copy_from_slice(&[255, 0, 0]);
+    ///     }
+    /// }
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_auto_subsamp(true);
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// assert_eq!(turbojpeg::read_header(&jpeg_data)?.subsamp, turbojpeg::Subsamp::None);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_auto_subsamp(&mut self, auto_subsamp: bool) {
+        self.auto_subsamp = auto_subsamp;
+    }
+
+    /// Set the colorspace of the compressed JPEG images.
+    ///
+    /// By default, the colorspace is selected automatically: [`Colorspace::Gray`] when
+    /// compressing with [`Subsamp::Gray`], [`Colorspace::YCCK`] when compressing
+    /// [`PixelFormat::CMYK`][crate::PixelFormat::CMYK] images and [`Colorspace::YCbCr`]
+    /// otherwise. Use this method to select a different colorspace, for example to store CMYK
+    /// images without any color transform in an Adobe CMYK JPEG image (print workflows often expect
+    /// this). Pass `None` to go back to the automatic selection.
+    ///
+    /// Note that [`Colorspace::CMYK`] and [`Colorspace::YCCK`] can only be used with CMYK images,
+    /// and CMYK images can only be compressed into these two colorspaces.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(300, 200, turbojpeg::PixelFormat::CMYK);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// // CMYK images are compressed into YCCK by default
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!(header.colorspace, turbojpeg::Colorspace::YCCK);
+    ///
+    /// compressor.set_colorspace(Some(turbojpeg::Colorspace::CMYK))?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!(header.colorspace, turbojpeg::Colorspace::CMYK);
+    ///
+    /// // the Adobe marker identifies the colorspace for other decoders
+    /// assert!(jpeg_data.windows(5).any(|w| w == b"Adobe"));
+    ///
+    /// // back to the automatic selection
+    /// compressor.set_colorspace(None)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// assert_eq!(turbojpeg::read_header(&jpeg_data)?.colorspace, turbojpeg::Colorspace::YCCK);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJPARAM_COLORSPACE")]
+    pub fn set_colorspace(&mut self, colorspace: Option<Colorspace>) -> Result<()> {
+        if let Some(colorspace) = colorspace {
+            self.handle
+                .set(ffi::TJPARAM_TJPARAM_COLORSPACE, colorspace as libc::c_int)?;
+        }
+        self.colorspace = colorspace;
+        Ok(())
+    }
+
+    /// Enable/disable deterministic output.
+    ///
+    /// The compressor produces bit-identical JPEG data for the same image and settings on every
+    /// machine (with the same version of libjpeg-turbo) only if libjpeg-turbo was built without
+    /// its SIMD extensions, because otherwise the SIMD code is selected at runtime for the
+    /// extensions that the CPU supports. The SIMD extensions are left out when this crate is built
+    /// without the default `simd` feature (which is several times slower).
+    ///
+    /// Enabling deterministic output only checks this guarantee: it returns
+    /// [`Error::InvalidParam`] if the crate was built with the `simd` feature, and it does not
+    /// change any setting of the compressor. Disabling deterministic output always succeeds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// if cfg!(feature = "simd") {
+    ///     assert!(compressor.set_deterministic(true).is_err());
+    /// } else {
+    ///     compressor.set_deterministic(true)?;
+    /// }
+    /// compressor.set_deterministic(false)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_deterministic(&mut self, deterministic: bool) -> Result<()> {
+        if deterministic && cfg!(feature = "simd") {
+            return Err(Error::InvalidParam("deterministic"));
+        }
+        Ok(())
+    }
+
+    /// Sets the colorspace of the handle for an image in `format` compressed with `subsamp`.
+    ///
+    /// TurboJPEG cannot go back to the automatic selection once a colorspace was set, so the
+    /// automatic selection of TurboJPEG is repeated here.
+    fn apply_colorspace(&mut self, format: PixelFormat, subsamp: Subsamp) -> Result<()> {
+        if self.colorspace.is_some() || self.handle.get(ffi::TJPARAM_TJPARAM_COLORSPACE) == -1 {
+            return Ok(());
+        }
+        let colorspace = if subsamp == Subsamp::Gray {
+            Colorspace::Gray
+        } else if format == PixelFormat::CMYK {
+            Colorspace::YCCK
+        } else {
+            Colorspace::YCbCr
+        };
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_COLORSPACE, colorspace as libc::c_int)
+    }
+
+    /// Enable/disable bottom-up row order of the source image.
+    ///
+    /// By default, the rows of the source image are stored top-down, so the first row in memory
+    /// is the top row of the image. When this is enabled, the rows are stored bottom-up, as in
+    /// OpenGL readbacks or BMP files, so the image does not need to be flipped before
+    /// compression.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(300, 200, turbojpeg::PixelFormat::RGB);
+    ///
+    /// // store the rows of the image in reverse order
+    /// let flipped_pixels: Vec<u8> = image.pixels.chunks(image.pitch).rev().flatten().copied().collect();
+    /// let flipped = turbojpeg::Image { pixels: &flipped_pixels[..], ..image.as_deref() };
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// compressor.set_bottom_up(true)?;
+    /// let bottom_up_jpeg_data = compressor.compress_to_vec(flipped)?;
+    ///
+    /// assert_eq!(jpeg_data, bottom_up_jpeg_data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJPARAM_BOTTOMUP")]
+    pub fn set_bottom_up(&mut self, bottom_up: bool) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_BOTTOMUP, bottom_up as libc::c_int)
     }
 
     /// Enable/disable optimized baseline entropy coding.
     ///
     /// When enabled, optimal Huffman tables will be computed for the JPEG image. Optimized
     /// baseline entropy coding will improve compression slightly (generally 5% or less), but it
-    /// will reduce compression performance considerably.
+    /// will reduce compression performance considerably. This setting is ignored when
+    /// [arithmetic entropy coding][Self::set_arithmetic] is enabled, which does not use Huffman
+    /// tables.
     ///
     /// # Example
     ///
@@ -95,6 +321,418 @@ impl Compressor {
             .set(ffi::TJPARAM_TJPARAM_OPTIMIZE, optimize as libc::c_int)
     }
 
+    /// Enable/disable progressive entropy coding.
+    ///
+    /// In a progressive JPEG image, the DCT coefficients are split across multiple scans of
+    /// increasing quality, so a low-quality version of the image can be displayed before the
+    /// whole image is transmitted. Progressive entropy coding will generally improve compression
+    /// relative to baseline entropy coding (the default), but it will reduce compression and
+    /// decompression performance considerably. Progressive entropy coding implies
+    /// [optimized baseline entropy coding][Self::set_optimize] unless
+    /// [arithmetic entropy coding][Self::set_arithmetic] is also enabled.
+    #[doc(alias = "TJPARAM_PROGRESSIVE")]
+    pub fn set_progressive(&mut self, progressive: bool) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_PROGRESSIVE, progressive as libc::c_int)
+    }
+
+    /// Enable/disable arithmetic entropy coding.
+    ///
+    /// Arithmetic entropy coding will generally improve compression relative to Huffman entropy
+    /// coding (the default), but it will reduce compression and decompression performance
+    /// considerably. Also, some older JPEG decoders do not support arithmetic coding at all.
+    /// [`Decompressor`][crate::Decompressor] always accepts arithmetic-coded JPEG images.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let huffman = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// compressor.set_arithmetic(true)?;
+    /// let arithmetic = compressor.compress_to_vec(image.as_deref())?;
+    /// assert!(arithmetic.len() < huffman.len());
+    ///
+    /// // arithmetic-coded images are decompressed as usual
+    /// let decompressed = turbojpeg::decompress(&arithmetic, turbojpeg::PixelFormat::RGB)?;
+    /// assert_eq!((decompressed.width, decompressed.height), (500, 500));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJPARAM_ARITHMETIC")]
+    pub fn set_arithmetic(&mut self, arithmetic: bool) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_ARITHMETIC, arithmetic as libc::c_int)
+    }
+
+    /// Set the restart marker interval in MCU blocks.
+    ///
+    /// A restart marker stops and restarts the entropy coding, so if a JPEG image is corrupted,
+    /// decompression can resume at the next marker. Restart markers also allow decoders to process
+    /// independent segments of the image in parallel. Adding more restart markers improves the
+    /// fault tolerance of the image, but too many markers will hurt the compression ratio and
+    /// performance.
+    ///
+    /// The default is 0, which emits no restart markers. Setting a non-zero interval resets the
+    /// interval set by [`set_restart_rows()`][Self::set_restart_rows] to 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_restart_blocks(16)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// // the image contains a DRI (define restart interval) marker
+    /// assert!(jpeg_data.windows(2).any(|m| m == [0xff, 0xdd]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJPARAM_RESTARTBLOCKS")]
+    pub fn set_restart_blocks(&mut self, blocks: u16) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_RESTARTBLOCKS, blocks as libc::c_int)
+    }
+
+    /// Set the restart marker interval in MCU rows.
+    ///
+    /// An MCU row is a row of MCU blocks spanning the entire width of the image. See
+    /// [`set_restart_blocks()`][Self::set_restart_blocks] for a description of restart markers.
+    ///
+    /// The default is 0, which emits no restart markers. Setting a non-zero interval resets the
+    /// interval set by [`set_restart_blocks()`][Self::set_restart_blocks] to 0.
+    #[doc(alias = "TJPARAM_RESTARTROWS")]
+    pub fn set_restart_rows(&mut self, rows: u16) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_RESTARTROWS, rows as libc::c_int)
+    }
+
+    /// Set the pixel density stored in the JFIF header of the compressed JPEG images.
+    ///
+    /// The density does not affect the image data, but applications such as printing and
+    /// scanning software use it to determine the physical size of the image. The default density
+    /// is 1x1 in [unknown units][DensityUnit::Unknown], which only describes a square pixel aspect
+    /// ratio.
+    ///
+    /// The density is stored only if the JPEG colorspace is YCbCr or grayscale.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_density(300, 300, turbojpeg::DensityUnit::PixelsPerInch)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// // the JFIF header contains the units followed by the horizontal and vertical density
+    /// let jfif = jpeg_data.windows(5).position(|w| w == b"JFIF\0").unwrap();
+    /// assert_eq!(&jpeg_data[jfif + 7..jfif + 12], &[1, 0x01, 0x2c, 0x01, 0x2c]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJPARAM_XDENSITY")]
+    #[doc(alias = "TJPARAM_YDENSITY")]
+    #[doc(alias = "TJPARAM_DENSITYUNITS")]
+    pub fn set_density(&mut self, x: u16, y: u16, unit: DensityUnit) -> Result<()> {
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_XDENSITY, x as libc::c_int)?;
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_YDENSITY, y as libc::c_int)?;
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_DENSITYUNITS, unit as libc::c_int)
+    }
+
+    /// Embed an ICC color management profile in the compressed JPEG images.
+    ///
+    /// The profile is stored in APP2 markers of every image produced by this compressor, so that
+    /// color-managed applications can correctly display wide-gamut images. TurboJPEG makes a copy
+    /// of `icc_profile`. Pass an empty slice to remove a previously set profile.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let icc_profile = vec![0u8; 128];
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_icc_profile(&icc_profile)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// // the profile is stored in an APP2 marker with the "ICC_PROFILE" identifier
+    /// assert!(jpeg_data.windows(12).any(|w| w == b"ICC_PROFILE\0"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3SetICCProfile")]
+    pub fn set_icc_profile(&mut self, icc_profile: &[u8]) -> Result<()> {
+        self.handle.set_icc_profile(icc_profile)?;
+        self.icc_profile = icc_profile.to_vec();
+        Ok(())
+    }
+
+    /// Add a custom application segment (APPn marker) to the compressed JPEG images.
+    ///
+    /// The segment with marker APP`n` and payload `data` is stored in every image produced by this
+    /// compressor, which is useful for metadata such as EXIF (APP1) or XMP (APP1) packets. The
+    /// segments are stored in the order in which they were added, after the JFIF (APP0) segment
+    /// and before the ICC profile (see [`set_icc_profile()`][Self::set_icc_profile]).
+    ///
+    /// Returns an error if `n` is larger than 15 or if `data` is longer than 65533 bytes (the
+    /// maximal payload of a JPEG segment).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.add_app_marker(1, b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0")?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// // the APP1 segment follows the SOI marker and the JFIF segment
+    /// assert_eq!(&jpeg_data[20..22], &[0xff, 0xe1]);
+    /// assert_eq!(&jpeg_data[24..30], b"Exif\0\0");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn add_app_marker(&mut self, n: u8, data: &[u8]) -> Result<()> {
+        if n > 15 {
+            return Err(Error::InvalidParam("n"));
+        }
+        self.add_marker(markers::APP0 + n, data)
+    }
+
+    /// Remove all segments added by [`add_app_marker()`][Self::add_app_marker].
+    pub fn clear_markers(&mut self) {
+        self.markers.clear();
+    }
+
+    /// Set the XMP packet of the compressed JPEG images.
+    ///
+    /// The packet is stored in an APP1 segment (with the `http://ns.adobe.com/xap/1.0/`
+    /// namespace) in every image produced by this compressor, after the application segments
+    /// added by [`add_app_marker()`][Self::add_app_marker] (so that it follows the EXIF data).
+    /// Pass an empty slice to remove a previously set packet. The packet can be read using
+    /// [`read_xmp()`][crate::read_xmp].
+    ///
+    /// Returns an error if `xmp` is longer than 65504 bytes (extended XMP is not supported).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_xmp(xmp)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// // the APP1 segment follows the SOI marker and the JFIF segment
+    /// assert_eq!(&jpeg_data[20..22], &[0xff, 0xe1]);
+    /// assert_eq!(turbojpeg::read_xmp(&jpeg_data)?.as_deref(), Some(&xmp[..]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_xmp(&mut self, xmp: &[u8]) -> Result<()> {
+        self.xmp = if xmp.is_empty() {
+            None
+        } else {
+            Some(xmp::xmp_payload(xmp)?)
+        };
+        Ok(())
+    }
+
+    /// Set the comment (COM segment) of the compressed JPEG images.
+    ///
+    /// The comment is stored in every image produced by this compressor, after the application
+    /// segments added by [`add_app_marker()`][Self::add_app_marker]. Pass an empty string to
+    /// remove a previously set comment. Comments can be read using
+    /// [`Decompressor::comments()`][crate::Decompressor::comments] (after enabling
+    /// [`Decompressor::set_save_comments()`][crate::Decompressor::set_save_comments]).
+    ///
+    /// Returns an error if `comment` is longer than 65533 bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_comment("archive id 1234")?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// let mut decompressor = turbojpeg::Decompressor::new()?;
+    /// decompressor.set_save_comments(true);
+    /// decompressor.read_header(&jpeg_data)?;
+    /// assert_eq!(decompressor.comments(), ["archive id 1234"]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_comment(&mut self, comment: &str) -> Result<()> {
+        if comment.len() > markers::MAX_PAYLOAD_LEN {
+            return Err(Error::InvalidParam("comment"));
+        }
+        self.comment = if comment.is_empty() {
+            None
+        } else {
+            Some(comment.as_bytes().to_vec())
+        };
+        Ok(())
+    }
+
+    fn add_marker(&mut self, marker: u8, data: &[u8]) -> Result<()> {
+        if data.len() > markers::MAX_PAYLOAD_LEN {
+            return Err(Error::InvalidParam("data"));
+        }
+        self.markers.push((marker, data.to_vec()));
+        Ok(())
+    }
+
+    /// Compresses into a scratch owned buffer with `compress`, then copies the result into the
+    /// borrowed `output`.
+    ///
+    /// This is used when `output` is smaller than the maximal size of the compressed image: with
+    /// NOREALLOC, TurboJPEG assumes that the buffer holds that many bytes, so it must not write
+    /// into `output` directly. An `output` created by [`OutputBuf::vec()`] is grown as needed.
+    fn compress_via_owned(
+        &mut self,
+        output: &mut OutputBuf,
+        compress: impl FnOnce(&mut Compressor, &mut OutputBuf) -> Result<()>,
+    ) -> Result<()> {
+        let mut buf = OutputBuf::new_owned();
+        compress(self, &mut buf)?;
+        if output.vec.is_none() && buf.len() > output.capacity() {
+            return Err(Error::OutputBufTooSmall(buf.len()));
+        }
+        output.clear();
+        output.grow_vec(buf.len());
+        output.write_data(&buf);
+        Ok(())
+    }
+
+    /// Inserts the custom segments into the compressed image in `output`.
+    fn write_markers(&self, output: &mut OutputBuf) -> Result<()> {
+        if self.markers.is_empty() && self.xmp.is_none() && self.comment.is_none() {
+            return Ok(());
+        }
+
+        // insert the segments after the JFIF segment (if any)
+        let mut insert_at = 2;
+        for segment in markers::segments(output) {
+            let segment = segment?;
+            if segment.marker == markers::APP0 && segment.payload.starts_with(b"JFIF\0") {
+                insert_at = segment.offset + segment.len;
+            } else if segment.marker != markers::SOI {
+                break;
+            }
+        }
+
+        let mut segments = Vec::new();
+        for (marker, data) in self.markers.iter() {
+            markers::write_segment(&mut segments, *marker, data);
+        }
+        if let Some(ref xmp) = self.xmp {
+            markers::write_segment(&mut segments, markers::APP0 + 1, xmp);
+        }
+        if let Some(ref comment) = self.comment {
+            markers::write_segment(&mut segments, markers::COM, comment);
+        }
+        let len = output.len + segments.len();
+
+        output.grow_vec(len);
+        let capacity = output.capacity();
+        if output.is_owned && len > capacity {
+            let mut buf = output.allocate_like(len);
+            buf[..insert_at].copy_from_slice(&output[..insert_at]);
+            buf[insert_at..][..segments.len()].copy_from_slice(&segments);
+            buf[insert_at + segments.len()..].copy_from_slice(&output[insert_at..]);
+            output.set_owned(buf);
+        } else {
+            if len > capacity {
+                return Err(Error::OutputBufTooSmall(len));
+            }
+            output.insert_data(insert_at, &segments);
+        }
+        Ok(())
+    }
+
+    /// Set the input smoothing factor.
+    ///
+    /// The smoothing factor ranges from 0 (no smoothing, the default) to 100 (maximal smoothing).
+    /// When enabled, libjpeg blends each sample of every component with its eight neighbors after
+    /// the color conversion (the `smoothing_factor` of libjpeg, or `cjpeg -smooth`). Light
+    /// smoothing of noisy inputs, such as scanned or dithered images, can reduce the compressed
+    /// size dramatically.
+    ///
+    /// TurboJPEG does not expose this setting, so this method requires the `libjpeg` feature. With
+    /// smoothing, [`compress()`][Self::compress] and its wrappers compress the image like
+    /// [`compress_rows()`][Self::compress_rows] (the output is copied once from the libjpeg
+    /// destination buffer). YUV images and images with 12 or 16 bits per sample cannot be
+    /// smoothed, compressing them returns [`Error::InvalidParam`] while smoothing is enabled. An
+    /// error is returned if `factor` is larger than 100.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// // add some noise to the image
+    /// for (i, value) in image.pixels.iter_mut().enumerate() {
+    ///     *value = value.saturating_add((i * 7919 % 31) as u8);
+    /// }
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let noisy = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// compressor.set_smoothing(30)?;
+    /// let smoothed = compressor.compress_to_vec(image.as_deref())?;
+    /// assert!(smoothed.len() < noisy.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "libjpeg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "libjpeg")))]
+    pub fn set_smoothing(&mut self, factor: u8) -> Result<()> {
+        if factor > 100 {
+            return Err(Error::InvalidParam("smoothing"));
+        }
+        self.smoothing = factor;
+        Ok(())
+    }
+
+    /// Apply all compression parameters from a [`CompressParams`] snapshot.
+    ///
+    /// The parameters are [validated][CompressParams::validate] before any of them is applied.
+    pub fn set_params(&mut self, params: &CompressParams) -> Result<()> {
+        params.validate()?;
+        self.set_quality(params.quality)?;
+        self.set_subsamp(params.subsamp)?;
+        self.set_optimize(params.optimize)?;
+        self.set_progressive(params.progressive)?;
+        self.set_arithmetic(params.arithmetic)?;
+        self.set_restart_blocks(params.restart_blocks)?;
+        self.set_restart_rows(params.restart_rows)?;
+        self.set_density(params.x_density, params.y_density, params.density_unit)?;
+        #[cfg(feature = "libjpeg")]
+        self.set_smoothing(params.smoothing)?;
+        self.set_auto_subsamp(params.auto_subsamp);
+        Ok(())
+    }
+
+    /// Get a [`CompressParams`] snapshot of the current compression parameters.
+    pub fn params(&self) -> Result<CompressParams> {
+        let get = |param| self.handle.get(param);
+        let density_unit = match get(ffi::TJPARAM_TJPARAM_DENSITYUNITS) {
+            1 => DensityUnit::PixelsPerInch,
+            2 => DensityUnit::PixelsPerCm,
+            _ => DensityUnit::Unknown,
+        };
+        Ok(CompressParams {
+            quality: get(ffi::TJPARAM_TJPARAM_QUALITY),
+            subsamp: self.subsamp,
+            optimize: get(ffi::TJPARAM_TJPARAM_OPTIMIZE) != 0,
+            progressive: get(ffi::TJPARAM_TJPARAM_PROGRESSIVE) != 0,
+            arithmetic: get(ffi::TJPARAM_TJPARAM_ARITHMETIC) != 0,
+            restart_blocks: get(ffi::TJPARAM_TJPARAM_RESTARTBLOCKS) as u16,
+            restart_rows: get(ffi::TJPARAM_TJPARAM_RESTARTROWS) as u16,
+            x_density: get(ffi::TJPARAM_TJPARAM_XDENSITY) as u16,
+            y_density: get(ffi::TJPARAM_TJPARAM_YDENSITY) as u16,
+            density_unit,
+            smoothing: self.smoothing,
+            auto_subsamp: self.auto_subsamp,
+        })
+    }
+
     /// Compresses the `image` into `output` buffer.
     ///
     /// This is the main compression method, which gives you full control of the output buffer. If
@@ -123,10 +761,48 @@ impl Compressor {
     ///
     /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
+    ///
+    /// # Grayscale images
+    ///
+    /// Images in [`PixelFormat::GRAY`] are always compressed into single-component grayscale JPEG
+    /// images, regardless of the [chrominance subsampling][Self::set_subsamp] setting of the
+    /// compressor. An error is returned if a color [colorspace][Self::set_colorspace] is selected
+    /// for a grayscale image:
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(300, 200, turbojpeg::PixelFormat::GRAY);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// assert_eq!(turbojpeg::read_header(&jpeg_data)?.subsamp, turbojpeg::Subsamp::Gray);
+    ///
+    /// compressor.set_colorspace(Some(turbojpeg::Colorspace::YCbCr))?;
+    /// assert!(compressor.compress_to_vec(image.as_deref()).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     #[doc(alias = "tj3Compress8")]
     pub fn compress(&mut self, image: Image<&[u8]>, output: &mut OutputBuf) -> Result<()> {
         image.assert_valid(image.pixels.len());
 
+        let subsamp = self.image_subsamp(image)?;
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_SUBSAMP, subsamp as i32 as libc::c_int)?;
+        self.apply_colorspace(image.format, subsamp)?;
+
+        #[cfg(feature = "libjpeg")]
+        if self.smoothing != 0 {
+            // TurboJPEG does not expose the smoothing of libjpeg, so the image is compressed
+            // through the libjpeg API, which reads the rows from top to bottom
+            let rows = self.image_rows(image);
+            return self.compress_rows_into(
+                image.width,
+                image.height,
+                image.format,
+                subsamp,
+                rows,
+                output,
+            );
+        }
+
         let Image {
             pixels,
             width,
@@ -144,11 +820,16 @@ impl Compressor {
             .try_into()
             .map_err(|_| Error::IntegerOverflow("height"))?;
 
-        self.handle.set(
-            ffi::TJPARAM_TJPARAM_NOREALLOC,
-            if output.is_owned { 0 } else { 1 } as libc::c_int,
-        )?;
-        let mut output_len = output.len as ffi::size_t;
+        let max_len = self.max_buf_len(image.width, image.height, 8)?;
+        if output.is_too_small(max_len) {
+            return self
+                .compress_via_owned(output, |compressor, buf| compressor.compress(image, buf));
+        }
+        output.prepare(max_len);
+        let no_realloc = output.no_realloc();
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_NOREALLOC, no_realloc as libc::c_int)?;
+        let mut output_len = output.capacity() as ffi::size_t;
         let res = unsafe {
             ffi::tj3Compress8(
                 self.handle.as_ptr(),
@@ -161,15 +842,14 @@ impl Compressor {
                 &mut output_len,
             )
         };
-        output.len = output_len as usize;
         if res != 0 {
             return Err(self.handle.get_error());
         } else if output.ptr.is_null() {
             output.len = 0;
             return Err(Error::Null);
         }
-
-        Ok(())
+        output.set_written(output_len as usize);
+        self.write_markers(output)
     }
 
     /// Compresses the `image` into an owned buffer.
@@ -189,20 +869,770 @@ impl Compressor {
     pub fn compress_to_vec(&mut self, image: Image<&[u8]>) -> Result<Vec<u8>> {
         let mut buf = OutputBuf::new_owned();
         self.compress(image, &mut buf)?;
-        Ok(buf.to_vec())
+        Ok(buf.into_owned().into_vec())
     }
 
     /// Compress the `image` into the slice `output`.
     ///
     /// Returns the size of the compressed JPEG data. If the compressed image does not fit into
-    /// `dest`, this method returns an error. Use [`buf_len()`](Compressor::buf_len) to determine
-    /// buffer size that is guaranteed to be large enough for the compressed image.
+    /// `output`, this method returns [`Error::OutputBufTooSmall`] with the number of bytes that
+    /// are required, so that you can grow the buffer and try again. Use
+    /// [`max_buf_len()`](Compressor::max_buf_len) to determine buffer size that is guaranteed to
+    /// be large enough for the compressed image. A smaller `output` is supported, but the image
+    /// is then compressed into a temporary buffer and copied into `output`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(300, 200, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let mut output = vec![0; 1000];
+    /// let len = match compressor.compress_to_slice(image.as_deref(), &mut output) {
+    ///     Err(turbojpeg::Error::OutputBufTooSmall(required_len)) => {
+    ///         output.resize(required_len, 0);
+    ///         compressor.compress_to_slice(image.as_deref(), &mut output)?
+    ///     }
+    ///     res => res?,
+    /// };
+    /// let header = turbojpeg::read_header(&output[..len])?;
+    /// assert_eq!((header.width, header.height), (300, 200));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn compress_to_slice(&mut self, image: Image<&[u8]>, output: &mut [u8]) -> Result<usize> {
         let mut buf = OutputBuf::borrowed(output);
         self.compress(image, &mut buf)?;
         Ok(buf.len())
     }
 
+    /// Compresses the 12-bit `image` into a JPEG image with 12-bit precision.
+    ///
+    /// Every sample of the image is stored in one `u16` and must be smaller than 4096, otherwise
+    /// [`Error::InvalidParam`] is returned. The [pitch][Image::pitch] of the image is measured in
+    /// samples, not in bytes. Apart from that, this works like [`compress()`][Self::compress].
+    /// The image can be decompressed with [`Decompressor::decompress_12bit()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut image12 = turbojpeg::Image::alloc16(64, 48, turbojpeg::PixelFormat::RGB);
+    /// for (sample12, &sample) in image12.pixels.iter_mut().zip(&image.pixels) {
+    ///     *sample12 = (sample as u16) << 4;
+    /// }
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let mut jpeg_data = turbojpeg::OutputBuf::new_owned();
+    /// compressor.compress_12bit(image12.as_deref(), &mut jpeg_data)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3Compress12")]
+    pub fn compress_12bit(&mut self, image: Image<&[u16]>, output: &mut OutputBuf) -> Result<()> {
+        image.assert_valid(image.pixels.len());
+        let row_len = image.width * image.format.size();
+        let rows = image.pixels.chunks(image.pitch.max(1)).take(image.height);
+        if rows
+            .flat_map(|row| &row[..row_len])
+            .any(|&sample| sample >= 4096)
+        {
+            return Err(Error::InvalidParam("pixels"));
+        }
+        self.compress_samples(image, output, 12)
+    }
+
+    /// Compresses the 16-bit `image` into a lossless JPEG image with 16-bit precision.
+    ///
+    /// 16-bit precision is only supported by the lossless JPEG process, so the quality, the
+    /// chrominance subsampling and the entropy coding settings of the compressor are ignored.
+    /// The [pitch][Image::pitch] of the image is measured in samples, not in bytes. The image can
+    /// be decompressed with [`Decompressor::decompress_16bit()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::alloc16(256, 256, turbojpeg::PixelFormat::GRAY);
+    /// for (i, sample) in image.pixels.iter_mut().enumerate() {
+    ///     *sample = i as u16;
+    /// }
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let mut jpeg_data = turbojpeg::OutputBuf::new_owned();
+    /// compressor.compress_16bit(image.as_deref(), &mut jpeg_data)?;
+    ///
+    /// // the compression is lossless
+    /// let mut output = turbojpeg::Image::alloc16(256, 256, turbojpeg::PixelFormat::GRAY);
+    /// turbojpeg::Decompressor::new()?.decompress_16bit(&jpeg_data, output.as_deref_mut())?;
+    /// assert_eq!(output.pixels, image.pixels);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3Compress16")]
+    pub fn compress_16bit(&mut self, image: Image<&[u16]>, output: &mut OutputBuf) -> Result<()> {
+        image.assert_valid(image.pixels.len());
+        self.handle.set(ffi::TJPARAM_TJPARAM_LOSSLESS, 1)?;
+        let res = self.compress_samples(image, output, 16);
+        self.handle.set(ffi::TJPARAM_TJPARAM_LOSSLESS, 0)?;
+        res
+    }
+
+    /// Compresses an image with 12-bit or 16-bit samples (`precision`).
+    fn compress_samples(
+        &mut self,
+        image: Image<&[u16]>,
+        output: &mut OutputBuf,
+        precision: u8,
+    ) -> Result<()> {
+        if self.smoothing != 0 {
+            return Err(Error::InvalidParam("smoothing"));
+        }
+        let subsamp = if image.format == PixelFormat::GRAY {
+            Subsamp::Gray
+        } else {
+            self.subsamp
+        };
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_SUBSAMP, subsamp as i32 as libc::c_int)?;
+        self.apply_colorspace(image.format, subsamp)?;
+
+        let Image {
+            pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = image;
+        let width = width
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("width"))?;
+        let pitch = pitch
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("pitch"))?;
+        let height = height
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("height"))?;
+
+        let max_len = self.max_buf_len(image.width, image.height, precision)?;
+        // TurboJPEG bounds the output with NOREALLOC by the size of an 8-bit image, which the
+        // data of 12-bit and 16-bit images may exceed, so they are compressed into a buffer that
+        // TurboJPEG can grow
+        if output.is_too_small(max_len) || (!output.is_owned && precision > 8) {
+            return self.compress_via_owned(output, |compressor, buf| {
+                compressor.compress_samples(image, buf, precision)
+            });
+        }
+        output.prepare(max_len);
+        let no_realloc = output.no_realloc();
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_NOREALLOC, no_realloc as libc::c_int)?;
+        let mut output_len = output.capacity() as ffi::size_t;
+        let res = unsafe {
+            if precision == 12 {
+                ffi::tj3Compress12(
+                    self.handle.as_ptr(),
+                    pixels.as_ptr() as *const libc::c_short,
+                    width,
+                    pitch,
+                    height,
+                    format as libc::c_int,
+                    &mut output.ptr,
+                    &mut output_len,
+                )
+            } else {
+                ffi::tj3Compress16(
+                    self.handle.as_ptr(),
+                    pixels.as_ptr(),
+                    width,
+                    pitch,
+                    height,
+                    format as libc::c_int,
+                    &mut output.ptr,
+                    &mut output_len,
+                )
+            }
+        };
+        if res != 0 {
+            return Err(self.handle.get_error());
+        } else if output.ptr.is_null() {
+            output.len = 0;
+            return Err(Error::Null);
+        }
+        output.set_written(output_len as usize);
+        self.write_markers(output)
+    }
+
+    /// Compress the `image` and write the JPEG data into `writer`.
+    ///
+    /// Returns the number of bytes written. Writing errors are returned as [`Error::Io`].
+    ///
+    /// With the `libjpeg` feature, the image is compressed through the incremental libjpeg API
+    /// (as by [`compress_rows()`][Self::compress_rows]), and the JPEG data are written into
+    /// `writer` in chunks of 64 KiB while the image is compressed, so the compressed image is
+    /// never held in memory as a whole. The output is the same as the output of
+    /// [`compress()`][Self::compress], except that the custom segments are written after the
+    /// Adobe segment of CMYK and YCCK images instead of before it.
+    ///
+    /// Without the `libjpeg` feature, this method does **not** stream: TurboJPEG cannot produce
+    /// its output incrementally, so the whole image is compressed into a temporary buffer, which
+    /// is written into `writer` and freed before this method returns. The peak memory use is then
+    /// the same as with [`compress_to_owned()`][Self::compress_to_owned].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    ///
+    /// let path = std::env::temp_dir().join("tj_compress_to_writer.jpg");
+    /// let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+    /// let written = compressor.compress_to_writer(image.as_deref(), &mut file)?;
+    /// drop(file);
+    ///
+    /// assert_eq!(std::fs::metadata(&path)?.len(), written as u64);
+    ///
+    /// // the output is the same as the output of `compress()`
+    /// compressor.set_comment("mandelbrot")?;
+    /// let mut jpeg_data = Vec::new();
+    /// compressor.compress_to_writer(image.as_deref(), &mut jpeg_data)?;
+    /// assert_eq!(jpeg_data, compressor.compress_to_vec(image.as_deref())?);
+    ///
+    /// // writing errors are returned
+    /// let mut short = [0; 1000];
+    /// let res = compressor.compress_to_writer(image.as_deref(), &mut short[..]);
+    /// assert!(matches!(res, Err(turbojpeg::Error::Io(_))));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_to_writer<W: std::io::Write>(
+        &mut self,
+        image: Image<&[u8]>,
+        mut writer: W,
+    ) -> Result<usize> {
+        #[cfg(feature = "libjpeg")]
+        {
+            image.assert_valid(image.pixels.len());
+            let subsamp = self.image_subsamp(image)?;
+            let markers = self
+                .markers
+                .iter()
+                .map(|(marker, data)| (*marker, data))
+                .chain(self.xmp.iter().map(|xmp| (markers::APP0 + 1, xmp)))
+                .chain(self.comment.iter().map(|comment| (markers::COM, comment)))
+                .map(|(marker, data)| ffi::tjrs_marker {
+                    marker: marker as libc::c_int,
+                    data: data.as_ptr(),
+                    len: data.len() as ffi::size_t,
+                })
+                .collect::<Vec<_>>();
+            let params =
+                self.rows_params(image.width, image.height, image.format, subsamp, &markers)?;
+
+            let mut sink = WriterSink {
+                writer: &mut writer,
+                written: 0,
+                error: None,
+                panic: None,
+            };
+            let mut handle = RowsHandle::new()?;
+            unsafe {
+                handle.set_writer(
+                    Some(write_callback),
+                    &mut sink as *mut WriterSink as *mut libc::c_void,
+                );
+            }
+            let res = handle
+                .start(&params)
+                .and_then(|()| {
+                    let rows = self.image_rows(image);
+                    write_rows(&mut handle, image.width, image.height, image.format, rows)
+                })
+                .and_then(|()| handle.finish().map(|_| ()));
+            drop(handle);
+
+            if let Some(panic) = sink.panic {
+                std::panic::resume_unwind(panic);
+            } else if let Some(err) = sink.error {
+                return Err(Error::Io(err));
+            }
+            res?;
+            Ok(sink.written)
+        }
+
+        #[cfg(not(feature = "libjpeg"))]
+        {
+            let mut buf = OutputBuf::new_owned();
+            self.compress(image, &mut buf)?;
+            writer.write_all(&buf)?;
+            Ok(buf.len())
+        }
+    }
+
+    /// Compress an image whose rows are produced by an iterator.
+    ///
+    /// The `rows` iterator must yield exactly `height` rows, from top to bottom, and every row
+    /// must contain at least `width` pixels in the given `format` (longer rows are truncated).
+    /// The rows are compressed one at a time as they are produced, using the incremental libjpeg
+    /// API underneath, so the whole frame never needs to be in memory. This is useful for images
+    /// that are generated on the fly (such as renders or stitched panoramas).
+    ///
+    /// The current settings of the compressor are used, except that the rows are always given
+    /// from top to bottom (see [`set_bottom_up()`][Self::set_bottom_up]), and that automatic
+    /// subsampling (see [`set_auto_subsamp()`][Self::set_auto_subsamp]), which needs to
+    /// analyze the whole image, falls back to the subsampling set by
+    /// [`set_subsamp()`][Self::set_subsamp].
+    ///
+    /// Returns [`Error::InvalidParam`] if a row is too short or if the iterator yields a
+    /// different number of rows than `height`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let (width, height) = (300, 200);
+    /// let rows = (0..height).map(|y| {
+    ///     (0..width).flat_map(|x| [(x % 256) as u8, (y % 256) as u8, 128]).collect::<Vec<u8>>()
+    /// });
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_subsamp(turbojpeg::Subsamp::Sub2x2)?;
+    /// let jpeg_data =
+    ///     compressor.compress_rows(width, height, turbojpeg::PixelFormat::RGB, rows)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (300, 200));
+    ///
+    /// // the output is the same as if the whole image was compressed at once
+    /// let image = turbojpeg::Image {
+    ///     pixels: (0..height)
+    ///         .flat_map(|y| (0..width).flat_map(move |x| [(x % 256) as u8, (y % 256) as u8, 128]))
+    ///         .collect::<Vec<u8>>(),
+    ///     width,
+    ///     pitch: 3 * width,
+    ///     height,
+    ///     format: turbojpeg::PixelFormat::RGB,
+    /// };
+    /// assert_eq!(&jpeg_data[..], &compressor.compress_to_vec(image.as_deref())?[..]);
+    ///
+    /// // and the rows are smoothed in the same way
+    /// compressor.set_smoothing(30)?;
+    /// let rows = image.pixels.chunks(image.pitch);
+    /// let smoothed = compressor.compress_rows(width, height, turbojpeg::PixelFormat::RGB, rows)?;
+    /// assert_ne!(&smoothed[..], &jpeg_data[..]);
+    /// assert_eq!(&smoothed[..], &compressor.compress_to_vec(image.as_deref())?[..]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "jpeg_write_scanlines")]
+    #[cfg(feature = "libjpeg")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "libjpeg")))]
+    pub fn compress_rows<I>(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        rows: I,
+    ) -> Result<OwnedBuf>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let subsamp = if format == PixelFormat::GRAY {
+            if self
+                .colorspace
+                .is_some_and(|colorspace| colorspace != Colorspace::Gray)
+            {
+                return Err(Error::IncompatibleParams("pixel format", "colorspace"));
+            }
+            Subsamp::Gray
+        } else {
+            self.subsamp
+        };
+        let mut output = OutputBuf::new_owned();
+        self.compress_rows_into(width, height, format, subsamp, rows, &mut output)?;
+        Ok(output.into_owned())
+    }
+
+    /// Compresses the `rows` with the incremental libjpeg API into `output`.
+    #[cfg(feature = "libjpeg")]
+    fn compress_rows_into<I>(
+        &mut self,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        subsamp: Subsamp,
+        rows: I,
+        output: &mut OutputBuf,
+    ) -> Result<()>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<[u8]>,
+    {
+        let params = self.rows_params(width, height, format, subsamp, &[])?;
+        let mut handle = RowsHandle::new()?;
+        handle.start(&params)?;
+        write_rows(&mut handle, width, height, format, rows)?;
+
+        let data = handle.finish()?;
+        output.clear();
+        output.grow_vec(data.len());
+        if data.len() > output.capacity() {
+            if !output.is_owned {
+                return Err(Error::OutputBufTooSmall(data.len()));
+            }
+            output.set_owned(output.allocate_like(data.len()));
+        }
+        output.write_data(data);
+        self.write_markers(output)
+    }
+
+    /// Returns the parameters of the incremental libjpeg API for the current settings, with the
+    /// given `markers` written before the ICC profile.
+    #[cfg(feature = "libjpeg")]
+    fn rows_params(
+        &self,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+        subsamp: Subsamp,
+        markers: &[ffi::tjrs_marker],
+    ) -> Result<ffi::tjrs_rows_params> {
+        let get = |param| self.handle.get(param);
+        Ok(ffi::tjrs_rows_params {
+            width: width
+                .try_into()
+                .map_err(|_| Error::IntegerOverflow("width"))?,
+            height: height
+                .try_into()
+                .map_err(|_| Error::IntegerOverflow("height"))?,
+            pixel_format: format as libc::c_int,
+            quality: get(ffi::TJPARAM_TJPARAM_QUALITY),
+            subsamp: subsamp as i32 as libc::c_int,
+            colorspace: self
+                .colorspace
+                .map_or(-1, |colorspace| colorspace as libc::c_int),
+            optimize: get(ffi::TJPARAM_TJPARAM_OPTIMIZE),
+            progressive: get(ffi::TJPARAM_TJPARAM_PROGRESSIVE),
+            arithmetic: get(ffi::TJPARAM_TJPARAM_ARITHMETIC),
+            restart_blocks: get(ffi::TJPARAM_TJPARAM_RESTARTBLOCKS),
+            restart_rows: get(ffi::TJPARAM_TJPARAM_RESTARTROWS),
+            x_density: get(ffi::TJPARAM_TJPARAM_XDENSITY),
+            y_density: get(ffi::TJPARAM_TJPARAM_YDENSITY),
+            density_unit: get(ffi::TJPARAM_TJPARAM_DENSITYUNITS),
+            smoothing: self.smoothing as libc::c_int,
+            markers: markers.as_ptr(),
+            num_markers: markers.len() as ffi::size_t,
+            icc_profile: self.icc_profile.as_ptr(),
+            icc_profile_len: self.icc_profile.len() as ffi::size_t,
+            wipe: cfg!(feature = "zeroize") as libc::c_int,
+        })
+    }
+
+    /// Returns the rows of `image` from top to bottom, honoring
+    /// [`set_bottom_up()`][Self::set_bottom_up].
+    #[cfg(feature = "libjpeg")]
+    fn image_rows<'a>(&self, image: Image<&'a [u8]>) -> impl Iterator<Item = &'a [u8]> {
+        let bottom_up = self.handle.get(ffi::TJPARAM_TJPARAM_BOTTOMUP) == 1;
+        let Image {
+            pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = image;
+        (0..height).map(move |y| {
+            let y = if bottom_up { height - 1 - y } else { y };
+            &pixels[y * pitch..][..width * format.size()]
+        })
+    }
+
+    /// Returns the chrominance subsampling used to compress `image`.
+    fn image_subsamp(&self, image: Image<&[u8]>) -> Result<Subsamp> {
+        Ok(if image.format == PixelFormat::GRAY {
+            if self
+                .colorspace
+                .is_some_and(|colorspace| colorspace != Colorspace::Gray)
+            {
+                return Err(Error::IncompatibleParams("pixel format", "colorspace"));
+            }
+            Subsamp::Gray
+        } else if self.auto_subsamp {
+            choose_subsamp(image)
+        } else {
+            self.subsamp
+        })
+    }
+
+    /// Compress the `image` as a grid of independent JPEG tiles.
+    ///
+    /// The image is split into tiles of `tile_width` x `tile_height` pixels (the tiles in the last
+    /// column and row may be smaller), and every tile is compressed into a separate JPEG image
+    /// with the current settings of the compressor. The tiles are borrowed from `image` using
+    /// [`Image::region()`], so no pixels are copied. To compress the tiles in parallel, see
+    /// `compress_tiles_parallel()` (requires the `rayon` feature).
+    ///
+    /// If the rows of `image` are [stored bottom-up][Self::set_bottom_up], the tiles still follow
+    /// the orientation of the image: the tile in row 0 is at the top of the image, which is the
+    /// end of the buffer.
+    ///
+    /// Returns an error if `tile_width` or `tile_height` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(1000, 600, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let grid = compressor.compress_tiles(image.as_deref(), 256, 256)?;
+    ///
+    /// assert_eq!((grid.columns, grid.rows), (4, 3));
+    /// let tile = grid.tile(3, 2);
+    /// assert_eq!((tile.x, tile.y, tile.width, tile.height), (768, 512, 232, 88));
+    /// let header = turbojpeg::read_header(&tile.data)?;
+    /// assert_eq!((header.width, header.height), (232, 88));
+    ///
+    /// // the same tiles are produced from bottom-up rows
+    /// let mut flipped = image.clone();
+    /// flipped.vflip();
+    /// compressor.set_bottom_up(true)?;
+    /// let bottom_up = compressor.compress_tiles(flipped.as_deref(), 256, 256)?;
+    /// for (tile, bottom_up_tile) in grid.tiles.iter().zip(&bottom_up.tiles) {
+    ///     assert_eq!(&*tile.data, &*bottom_up_tile.data);
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_tiles(
+        &mut self,
+        image: Image<&[u8]>,
+        tile_width: usize,
+        tile_height: usize,
+    ) -> Result<TileGrid> {
+        let layout = TileLayout::new(image.width, image.height, tile_width, tile_height)?;
+        let bottom_up = self.handle.get(ffi::TJPARAM_TJPARAM_BOTTOMUP) == 1;
+        let tiles = layout
+            .tiles()
+            .map(|tile| self.compress_to_owned(tile.region(image, bottom_up)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(layout.into_grid(tiles))
+    }
+
+    /// Compress the `image` with both optimized baseline and progressive entropy coding and return
+    /// the smaller result.
+    ///
+    /// Progressive JPEG images are usually smaller than optimized baseline images, but not always
+    /// (especially for small images), so this method performs both trial compressions with the
+    /// same handle and keeps the smaller one. All other settings of the compressor are used as
+    /// usual, and the [optimize][Self::set_optimize], [progressive][Self::set_progressive] and
+    /// [arithmetic][Self::set_arithmetic] settings are restored when this method returns.
+    ///
+    /// Arithmetic entropy coding is only tried if it is enabled with
+    /// [`set_arithmetic()`][Self::set_arithmetic], because some JPEG decoders (including most web
+    /// browsers) cannot decode arithmetic-coded images. In that case, the image is additionally
+    /// compressed with sequential and progressive arithmetic coding, and the smallest of all four
+    /// results is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let smallest = compressor.compress_smallest(image.as_deref())?;
+    ///
+    /// compressor.set_optimize(true)?;
+    /// let optimized = compressor.compress_to_vec(image.as_deref())?;
+    /// assert!(smallest.len() <= optimized.len());
+    ///
+    /// // with arithmetic coding enabled, the arithmetic-coded candidates are tried as well
+    /// compressor.set_arithmetic(true)?;
+    /// let arithmetic = compressor.compress_to_vec(image.as_deref())?;
+    /// let smallest = compressor.compress_smallest(image.as_deref())?;
+    /// assert!(smallest.len() <= arithmetic.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_smallest(&mut self, image: Image<&[u8]>) -> Result<OwnedBuf> {
+        let orig_optimize = self.handle.get(ffi::TJPARAM_TJPARAM_OPTIMIZE);
+        let orig_progressive = self.handle.get(ffi::TJPARAM_TJPARAM_PROGRESSIVE);
+        let orig_arithmetic = self.handle.get(ffi::TJPARAM_TJPARAM_ARITHMETIC);
+        let res = self.compress_all_codings(image, orig_arithmetic != 0);
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_OPTIMIZE, orig_optimize)?;
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_PROGRESSIVE, orig_progressive)?;
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_ARITHMETIC, orig_arithmetic)?;
+        res
+    }
+
+    fn compress_all_codings(&mut self, image: Image<&[u8]>, arithmetic: bool) -> Result<OwnedBuf> {
+        let mut smallest = self.compress_with_coding(image, false, false)?;
+        let mut codings = vec![(false, true)];
+        if arithmetic {
+            codings.extend([(true, false), (true, true)]);
+        }
+        for (arithmetic, progressive) in codings {
+            let candidate = self.compress_with_coding(image, arithmetic, progressive)?;
+            if candidate.len() < smallest.len() {
+                smallest = candidate;
+            }
+        }
+        Ok(smallest)
+    }
+
+    /// Compress the `image` with optimized Huffman or arithmetic entropy coding.
+    fn compress_with_coding(
+        &mut self,
+        image: Image<&[u8]>,
+        arithmetic: bool,
+        progressive: bool,
+    ) -> Result<OwnedBuf> {
+        self.set_arithmetic(arithmetic)?;
+        self.set_optimize(!arithmetic)?;
+        self.set_progressive(progressive)?;
+        self.compress_to_owned(image)
+    }
+
+    /// Compress the `image` with the highest quality that fits into `max_bytes`.
+    ///
+    /// This method searches over the quality (using bisection, so it needs at most 7 trial
+    /// compressions) and returns the JPEG image with the highest quality whose size does not
+    /// exceed `max_bytes`. All other settings of the compressor (such as chrominance subsampling)
+    /// are used as usual, and the quality of the compressor is restored when this method returns.
+    ///
+    /// Returns [`Error::TargetSizeUnreachable`] if the image does not fit into `max_bytes` even
+    /// with the lowest quality.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_to_target_size(image.as_deref(), 20_000)?;
+    /// assert!(jpeg_data.len() <= 20_000);
+    ///
+    /// // a tiny budget cannot be met
+    /// assert!(compressor.compress_to_target_size(image.as_deref(), 100).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_to_target_size(
+        &mut self,
+        image: Image<&[u8]>,
+        max_bytes: usize,
+    ) -> Result<OwnedBuf> {
+        let orig_quality = self.handle.get(ffi::TJPARAM_TJPARAM_QUALITY);
+        let res = self.search_quality(image, max_bytes);
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_QUALITY, orig_quality)?;
+        res?.ok_or(Error::TargetSizeUnreachable(max_bytes))
+    }
+
+    fn search_quality(
+        &mut self,
+        image: Image<&[u8]>,
+        max_bytes: usize,
+    ) -> Result<Option<OwnedBuf>> {
+        let (mut lo, mut hi) = (1, 100);
+        let mut best = None;
+        while lo <= hi {
+            let quality = (lo + hi) / 2;
+            self.set_quality(quality)?;
+            let buf = self.compress_to_owned(image)?;
+            if buf.len() <= max_bytes {
+                best = Some(buf);
+                lo = quality + 1;
+            } else {
+                hi = quality - 1;
+            }
+        }
+        Ok(best)
+    }
+
+    /// Compress the `image` with the lowest quality that meets a perceptual quality `target`.
+    ///
+    /// Unlike the quality set by [`set_quality()`][Self::set_quality], which maps to very
+    /// different visual results for different images, a perceptual metric such as SSIM or PSNR
+    /// normalizes the quality across diverse content. This method searches over the quality
+    /// (using bisection, so it needs at most 7 trial compressions), decompresses each trial image
+    /// and compares it with `image`. It returns the smallest JPEG image whose quality meets the
+    /// `target`, or the image compressed with the highest quality if the target cannot be met.
+    ///
+    /// All other settings of the compressor are used as usual, and the quality of the compressor
+    /// is restored when this method returns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let high_quality = compressor.compress_to_vec(image.as_deref())?;
+    ///
+    /// let target = turbojpeg::QualityTarget::Ssim(0.95);
+    /// let jpeg_data = compressor.compress_to_quality_target(image.as_deref(), target)?;
+    /// assert!(jpeg_data.len() < high_quality.len());
+    ///
+    /// // bottom-up images are compared in the same row order
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let photo = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::RGB)?;
+    /// let top_down = compressor.compress_to_quality_target(photo.as_deref(), target)?;
+    /// let mut flipped = photo.clone();
+    /// flipped.vflip();
+    /// compressor.set_bottom_up(true)?;
+    /// let bottom_up = compressor.compress_to_quality_target(flipped.as_deref(), target)?;
+    /// assert_eq!(&*bottom_up, &*top_down);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_to_quality_target(
+        &mut self,
+        image: Image<&[u8]>,
+        target: QualityTarget,
+    ) -> Result<OwnedBuf> {
+        let orig_quality = self.handle.get(ffi::TJPARAM_TJPARAM_QUALITY);
+        let res = self.search_quality_target(image, target);
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_QUALITY, orig_quality)?;
+        res
+    }
+
+    fn search_quality_target(
+        &mut self,
+        image: Image<&[u8]>,
+        target: QualityTarget,
+    ) -> Result<OwnedBuf> {
+        let mut decompressor = Decompressor::new()?;
+        let bottom_up = self.handle.get(ffi::TJPARAM_TJPARAM_BOTTOMUP) == 1;
+        let pitch = image.width * image.format.size();
+        let mut decompressed = Image {
+            pixels: vec![0; pitch * image.height],
+            width: image.width,
+            pitch,
+            height: image.height,
+            format: image.format,
+        };
+
+        let (mut lo, mut hi) = (1, 100);
+        let mut best = None;
+        while lo <= hi {
+            let quality = (lo + hi) / 2;
+            self.set_quality(quality)?;
+            let buf = self.compress_to_owned(image)?;
+            decompressor.decompress(&buf, decompressed.as_deref_mut())?;
+            if bottom_up {
+                // the rows of `image` were compressed from bottom to top
+                decompressed.vflip();
+            }
+            let meets_target = match target {
+                QualityTarget::Psnr(psnr) => metrics::psnr(image, decompressed.as_deref()) >= psnr,
+                QualityTarget::Ssim(ssim) => metrics::ssim(image, decompressed.as_deref()) >= ssim,
+            };
+            if meets_target {
+                best = Some(buf);
+                hi = quality - 1;
+            } else {
+                lo = quality + 1;
+            }
+        }
+
+        match best {
+            Some(buf) => Ok(buf),
+            None => {
+                self.set_quality(100)?;
+                self.compress_to_owned(image)
+            }
+        }
+    }
+
     /// Compresses the [`YuvImage`] into `output` buffer.
     ///
     /// This is similar to [`compress()`][Self::compress], but encodes a YUV image instead of RGB
@@ -252,6 +1682,9 @@ impl Compressor {
     #[doc(alias = "tj3CompressFromYUV8")]
     pub fn compress_yuv(&mut self, image: YuvImage<&[u8]>, output: &mut OutputBuf) -> Result<()> {
         image.assert_valid(image.pixels.len());
+        if self.smoothing != 0 {
+            return Err(Error::InvalidParam("smoothing"));
+        }
 
         let YuvImage {
             pixels,
@@ -259,8 +1692,10 @@ impl Compressor {
             align,
             height,
             subsamp,
-        } = image;
+        } = image.as_deref();
         self.set_subsamp(subsamp)?;
+        // YUV images are compressed like RGB images by TurboJPEG
+        self.apply_colorspace(PixelFormat::RGB, subsamp)?;
         let width: libc::c_int = width
             .try_into()
             .map_err(|_| Error::IntegerOverflow("width"))?;
@@ -271,12 +1706,17 @@ impl Compressor {
             .try_into()
             .map_err(|_| Error::IntegerOverflow("height"))?;
 
-        self.handle.set(
-            ffi::TJPARAM_TJPARAM_NOREALLOC,
-            if output.is_owned { 0 } else { 1 } as libc::c_int,
-        )?;
-
-        let mut output_len = output.len as ffi::size_t;
+        let max_len = self.max_buf_len(image.width, image.height, 8)?;
+        if output.is_too_small(max_len) {
+            return self.compress_via_owned(output, |compressor, buf| {
+                compressor.compress_yuv(image, buf)
+            });
+        }
+        output.prepare(max_len);
+        let no_realloc = output.no_realloc();
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_NOREALLOC, no_realloc as libc::c_int)?;
+        let mut output_len = output.capacity() as ffi::size_t;
         let res = unsafe {
             ffi::tj3CompressFromYUV8(
                 self.handle.as_ptr(),
@@ -288,14 +1728,14 @@ impl Compressor {
                 &mut output_len,
             )
         };
-        output.len = output_len as usize;
         if res != 0 {
             return Err(self.handle.get_error());
         } else if output.ptr.is_null() {
             output.len = 0;
             return Err(Error::Null);
         }
-        Ok(())
+        output.set_written(output_len as usize);
+        self.write_markers(output)
     }
 
     /// Compresses the [`YuvImage`] into an owned buffer.
@@ -333,29 +1773,958 @@ impl Compressor {
         Ok(buf.len())
     }
 
-    /// Compute the maximum size of a compressed image.
+    /// Compresses the [`YuvPlanes`] into `output` buffer.
     ///
-    /// This depends on image `width` and `height`, and also on the current setting of chrominance
-    /// subsampling (see [`set_subsamp()`](Compressor::set_subsamp)).
+    /// This is similar to [`compress_yuv()`][Self::compress_yuv], but the Y, U and V planes are
+    /// read from separate buffers, each with its own stride. This avoids copying frames that are
+    /// stored as separate planes (for example, by a video decoder) into one contiguous buffer.
     ///
-    /// You can also use [`compressed_buf_len()`] directly.
-    #[doc(alias = "tj3JPEGBufSize")]
-    pub fn buf_len(&self, width: usize, height: usize) -> Result<usize> {
-        compressed_buf_len(width, height, self.subsamp)
-    }
-}
+    /// # Example
+    ///
+    /// ```
+    /// const WIDTH: usize = 1920;
+    /// const HEIGHT: usize = 1080;
+    ///
+    /// // planes with padded strides, as produced by a video decoder
+    /// let y_plane = vec![128; 2048 * HEIGHT];
+    /// let u_plane = vec![100; 1024 * HEIGHT / 2];
+    /// let v_plane = vec![150; 1024 * HEIGHT / 2];
+    ///
+    /// let image = turbojpeg::YuvPlanes {
+    ///     planes: [&y_plane[..], &u_plane[..], &v_plane[..]],
+    ///     strides: [2048, 1024, 1024],
+    ///     width: WIDTH,
+    ///     height: HEIGHT,
+    ///     subsamp: turbojpeg::Subsamp::Sub2x2,
+    /// };
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_yuv_planes_to_owned(image)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (WIDTH, HEIGHT));
+    /// assert_eq!(header.subsamp, turbojpeg::Subsamp::Sub2x2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3CompressFromYUVPlanes8")]
+    pub fn compress_yuv_planes(
+        &mut self,
+        image: YuvPlanes<&[u8]>,
+        output: &mut OutputBuf,
+    ) -> Result<()> {
+        let [y, u, v] = image.planes;
+        image.assert_valid([y.len(), u.len(), v.len()]);
+        if self.smoothing != 0 {
+            return Err(Error::InvalidParam("smoothing"));
+        }
 
-/// Compress an image to JPEG.
-///
-/// Uses the given quality and chrominance subsampling option and returns the JPEG data in a buffer
-/// owned by TurboJPEG. If this function does not fit your needs, please see [`Compressor`].
-///
-/// # Example
-///
-/// ```
-/// // create an image (a Mandelbrot set visualization)
-/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
-///
+        let YuvPlanes {
+            planes,
+            strides,
+            width,
+            height,
+            subsamp,
+        } = image;
+        self.set_subsamp(subsamp)?;
+        // YUV images are compressed like RGB images by TurboJPEG
+        self.apply_colorspace(PixelFormat::RGB, subsamp)?;
+        let width: libc::c_int = width
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("width"))?;
+        let height: libc::c_int = height
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("height"))?;
+        let mut c_strides: [libc::c_int; 3] = [0; 3];
+        for (c_stride, stride) in c_strides.iter_mut().zip(strides) {
+            *c_stride = stride
+                .try_into()
+                .map_err(|_| Error::IntegerOverflow("stride"))?;
+        }
+        let plane_ptrs = planes.map(|plane| plane.as_ptr());
+
+        let max_len = self.max_buf_len(image.width, image.height, 8)?;
+        if output.is_too_small(max_len) {
+            return self.compress_via_owned(output, |compressor, buf| {
+                compressor.compress_yuv_planes(image, buf)
+            });
+        }
+        output.prepare(max_len);
+        let no_realloc = output.no_realloc();
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_NOREALLOC, no_realloc as libc::c_int)?;
+        let mut output_len = output.capacity() as ffi::size_t;
+        let res = unsafe {
+            ffi::tj3CompressFromYUVPlanes8(
+                self.handle.as_ptr(),
+                plane_ptrs.as_ptr(),
+                width,
+                c_strides.as_ptr(),
+                height,
+                &mut output.ptr,
+                &mut output_len,
+            )
+        };
+        if res != 0 {
+            return Err(self.handle.get_error());
+        } else if output.ptr.is_null() {
+            output.len = 0;
+            return Err(Error::Null);
+        }
+        output.set_written(output_len as usize);
+        self.write_markers(output)
+    }
+
+    /// Compresses the [`YuvPlanes`] into an owned buffer.
+    ///
+    /// This method automatically allocates the memory for output and avoids needless copying.
+    pub fn compress_yuv_planes_to_owned(&mut self, image: YuvPlanes<&[u8]>) -> Result<OwnedBuf> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_yuv_planes(image, &mut buf)?;
+        Ok(buf.into_owned())
+    }
+
+    /// Compress the `YuvPlanes` into a new `Vec<u8>`.
+    ///
+    /// This method copies the compressed data into a new `Vec`. If you would like to avoid the
+    /// extra allocation and copying, consider using
+    /// [`compress_yuv_planes_to_owned()`][Self::compress_yuv_planes_to_owned] instead.
+    pub fn compress_yuv_planes_to_vec(&mut self, image: YuvPlanes<&[u8]>) -> Result<Vec<u8>> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_yuv_planes(image, &mut buf)?;
+        Ok(buf.to_vec())
+    }
+
+    /// Compress the `YuvPlanes` into the slice `output`.
+    ///
+    /// Returns the size of the compressed JPEG data. If the compressed image does not fit into
+    /// `dest`, this method returns an error. Use [`compressed_buf_len()`] to determine buffer size
+    /// that is guaranteed to be large enough for the compressed image.
+    pub fn compress_yuv_planes_to_slice(
+        &mut self,
+        image: YuvPlanes<&[u8]>,
+        output: &mut [u8],
+    ) -> Result<usize> {
+        let mut buf = OutputBuf::borrowed(output);
+        self.compress_yuv_planes(image, &mut buf)?;
+        Ok(buf.len())
+    }
+
+    /// Converts the packed RGB (or grayscale) `image` into the YUV image `output`.
+    ///
+    /// This performs only the color conversion and chrominance downsampling of TurboJPEG
+    /// (accelerated with SIMD instructions), without compressing the result into JPEG. The
+    /// subsampling of the `output` is also set as the subsampling of the compressor (see
+    /// [`set_subsamp()`][Self::set_subsamp]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` does not have the same width and height as the `image`, or if one
+    /// of the images is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut yuv_image = turbojpeg::YuvImage {
+    ///     pixels: vec![0; turbojpeg::yuv_pixels_len(64, 1, 48, turbojpeg::Subsamp::Sub2x2)?],
+    ///     width: 64,
+    ///     align: 1,
+    ///     height: 48,
+    ///     subsamp: turbojpeg::Subsamp::Sub2x2,
+    /// };
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.encode_yuv(image.as_deref(), yuv_image.as_deref_mut())?;
+    ///
+    /// // compressing the YUV image gives the same result as compressing the RGB image
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// assert_eq!(compressor.compress_yuv_to_vec(yuv_image.as_deref())?, jpeg_data);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3EncodeYUV8")]
+    pub fn encode_yuv(&mut self, image: Image<&[u8]>, output: YuvImage<&mut [u8]>) -> Result<()> {
+        image.assert_valid(image.pixels.len());
+        output.assert_valid(output.pixels.len());
+        assert!(
+            (output.width, output.height) == (image.width, image.height),
+            "output image {}x{} does not have the size of the input image {}x{}",
+            output.width,
+            output.height,
+            image.width,
+            image.height
+        );
+
+        let Image {
+            pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = image;
+        let width: libc::c_int = width
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("width"))?;
+        let pitch: libc::c_int = pitch
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("pitch"))?;
+        let height: libc::c_int = height
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("height"))?;
+        let align = output
+            .align
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("align"))?;
+
+        self.set_subsamp(output.subsamp)?;
+        let res = unsafe {
+            ffi::tj3EncodeYUV8(
+                self.handle.as_ptr(),
+                pixels.as_ptr(),
+                width,
+                pitch,
+                height,
+                format as libc::c_int,
+                output.pixels.as_mut_ptr(),
+                align,
+            )
+        };
+        if res != 0 {
+            return Err(self.handle.get_error());
+        }
+
+        Ok(())
+    }
+
+    /// Compresses the packed YUV 4:2:2 image into `output` buffer.
+    ///
+    /// The image is converted to separate Y, U and V planes (see
+    /// [`PackedYuvImage::to_yuv_planes()`]), which are then compressed using
+    /// [`compress_yuv_planes()`][Self::compress_yuv_planes] with 4:2:2 subsampling. This is useful
+    /// for compressing frames from webcams, which typically produce YUYV or UYVY images.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// const WIDTH: usize = 640;
+    /// const HEIGHT: usize = 480;
+    ///
+    /// // grab a YUYV frame from a webcam
+    /// let frame = vec![128; WIDTH * 2 * HEIGHT];
+    ///
+    /// let image = turbojpeg::PackedYuvImage {
+    ///     pixels: &frame[..],
+    ///     width: WIDTH,
+    ///     pitch: WIDTH * 2,
+    ///     height: HEIGHT,
+    ///     format: turbojpeg::PackedYuvFormat::YUYV,
+    /// };
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_packed_yuv_to_owned(image)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (WIDTH, HEIGHT));
+    /// assert_eq!(header.subsamp, turbojpeg::Subsamp::Sub2x1);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_packed_yuv(
+        &mut self,
+        image: PackedYuvImage<&[u8]>,
+        output: &mut OutputBuf,
+    ) -> Result<()> {
+        let planes = image.to_yuv_planes();
+        self.compress_yuv_planes(planes.as_deref(), output)
+    }
+
+    /// Compresses the packed YUV 4:2:2 image into an owned buffer.
+    ///
+    /// This method automatically allocates the memory for output and avoids needless copying.
+    pub fn compress_packed_yuv_to_owned(
+        &mut self,
+        image: PackedYuvImage<&[u8]>,
+    ) -> Result<OwnedBuf> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_packed_yuv(image, &mut buf)?;
+        Ok(buf.into_owned())
+    }
+
+    /// Compress the packed YUV 4:2:2 image into a new `Vec<u8>`.
+    ///
+    /// This method copies the compressed data into a new `Vec`. If you would like to avoid the
+    /// extra allocation and copying, consider using
+    /// [`compress_packed_yuv_to_owned()`][Self::compress_packed_yuv_to_owned] instead.
+    pub fn compress_packed_yuv_to_vec(&mut self, image: PackedYuvImage<&[u8]>) -> Result<Vec<u8>> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_packed_yuv(image, &mut buf)?;
+        Ok(buf.to_vec())
+    }
+
+    /// Compress the packed YUV 4:2:2 image into the slice `output`.
+    ///
+    /// Returns the size of the compressed JPEG data. If the compressed image does not fit into
+    /// `dest`, this method returns an error. Use [`compressed_buf_len()`] to determine buffer size
+    /// that is guaranteed to be large enough for the compressed image.
+    pub fn compress_packed_yuv_to_slice(
+        &mut self,
+        image: PackedYuvImage<&[u8]>,
+        output: &mut [u8],
+    ) -> Result<usize> {
+        let mut buf = OutputBuf::borrowed(output);
+        self.compress_packed_yuv(image, &mut buf)?;
+        Ok(buf.len())
+    }
+
+    /// Compresses the semi-planar YUV 4:2:0 image (NV12 or NV21) into `output` buffer.
+    ///
+    /// The luminance plane is passed to TurboJPEG directly, only the interleaved chrominance plane
+    /// is split into separate U and V planes, which are then compressed using
+    /// [`compress_yuv_planes()`][Self::compress_yuv_planes] with 4:2:0 subsampling.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// const WIDTH: usize = 1280;
+    /// const HEIGHT: usize = 720;
+    ///
+    /// // grab a NV12 frame from a hardware decoder
+    /// let frame = vec![128; WIDTH * HEIGHT * 3 / 2];
+    /// let (y_plane, uv_plane) = frame.split_at(WIDTH * HEIGHT);
+    ///
+    /// let image = turbojpeg::SemiPlanarImage {
+    ///     planes: [y_plane, uv_plane],
+    ///     strides: [WIDTH, WIDTH],
+    ///     width: WIDTH,
+    ///     height: HEIGHT,
+    ///     format: turbojpeg::SemiPlanarFormat::NV12,
+    /// };
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_semi_planar_to_owned(image)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (WIDTH, HEIGHT));
+    /// assert_eq!(header.subsamp, turbojpeg::Subsamp::Sub2x2);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn compress_semi_planar(
+        &mut self,
+        image: SemiPlanarImage<&[u8]>,
+        output: &mut OutputBuf,
+    ) -> Result<()> {
+        let [y, uv] = image.planes;
+        image.assert_valid([y.len(), uv.len()]);
+
+        let [u, v] = image.deinterleave_uv();
+        let uv_width = image.uv_size().0;
+        let planes = YuvPlanes {
+            planes: [y, &u[..], &v[..]],
+            strides: [image.strides[0], uv_width, uv_width],
+            width: image.width,
+            height: image.height,
+            subsamp: Subsamp::Sub2x2,
+        };
+        self.compress_yuv_planes(planes, output)
+    }
+
+    /// Compresses the semi-planar YUV 4:2:0 image into an owned buffer.
+    ///
+    /// This method automatically allocates the memory for output and avoids needless copying.
+    pub fn compress_semi_planar_to_owned(
+        &mut self,
+        image: SemiPlanarImage<&[u8]>,
+    ) -> Result<OwnedBuf> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_semi_planar(image, &mut buf)?;
+        Ok(buf.into_owned())
+    }
+
+    /// Compress the semi-planar YUV 4:2:0 image into a new `Vec<u8>`.
+    ///
+    /// This method copies the compressed data into a new `Vec`. If you would like to avoid the
+    /// extra allocation and copying, consider using
+    /// [`compress_semi_planar_to_owned()`][Self::compress_semi_planar_to_owned] instead.
+    pub fn compress_semi_planar_to_vec(
+        &mut self,
+        image: SemiPlanarImage<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        let mut buf = OutputBuf::new_owned();
+        self.compress_semi_planar(image, &mut buf)?;
+        Ok(buf.to_vec())
+    }
+
+    /// Compress the semi-planar YUV 4:2:0 image into the slice `output`.
+    ///
+    /// Returns the size of the compressed JPEG data. If the compressed image does not fit into
+    /// `dest`, this method returns an error. Use [`compressed_buf_len()`] to determine buffer size
+    /// that is guaranteed to be large enough for the compressed image.
+    pub fn compress_semi_planar_to_slice(
+        &mut self,
+        image: SemiPlanarImage<&[u8]>,
+        output: &mut [u8],
+    ) -> Result<usize> {
+        let mut buf = OutputBuf::borrowed(output);
+        self.compress_semi_planar(image, &mut buf)?;
+        Ok(buf.len())
+    }
+
+    /// Compute the maximum size of a compressed image.
+    ///
+    /// This depends on image `width` and `height`, and also on the current setting of chrominance
+    /// subsampling (see [`set_subsamp()`](Compressor::set_subsamp)). When the subsampling is
+    /// [selected automatically][Self::set_auto_subsamp], this is the larger size of the two
+    /// subsamplings that may be selected ([`Subsamp::None`] and [`Subsamp::Sub2x2`]).
+    ///
+    /// You can also use [`compressed_buf_len()`] directly.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{compressed_buf_len, Subsamp};
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_subsamp(Subsamp::Sub2x2)?;
+    /// assert_eq!(compressor.buf_len(64, 64)?, compressed_buf_len(64, 64, Subsamp::Sub2x2)?);
+    ///
+    /// compressor.set_auto_subsamp(true);
+    /// assert_eq!(compressor.buf_len(64, 64)?, compressed_buf_len(64, 64, Subsamp::None)?);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3JPEGBufSize")]
+    pub fn buf_len(&self, width: usize, height: usize) -> Result<usize> {
+        if self.auto_subsamp {
+            // padding to 16x16 MCU blocks can outweigh the chrominance for tiny images
+            let none = compressed_buf_len(width, height, Subsamp::None)?;
+            let sub2x2 = compressed_buf_len(width, height, Subsamp::Sub2x2)?;
+            Ok(none.max(sub2x2))
+        } else {
+            compressed_buf_len(width, height, self.subsamp)
+        }
+    }
+
+    /// Compute the maximum size of an image compressed by this compressor.
+    ///
+    /// In addition to [`buf_len()`][Self::buf_len], which bounds only the compressed image data
+    /// of 8-bit images, this accounts for the sample `precision` and for the metadata that this
+    /// compressor stores into every image:
+    ///
+    /// - `precision` is 8 for [`compress()`][Self::compress] and the other 8-bit methods, 12 for
+    ///   [`compress_12bit()`][Self::compress_12bit] and 16 for
+    ///   [`compress_16bit()`][Self::compress_16bit]. Samples with more than 8 bits need up to
+    ///   twice as many bytes, and 16-bit images are never subsampled (they are lossless).
+    /// - The ICC profile set by [`set_icc_profile()`][Self::set_icc_profile] is split into as
+    ///   many APP2 segments as needed.
+    /// - The segments added by [`add_app_marker()`][Self::add_app_marker] (such as EXIF data),
+    ///   the [XMP packet][Self::set_xmp] and the [comment][Self::set_comment] are never split,
+    ///   because their payload is limited to a single segment.
+    ///
+    /// A buffer of this size is large enough for [`compress_to_slice()`][Self::compress_to_slice]
+    /// (with `precision` 8), or for [`compress_12bit()`][Self::compress_12bit] and
+    /// [`compress_16bit()`][Self::compress_16bit] into a borrowed [`OutputBuf`] (with `precision`
+    /// 12 or 16), with the current settings.
+    ///
+    /// Returns [`Error::InvalidParam`] if `precision` is not between 2 and 16.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # let icc_profile = vec![0u8; 100_000];
+    /// let image = turbojpeg::Image::mandelbrot(64, 64, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_subsamp(turbojpeg::Subsamp::Sub2x2)?;
+    /// compressor.set_icc_profile(&icc_profile)?;
+    /// compressor.add_app_marker(1, b"Exif\0\0MM\0\x2a\0\0\0\x08\0\0")?;
+    /// compressor.set_comment("parrots")?;
+    ///
+    /// let mut output = vec![0; compressor.max_buf_len(64, 64, 8)?];
+    /// let len = compressor.compress_to_slice(image.as_deref(), &mut output)?;
+    /// assert!(len > 100_000);
+    ///
+    /// // 12-bit images need a larger buffer
+    /// let mut image12 = turbojpeg::Image::alloc16(64, 64, turbojpeg::PixelFormat::RGB);
+    /// for (sample12, &sample) in image12.pixels.iter_mut().zip(&image.pixels) {
+    ///     *sample12 = (sample as u16) << 4;
+    /// }
+    /// let mut output = vec![0; compressor.max_buf_len(64, 64, 12)?];
+    /// let mut buf = turbojpeg::OutputBuf::borrowed(&mut output);
+    /// compressor.compress_12bit(image12.as_deref(), &mut buf)?;
+    /// assert!(buf.len() > 100_000);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn max_buf_len(&self, width: usize, height: usize, precision: u8) -> Result<usize> {
+        if !(2..=16).contains(&precision) {
+            return Err(Error::InvalidParam("precision"));
+        }
+        let data_len = if precision > 12 {
+            compressed_buf_len(width, height, Subsamp::None)?
+        } else {
+            self.buf_len(width, height)?
+        };
+        let data_len = if precision > 8 {
+            data_len
+                .checked_mul(2)
+                .ok_or(Error::IntegerOverflow("buf len"))?
+        } else {
+            data_len
+        };
+        // the ICC profile is split into APP2 markers with at most 65519 bytes of data, each
+        // marker adds 18 bytes of overhead (marker, length, "ICC_PROFILE\0", sequence number and
+        // number of markers)
+        let icc_markers = (self.icc_profile.len() + 65518) / 65519;
+        let icc_len = self.icc_profile.len() + 18 * icc_markers;
+        // the other segments add 4 bytes of overhead (marker and length)
+        let markers_len: usize = self
+            .markers
+            .iter()
+            .map(|(_, data)| data)
+            .chain(self.xmp.iter())
+            .chain(self.comment.iter())
+            .map(|data| 4 + data.len())
+            .sum();
+        data_len
+            .checked_add(icc_len + markers_len)
+            .ok_or(Error::IntegerOverflow("buf len"))
+    }
+}
+
+/// Snapshot of the compression settings of a [`Compressor`].
+///
+/// This is a plain struct that can be stored (with the `serde` feature, it can be serialized, for
+/// example into a configuration file), obtained from a compressor using
+/// [`Compressor::params()`] and applied using [`Compressor::set_params()`]. The default instance
+/// contains the defaults of [`Compressor::new()`].
+///
+/// # Example
+///
+/// ```
+/// let mut params = turbojpeg::CompressParams::default();
+/// params.quality = 80;
+/// params.subsamp = turbojpeg::Subsamp::Sub2x2;
+/// params.progressive = true;
+///
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// compressor.set_params(&params)?;
+/// assert_eq!(compressor.params()?, params);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[non_exhaustive]
+pub struct CompressParams {
+    /// Quality from 1 (worst) to 100 (best), see [`Compressor::set_quality()`].
+    pub quality: i32,
+    /// Chrominance subsampling, see [`Compressor::set_subsamp()`].
+    pub subsamp: Subsamp,
+    /// Optimized baseline entropy coding, see [`Compressor::set_optimize()`]. Ignored when
+    /// `arithmetic` is set.
+    pub optimize: bool,
+    /// Progressive entropy coding, see [`Compressor::set_progressive()`].
+    pub progressive: bool,
+    /// Arithmetic entropy coding, see [`Compressor::set_arithmetic()`].
+    pub arithmetic: bool,
+    /// Restart interval in MCU blocks, see [`Compressor::set_restart_blocks()`].
+    pub restart_blocks: u16,
+    /// Restart interval in MCU rows, see [`Compressor::set_restart_rows()`].
+    pub restart_rows: u16,
+    /// Horizontal pixel density, see [`Compressor::set_density()`].
+    pub x_density: u16,
+    /// Vertical pixel density, see [`Compressor::set_density()`].
+    pub y_density: u16,
+    /// Units of the pixel density, see [`Compressor::set_density()`].
+    pub density_unit: DensityUnit,
+    /// Input smoothing factor from 0 to 100, see `Compressor::set_smoothing()`.
+    ///
+    /// Smoothing requires the `libjpeg` feature, without it, only 0 is valid.
+    pub smoothing: u8,
+    /// Automatic selection of chrominance subsampling, see [`Compressor::set_auto_subsamp()`].
+    pub auto_subsamp: bool,
+}
+
+impl CompressParams {
+    /// Check that the parameters are valid and can be combined.
+    ///
+    /// Returns [`Error::InvalidParam`] if a parameter has an invalid value, or
+    /// [`Error::IncompatibleParams`] if two parameters cannot be combined.
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=100).contains(&self.quality) {
+            return Err(Error::InvalidParam("quality"));
+        }
+        if self.subsamp == Subsamp::Unknown {
+            return Err(Error::InvalidParam("subsamp"));
+        }
+        if self.smoothing > 100 || (self.smoothing != 0 && !cfg!(feature = "libjpeg")) {
+            return Err(Error::InvalidParam("smoothing"));
+        }
+        if self.x_density == 0 || self.y_density == 0 {
+            return Err(Error::InvalidParam("density"));
+        }
+        if self.restart_blocks != 0 && self.restart_rows != 0 {
+            return Err(Error::IncompatibleParams("restart_blocks", "restart_rows"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for CompressParams {
+    fn default() -> Self {
+        CompressParams {
+            quality: DEFAULT_QUALITY,
+            subsamp: DEFAULT_SUBSAMP,
+            optimize: false,
+            progressive: false,
+            arithmetic: false,
+            restart_blocks: 0,
+            restart_rows: 0,
+            x_density: 1,
+            y_density: 1,
+            density_unit: DensityUnit::Unknown,
+            smoothing: 0,
+            auto_subsamp: false,
+        }
+    }
+}
+
+/// Builder for a [`Compressor`] with validated settings.
+///
+/// The builder collects all settings of the compressor and validates them when
+/// [`build()`][Self::build] is called, so that invalid values and incompatible combinations are
+/// reported up front instead of failing later during compression. Settings that are not
+/// specified keep the defaults of [`Compressor::new()`].
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+/// let mut compressor = turbojpeg::Compressor::builder()
+///     .quality(85)
+///     .subsamp(turbojpeg::Subsamp::Sub2x2)
+///     .progressive(true)
+///     .build()?;
+/// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+///
+/// // invalid settings are reported by build()
+/// assert!(turbojpeg::Compressor::builder().quality(0).build().is_err());
+/// assert!(turbojpeg::Compressor::builder().restart_blocks(4).restart_rows(1).build().is_err());
+///
+/// // like in TurboJPEG, optimize is ignored when arithmetic coding is enabled
+/// let mut compressor = turbojpeg::Compressor::builder().optimize(true).arithmetic(true).build()?;
+/// let arithmetic = compressor.compress_to_vec(image.as_deref())?;
+/// compressor.set_optimize(false)?;
+/// assert_eq!(arithmetic, compressor.compress_to_vec(image.as_deref())?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CompressorBuilder {
+    params: CompressParams,
+    icc_profile: Option<Vec<u8>>,
+    app_markers: Vec<(u8, Vec<u8>)>,
+    xmp: Option<Vec<u8>>,
+}
+
+impl CompressorBuilder {
+    /// Create a builder with default settings.
+    pub fn new() -> CompressorBuilder {
+        CompressorBuilder::default()
+    }
+
+    /// Start from the given parameters (see [`CompressParams`]).
+    pub fn params(mut self, params: CompressParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Set the quality (see [`Compressor::set_quality()`]).
+    pub fn quality(mut self, quality: i32) -> Self {
+        self.params.quality = quality;
+        self
+    }
+
+    /// Set the chrominance subsampling (see [`Compressor::set_subsamp()`]).
+    pub fn subsamp(mut self, subsamp: Subsamp) -> Self {
+        self.params.subsamp = subsamp;
+        self
+    }
+
+    /// Enable/disable optimized baseline entropy coding (see [`Compressor::set_optimize()`]).
+    pub fn optimize(mut self, optimize: bool) -> Self {
+        self.params.optimize = optimize;
+        self
+    }
+
+    /// Enable/disable progressive entropy coding (see [`Compressor::set_progressive()`]).
+    pub fn progressive(mut self, progressive: bool) -> Self {
+        self.params.progressive = progressive;
+        self
+    }
+
+    /// Enable/disable arithmetic entropy coding (see [`Compressor::set_arithmetic()`]).
+    pub fn arithmetic(mut self, arithmetic: bool) -> Self {
+        self.params.arithmetic = arithmetic;
+        self
+    }
+
+    /// Set the restart interval in MCU blocks (see [`Compressor::set_restart_blocks()`]).
+    pub fn restart_blocks(mut self, blocks: u16) -> Self {
+        self.params.restart_blocks = blocks;
+        self
+    }
+
+    /// Set the restart interval in MCU rows (see [`Compressor::set_restart_rows()`]).
+    pub fn restart_rows(mut self, rows: u16) -> Self {
+        self.params.restart_rows = rows;
+        self
+    }
+
+    /// Set the pixel density (see [`Compressor::set_density()`]).
+    pub fn density(mut self, x: u16, y: u16, unit: DensityUnit) -> Self {
+        self.params.x_density = x;
+        self.params.y_density = y;
+        self.params.density_unit = unit;
+        self
+    }
+
+    /// Embed an ICC profile (see [`Compressor::set_icc_profile()`]).
+    pub fn icc_profile(mut self, icc_profile: &[u8]) -> Self {
+        self.icc_profile = Some(icc_profile.to_vec());
+        self
+    }
+
+    /// Add a custom application segment (see [`Compressor::add_app_marker()`]).
+    pub fn app_marker(mut self, n: u8, data: &[u8]) -> Self {
+        self.app_markers.push((n, data.to_vec()));
+        self
+    }
+
+    /// Embed an XMP packet (see [`Compressor::set_xmp()`]).
+    pub fn xmp(mut self, xmp: &[u8]) -> Self {
+        self.xmp = Some(xmp.to_vec());
+        self
+    }
+
+    /// Set the input smoothing factor (see `Compressor::set_smoothing()`, which requires the
+    /// `libjpeg` feature).
+    pub fn smoothing(mut self, factor: u8) -> Self {
+        self.params.smoothing = factor;
+        self
+    }
+
+    /// Enable/disable automatic selection of chrominance subsampling (see
+    /// [`Compressor::set_auto_subsamp()`]).
+    pub fn auto_subsamp(mut self, auto_subsamp: bool) -> Self {
+        self.params.auto_subsamp = auto_subsamp;
+        self
+    }
+
+    /// Validate the settings and create the compressor.
+    ///
+    /// Returns [`Error::InvalidParam`] if a setting has an invalid value, or
+    /// [`Error::IncompatibleParams`] if two settings cannot be combined.
+    pub fn build(&self) -> Result<Compressor> {
+        let mut compressor = Compressor::new()?;
+        compressor.set_params(&self.params)?;
+        if let Some(ref icc_profile) = self.icc_profile {
+            compressor.set_icc_profile(icc_profile)?;
+        }
+        for (n, data) in self.app_markers.iter() {
+            compressor.add_app_marker(*n, data)?;
+        }
+        if let Some(ref xmp) = self.xmp {
+            compressor.set_xmp(xmp)?;
+        }
+        Ok(compressor)
+    }
+}
+
+/// An image compressed as a grid of independent JPEG tiles.
+///
+/// Produced by [`Compressor::compress_tiles()`] or, losslessly from an existing JPEG image, by
+/// [`Transformer::crop_grid()`][crate::Transformer::crop_grid]. The tiles are stored in row-major order; the
+/// tiles in the last column and row may be smaller than [`tile_width`][Self::tile_width] x
+/// [`tile_height`][Self::tile_height].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TileGrid {
+    /// Width of the whole image in pixels.
+    pub width: usize,
+    /// Height of the whole image in pixels.
+    pub height: usize,
+    /// Nominal width of a tile in pixels.
+    pub tile_width: usize,
+    /// Nominal height of a tile in pixels.
+    pub tile_height: usize,
+    /// Number of tile columns.
+    pub columns: usize,
+    /// Number of tile rows.
+    pub rows: usize,
+    /// The compressed tiles in row-major order.
+    pub tiles: Vec<Tile>,
+}
+
+impl TileGrid {
+    /// Get the tile in the given `column` and `row`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `column` or `row` is out of range.
+    pub fn tile(&self, column: usize, row: usize) -> &Tile {
+        assert!(
+            column < self.columns && row < self.rows,
+            "tile ({}, {}) is out of range for grid {}x{}",
+            column,
+            row,
+            self.columns,
+            self.rows
+        );
+        &self.tiles[row * self.columns + column]
+    }
+}
+
+/// A single compressed tile of a [`TileGrid`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Tile {
+    /// Column of the tile in the grid.
+    pub column: usize,
+    /// Row of the tile in the grid.
+    pub row: usize,
+    /// Horizontal position of the tile in the whole image in pixels.
+    pub x: usize,
+    /// Vertical position of the tile in the whole image in pixels.
+    pub y: usize,
+    /// Width of the tile in pixels.
+    pub width: usize,
+    /// Height of the tile in pixels.
+    pub height: usize,
+    /// The compressed JPEG data of the tile.
+    pub data: OwnedBuf,
+}
+
+/// Layout of the tiles in a [`TileGrid`], before the tiles are compressed.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TileLayout {
+    width: usize,
+    height: usize,
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    rows: usize,
+}
+
+/// Position of a tile in a [`TileLayout`].
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct TileRect {
+    pub column: usize,
+    pub row: usize,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl TileLayout {
+    pub(crate) fn new(
+        width: usize,
+        height: usize,
+        tile_width: usize,
+        tile_height: usize,
+    ) -> Result<TileLayout> {
+        if tile_width == 0 {
+            return Err(Error::InvalidParam("tile_width"));
+        }
+        if tile_height == 0 {
+            return Err(Error::InvalidParam("tile_height"));
+        }
+        Ok(TileLayout {
+            width,
+            height,
+            tile_width,
+            tile_height,
+            columns: width.div_ceil(tile_width),
+            rows: height.div_ceil(tile_height),
+        })
+    }
+
+    /// Iterates over the tiles in row-major order.
+    pub(crate) fn tiles(self) -> impl Iterator<Item = TileRect> {
+        (0..self.rows).flat_map(move |row| {
+            (0..self.columns).map(move |column| {
+                let (x, y) = (column * self.tile_width, row * self.tile_height);
+                TileRect {
+                    column,
+                    row,
+                    x,
+                    y,
+                    width: usize::min(self.tile_width, self.width - x),
+                    height: usize::min(self.tile_height, self.height - y),
+                }
+            })
+        })
+    }
+
+    /// Assembles the grid from tiles compressed in the order of [`tiles()`][Self::tiles].
+    pub(crate) fn into_grid(self, data: Vec<OwnedBuf>) -> TileGrid {
+        let tiles = self
+            .tiles()
+            .zip(data)
+            .map(|(rect, data)| Tile {
+                column: rect.column,
+                row: rect.row,
+                x: rect.x,
+                y: rect.y,
+                width: rect.width,
+                height: rect.height,
+                data,
+            })
+            .collect();
+        TileGrid {
+            width: self.width,
+            height: self.height,
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            columns: self.columns,
+            rows: self.rows,
+            tiles,
+        }
+    }
+}
+
+impl TileRect {
+    /// Borrows the tile from `image` (like [`Image::region()`], but keeps the lifetime of the
+    /// pixels). If `bottom_up` is set, the rows of `image` are stored from bottom to top.
+    pub(crate) fn region(self, image: Image<&[u8]>, bottom_up: bool) -> Image<&[u8]> {
+        let Image {
+            pixels,
+            pitch,
+            height,
+            format,
+            ..
+        } = image;
+        let y = if bottom_up {
+            height - self.y - self.height
+        } else {
+            self.y
+        };
+        let offset = usize::min(y * pitch + self.x * format.size(), pixels.len());
+        Image {
+            pixels: &pixels[offset..],
+            width: self.width,
+            pitch,
+            height: self.height,
+            format,
+        }
+    }
+}
+
+/// Compress an image to JPEG.
+///
+/// Uses the given quality and chrominance subsampling option and returns the JPEG data in a buffer
+/// owned by TurboJPEG. If this function does not fit your needs, please see [`Compressor`].
+///
+/// # Example
+///
+/// ```
+/// // create an image (a Mandelbrot set visualization)
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+///
 /// // compress the image into JPEG with quality 75 and in grayscale
 /// // (we use as_deref() to convert from &Image<Vec<u8>> to Image<&[u8]>)
 /// let jpeg_data = turbojpeg::compress(image.as_deref(), 75, turbojpeg::Subsamp::Sub2x2)?;
@@ -372,6 +2741,94 @@ pub fn compress(image: Image<&[u8]>, quality: i32, subsamp: Subsamp) -> Result<O
     compressor.compress_to_owned(image)
 }
 
+/// Compress an image to a grayscale JPEG.
+///
+/// The image can be in any pixel format: color images are converted to grayscale, and grayscale
+/// images ([`PixelFormat::GRAY`]) are stored directly. The resulting JPEG image has a single
+/// component, which makes it considerably smaller than a color JPEG image of the same content.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::GRAY);
+/// let jpeg_data = turbojpeg::compress_gray(image.as_deref(), 80)?;
+///
+/// let header = turbojpeg::read_header(&jpeg_data)?;
+/// assert_eq!(header.subsamp, turbojpeg::Subsamp::Gray);
+/// assert_eq!(header.colorspace, turbojpeg::Colorspace::Gray);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn compress_gray(image: Image<&[u8]>, quality: i32) -> Result<OwnedBuf> {
+    compress(image, quality, Subsamp::Gray)
+}
+
+/// Compress an image to JPEG and write it into a file.
+///
+/// Uses the given quality and chrominance subsampling option, like [`compress()`]. The file is
+/// written atomically: the JPEG data is first written into a new temporary file in the same
+/// directory and flushed to the disk, and the temporary file is then renamed to `path`, so readers
+/// never observe a partially written file. Every call uses a different temporary file, so
+/// concurrent calls can write the same `path` (the last rename wins). If the file already exists,
+/// it is replaced.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+/// let path = std::env::temp_dir().join("tj_compress_file.jpg");
+/// turbojpeg::compress_file(image.as_deref(), &path, 80, turbojpeg::Subsamp::Sub2x2)?;
+///
+/// let header = turbojpeg::read_header(&std::fs::read(&path)?)?;
+/// assert_eq!((header.width, header.height), (500, 500));
+///
+/// // concurrent writers do not interfere with each other
+/// std::thread::scope(|scope| {
+///     let writers: Vec<_> = [50, 60, 70, 80]
+///         .into_iter()
+///         .map(|quality| {
+///             let (image, path) = (image.as_deref(), &path);
+///             scope.spawn(move || {
+///                 turbojpeg::compress_file(image, path, quality, turbojpeg::Subsamp::Sub2x2)
+///             })
+///         })
+///         .collect();
+///     writers.into_iter().try_for_each(|writer| writer.join().unwrap())
+/// })?;
+/// let header = turbojpeg::read_header(&std::fs::read(&path)?)?;
+/// assert_eq!((header.width, header.height), (500, 500));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn compress_file<P: AsRef<std::path::Path>>(
+    image: Image<&[u8]>,
+    path: P,
+    quality: i32,
+    subsamp: Subsamp,
+) -> Result<()> {
+    let jpeg_data = compress(image, quality, subsamp)?;
+
+    let path = path.as_ref();
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    // the counter distinguishes concurrent calls in this process
+    static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let counter = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    tmp_name.push(format!(".{}.{}.tmp", std::process::id(), counter));
+    let tmp_path = path.with_file_name(tmp_name);
+
+    // `create_new` never clobbers a file that is not ours (such as a leftover of another process)
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp_path)?;
+    let res = file.write_all(&jpeg_data).and_then(|_| file.sync_all());
+    drop(file);
+    let res = res.and_then(|_| std::fs::rename(&tmp_path, path));
+    if res.is_err() {
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    Ok(res?)
+}
+
 /// Compress a YUV image to JPEG.
 ///
 /// Uses the given quality and returns the JPEG data in a buffer owned by TurboJPEG. If this
@@ -399,6 +2856,34 @@ pub fn compress_yuv(image: YuvImage<&[u8]>, quality: i32) -> Result<OwnedBuf> {
     compressor.compress_yuv_to_owned(image)
 }
 
+/// Convert a packed RGB (or grayscale) image into a YUV image.
+///
+/// Returns a newly allocated YUV image with the given chrominance subsampling and row alignment
+/// of 4 (the same layout as [`decompress_to_yuv()`][crate::decompress_to_yuv]). See
+/// [`Compressor::encode_yuv()`] for details.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(640, 480, turbojpeg::PixelFormat::BGRA);
+/// let yuv_image = turbojpeg::encode_yuv(image.as_deref(), turbojpeg::Subsamp::Sub2x2)?;
+/// assert_eq!(yuv_image.y_size(), (640, 480));
+/// assert_eq!(yuv_image.uv_size(), (320, 240));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn encode_yuv(image: Image<&[u8]>, subsamp: Subsamp) -> Result<YuvImage<Vec<u8>>> {
+    let align = 4;
+    let mut yuv_image = YuvImage {
+        pixels: vec![0; yuv_pixels_len(image.width, align, image.height, subsamp)?],
+        width: image.width,
+        align,
+        height: image.height,
+        subsamp,
+    };
+    Compressor::new()?.encode_yuv(image, yuv_image.as_deref_mut())?;
+    Ok(yuv_image)
+}
+
 /// Compute the maximum size of a compressed image.
 ///
 /// This depends on image `width` and `height` and also on the chrominance subsampling method.
@@ -419,3 +2904,179 @@ pub fn compressed_buf_len(width: usize, height: usize, subsamp: Subsamp) -> Resu
         .map_err(|_| Error::IntegerOverflow("buf len"))?;
     Ok(len)
 }
+
+/// Compresses exactly `height` `rows` of pixels in `format` with a started `handle`.
+#[cfg(feature = "libjpeg")]
+fn write_rows<I>(
+    handle: &mut RowsHandle,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    rows: I,
+) -> Result<()>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let pitch = width
+        .checked_mul(format.size())
+        .ok_or(Error::IntegerOverflow("pitch"))?;
+    let mut row_count = 0;
+    for row in rows {
+        let row = row.as_ref();
+        if row_count == height || row.len() < pitch {
+            return Err(Error::InvalidParam("rows"));
+        }
+        handle.write(row)?;
+        row_count += 1;
+    }
+    if row_count != height {
+        return Err(Error::InvalidParam("rows"));
+    }
+    Ok(())
+}
+
+/// Destination of [`Compressor::compress_to_writer()`].
+#[cfg(feature = "libjpeg")]
+struct WriterSink<'w> {
+    writer: &'w mut dyn std::io::Write,
+    written: usize,
+    error: Option<std::io::Error>,
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+#[cfg(feature = "libjpeg")]
+unsafe extern "C" fn write_callback(
+    ctx: *mut libc::c_void,
+    data: *const libc::c_uchar,
+    len: ffi::size_t,
+) -> libc::c_int {
+    let sink = &mut *(ctx as *mut WriterSink);
+    if sink.error.is_some() || sink.panic.is_some() {
+        return -1;
+    }
+
+    let data = std::slice::from_raw_parts(data, len as usize);
+    // unwinding across the FFI boundary is undefined behavior, so the panic is resumed after
+    // libjpeg returns
+    let writer = &mut *sink.writer;
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| writer.write_all(data))) {
+        Ok(Ok(())) => {
+            sink.written += data.len();
+            0
+        }
+        Ok(Err(err)) => {
+            sink.error = Some(err);
+            -1
+        }
+        Err(panic) => {
+            sink.panic = Some(panic);
+            -1
+        }
+    }
+}
+
+/// Choose chrominance subsampling that suits the content of the `image`.
+///
+/// 4:2:0 subsampling ([`Subsamp::Sub2x2`]) halves the amount of color data, which is invisible in
+/// photographs but blurs sharp color edges, such as colored text and lines in screenshots and
+/// user interfaces. This function measures how much chrominance detail would be lost: for every
+/// 2x2 block of pixels, it computes the deviation of the chrominance of each pixel from the block
+/// average. If a noticeable fraction of the blocks contains strong chrominance edges,
+/// [`Subsamp::None`] is returned, otherwise [`Subsamp::Sub2x2`].
+///
+/// [`PixelFormat::GRAY`] images return [`Subsamp::Gray`] and [`PixelFormat::CMYK`] images are
+/// never subsampled ([`Subsamp::None`]). The choice is also used by
+/// [`Compressor::set_auto_subsamp()`].
+///
+/// # Example
+///
+/// ```
+/// // smooth gradient, as in photographs
+/// let mut image = turbojpeg::Image {
+///     pixels: vec![0; 64 * 64 * 3],
+///     width: 64,
+///     pitch: 64 * 3,
+///     height: 64,
+///     format: turbojpeg::PixelFormat::RGB,
+/// };
+/// for (i, pixel) in image.pixels.chunks_exact_mut(3).enumerate() {
+///     let (x, y) = ((i % 64) as u8, (i / 64) as u8);
+///     pixel.copy_from_slice(&[2 * x, 2 * y, 128]);
+/// }
+/// assert_eq!(turbojpeg::choose_subsamp(image.as_deref()), turbojpeg::Subsamp::Sub2x2);
+///
+/// // one-pixel wide blue lines
+/// for (i, pixel) in image.pixels.chunks_exact_mut(3).enumerate() {
+///     if (i / 64) % 3 == 0 {
+///         pixel.copy_from_slice(&[0, 0, 255]);
+///     }
+/// }
+/// assert_eq!(turbojpeg::choose_subsamp(image.as_deref()), turbojpeg::Subsamp::None);
+/// ```
+pub fn choose_subsamp(image: Image<&[u8]>) -> Subsamp {
+    image.assert_valid(image.pixels.len());
+    let Image {
+        pixels,
+        width,
+        pitch,
+        height,
+        format,
+    } = image;
+    let [r, g, b] = match format {
+        PixelFormat::GRAY => return Subsamp::Gray,
+        PixelFormat::CMYK => return Subsamp::None,
+        PixelFormat::RGB | PixelFormat::RGBX | PixelFormat::RGBA => [0, 1, 2],
+        PixelFormat::BGR | PixelFormat::BGRX | PixelFormat::BGRA => [2, 1, 0],
+        PixelFormat::XRGB | PixelFormat::ARGB => [1, 2, 3],
+        PixelFormat::XBGR | PixelFormat::ABGR => [3, 2, 1],
+    };
+    let pixel_size = format.size();
+
+    // chrominance of a pixel in fixed point (scaled by 2^16), as in the JPEG YCbCr conversion
+    let chroma = |x: usize, y: usize| {
+        let pixel = &pixels[y * pitch + x * pixel_size..];
+        let (r, g, b) = (pixel[r] as i32, pixel[g] as i32, pixel[b] as i32);
+        let cb = -11059 * r - 21709 * g + 32768 * b;
+        let cr = 32768 * r - 27439 * g - 5329 * b;
+        [cb, cr]
+    };
+
+    let (mut blocks, mut sharp_blocks) = (0usize, 0usize);
+    for y in (0..height / 2 * 2).step_by(2) {
+        for x in (0..width / 2 * 2).step_by(2) {
+            let block = [
+                chroma(x, y),
+                chroma(x + 1, y),
+                chroma(x, y + 1),
+                chroma(x + 1, y + 1),
+            ];
+            let max_dev = (0..2)
+                .map(|c| {
+                    let mean = block.iter().map(|sample| sample[c]).sum::<i32>() / 4;
+                    block
+                        .iter()
+                        .map(|sample| (sample[c] - mean).abs())
+                        .max()
+                        .unwrap()
+                })
+                .max()
+                .unwrap();
+            blocks += 1;
+            if max_dev > SHARP_CHROMA_DEVIATION << 16 {
+                sharp_blocks += 1;
+            }
+        }
+    }
+
+    if sharp_blocks * SHARP_BLOCKS_RATIO > blocks {
+        Subsamp::None
+    } else {
+        Subsamp::Sub2x2
+    }
+}
+
+/// Chrominance deviation from the 2x2 block average that is considered a sharp edge.
+const SHARP_CHROMA_DEVIATION: i32 = 24;
+/// Subsampling is disabled if more than one in this many 2x2 blocks contains a sharp edge.
+const SHARP_BLOCKS_RATIO: usize = 64;
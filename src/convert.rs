@@ -0,0 +1,305 @@
+use crate::common::{Error, PixelFormat, Result};
+use crate::Image;
+use std::ops::{Deref, DerefMut};
+
+/// Index into the extended source pixel that always contains 255 (used for alpha and padding).
+const OPAQUE: usize = 4;
+
+impl<T> Image<T> {
+    /// Converts the pixels into another packed RGB format, writing them into `output`.
+    ///
+    /// The conversion reorders the color channels (for example RGB to BGR), adds an alpha or
+    /// padding channel (which is set to 255) or strips it. It supports all formats except
+    /// [`PixelFormat::GRAY`] and [`PixelFormat::CMYK`], for which it returns
+    /// [`Error::InvalidParam`]. The conversions between 4-byte formats are byte permutations of
+    /// 32-bit pixels (such as [`u32::swap_bytes()`] or [`u32::rotate_left()`]), and the conversions
+    /// from or to 3-byte formats (such as RGB → BGRA or RGBA → RGB) use a channel order that is
+    /// fixed at compile time, so that the compiler can vectorize all of them (depending on the
+    /// target features enabled with `-C target-cpu`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `output` does not have the same width and height as `self`, or if one of the
+    /// images is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut bgra = turbojpeg::Image::alloc(64, 48, turbojpeg::PixelFormat::BGRA);
+    /// image.convert_into(bgra.as_deref_mut())?;
+    ///
+    /// let [r, g, b] = *image.pixel(20, 30) else { unreachable!() };
+    /// assert_eq!(bgra.pixel(20, 30), &[b, g, r, 255]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_into(&self, mut output: Image<&mut [u8]>) -> Result<()>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        output.assert_valid(output.pixels.len());
+        assert!(
+            (output.width, output.height) == (self.width, self.height),
+            "output image {}x{} does not have the size of the input image {}x{}",
+            output.width,
+            output.height,
+            self.width,
+            self.height
+        );
+
+        let map = channel_map(self.format, output.format)?;
+        let (src_size, dst_size) = (self.format.size(), output.format.size());
+        let permutation = permutation_u32(map);
+        for (src, dst) in self.rows().zip(output.rows_mut()) {
+            match (src_size, dst_size, permutation) {
+                (4, 4, Some((permutation, opaque))) => {
+                    convert_row_u32(src, dst, permutation, opaque)
+                }
+                (3, 3, _) => convert_row_rgb::<3, 3>(src, dst, map),
+                (3, 4, _) => convert_row_rgb::<3, 4>(src, dst, map),
+                (4, 3, _) => convert_row_rgb::<4, 3>(src, dst, map),
+                (4, 4, None) => convert_row::<4, 4>(src, dst, map),
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the pixels into another packed RGB format, returning a new image.
+    ///
+    /// See [`convert_into()`][Self::convert_into] for the supported formats.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::ARGB);
+    /// let rgb = image.convert(turbojpeg::PixelFormat::RGB)?;
+    /// assert_eq!(rgb.pitch, 64 * 3);
+    /// assert_eq!(rgb.pixel(10, 10), &image.pixel(10, 10)[1..]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert(&self, format: PixelFormat) -> Result<Image<Vec<u8>>>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        let mut output = Image::alloc(self.width, self.height, format);
+        self.convert_into(output.as_deref_mut())?;
+        Ok(output)
+    }
+
+    /// Converts the pixels into another packed RGB format of the same pixel size in place.
+    ///
+    /// This can swap the red and blue channels (RGB ↔ BGR, RGBA ↔ BGRA, ...) or move the alpha
+    /// channel (RGBA ↔ ARGB, ...) without allocating a new image. The `format` of the image is
+    /// updated. If the pixel sizes differ, use [`convert()`][Self::convert] instead; in that case
+    /// (and for the formats not supported by [`convert_into()`][Self::convert_into]), this
+    /// returns [`Error::InvalidParam`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGBA);
+    /// let [r, g, b, a] = *image.pixel(5, 6) else { unreachable!() };
+    ///
+    /// image.convert_in_place(turbojpeg::PixelFormat::BGRA)?;
+    /// assert_eq!(image.format, turbojpeg::PixelFormat::BGRA);
+    /// assert_eq!(image.pixel(5, 6), &[b, g, r, a]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn convert_in_place(&mut self, format: PixelFormat) -> Result<()>
+    where
+        T: DerefMut<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        if format.size() != self.format.size() {
+            return Err(Error::InvalidParam("format"));
+        }
+
+        let map = channel_map(self.format, format)?;
+        let size = format.size();
+        let permutation = permutation_u32(map);
+        for row in self.rows_mut() {
+            match (size, permutation) {
+                (4, Some((permutation, opaque))) => convert_row_in_blocks::<4>(row, |src, dst| {
+                    convert_row_u32(src, dst, permutation, opaque)
+                }),
+                (3, _) => convert_row_in_blocks::<3>(row, |src, dst| {
+                    convert_row_rgb::<3, 3>(src, dst, map)
+                }),
+                (4, None) => convert_row_in_place::<4>(row, map),
+                _ => unreachable!(),
+            }
+        }
+        self.format = format;
+        Ok(())
+    }
+}
+
+/// Returns the offsets of the red, green and blue channels and of the alpha channel (if any).
+fn channels(format: PixelFormat) -> Result<([usize; 3], Option<usize>)> {
+    match (
+        format.red_offset(),
+        format.green_offset(),
+        format.blue_offset(),
+    ) {
+        (Some(r), Some(g), Some(b)) => Ok(([r, g, b], format.alpha_offset())),
+        _ => Err(Error::InvalidParam("format")),
+    }
+}
+
+/// Returns the index of the source byte (or [`OPAQUE`]) for every byte of the destination pixel.
+fn channel_map(src: PixelFormat, dst: PixelFormat) -> Result<[usize; 4]> {
+    let (src_rgb, src_alpha) = channels(src)?;
+    let (dst_rgb, dst_alpha) = channels(dst)?;
+    let mut map = [OPAQUE; 4];
+    for (&src_offset, &dst_offset) in src_rgb.iter().zip(&dst_rgb) {
+        map[dst_offset] = src_offset;
+    }
+    if let (Some(src_alpha), Some(dst_alpha)) = (src_alpha, dst_alpha) {
+        map[dst_alpha] = src_alpha;
+    }
+    Ok(map)
+}
+
+fn convert_row<const S: usize, const D: usize>(src: &[u8], dst: &mut [u8], map: [usize; 4]) {
+    for (src, dst) in src.chunks_exact(S).zip(dst.chunks_exact_mut(D)) {
+        let mut pixel = [255; 5];
+        pixel[..S].copy_from_slice(src);
+        for (dst, &index) in dst.iter_mut().zip(&map) {
+            *dst = pixel[index];
+        }
+    }
+}
+
+fn convert_row_in_place<const S: usize>(row: &mut [u8], map: [usize; 4]) {
+    for dst in row.chunks_exact_mut(S) {
+        let mut pixel = [255; 5];
+        pixel[..S].copy_from_slice(dst);
+        for (dst, &index) in dst.iter_mut().zip(&map) {
+            *dst = pixel[index];
+        }
+    }
+}
+
+/// Converts a row from or to a 3-byte format. The channel maps that occur between the packed RGB
+/// formats are dispatched to [`convert_row_const()`].
+fn convert_row_rgb<const S: usize, const D: usize>(src: &[u8], dst: &mut [u8], map: [usize; 4]) {
+    match map {
+        [0, 1, 2, OPAQUE] => convert_row_const::<S, D, 0, 1, 2, OPAQUE>(src, dst),
+        [2, 1, 0, OPAQUE] => convert_row_const::<S, D, 2, 1, 0, OPAQUE>(src, dst),
+        // RGB/BGR → XRGB/XBGR/ARGB/ABGR
+        [OPAQUE, 0, 1, 2] if S == 3 => convert_row_const::<S, D, OPAQUE, 0, 1, 2>(src, dst),
+        [OPAQUE, 2, 1, 0] if S == 3 => convert_row_const::<S, D, OPAQUE, 2, 1, 0>(src, dst),
+        // XRGB/XBGR/ARGB/ABGR → RGB/BGR
+        [1, 2, 3, OPAQUE] if S == 4 => convert_row_const::<S, D, 1, 2, 3, OPAQUE>(src, dst),
+        [3, 2, 1, OPAQUE] if S == 4 => convert_row_const::<S, D, 3, 2, 1, OPAQUE>(src, dst),
+        _ => convert_row::<S, D>(src, dst, map),
+    }
+}
+
+/// Returns the source byte `M` of a pixel, or 255 if `M` is [`OPAQUE`].
+#[inline(always)]
+fn channel<const M: usize>(src: &[u8]) -> u8 {
+    if M == OPAQUE {
+        255
+    } else {
+        src[M]
+    }
+}
+
+/// Converts `S`-byte pixels into `D`-byte pixels with a channel map that is known at compile
+/// time, so that the compiler can turn the loop into vector shuffles.
+fn convert_row_const<
+    const S: usize,
+    const D: usize,
+    const M0: usize,
+    const M1: usize,
+    const M2: usize,
+    const M3: usize,
+>(
+    src: &[u8],
+    dst: &mut [u8],
+) {
+    for (src, dst) in src.chunks_exact(S).zip(dst.chunks_exact_mut(D)) {
+        dst[0] = channel::<M0>(src);
+        dst[1] = channel::<M1>(src);
+        dst[2] = channel::<M2>(src);
+        if D == 4 {
+            dst[3] = channel::<M3>(src);
+        }
+    }
+}
+
+/// Converts the row in blocks that are copied to the stack, so that `convert` can read and write
+/// different buffers.
+fn convert_row_in_blocks<const S: usize>(row: &mut [u8], convert: impl Fn(&[u8], &mut [u8])) {
+    for chunk in row.chunks_mut(16 * S) {
+        let mut block = [0; 64];
+        let block = &mut block[..chunk.len()];
+        block.copy_from_slice(chunk);
+        convert(block, chunk);
+    }
+}
+
+// Byte permutations of 4-byte pixels, which cover the conversions between all 4-byte formats.
+const IDENTITY: u8 = 0;
+const SWAP_0_2: u8 = 1;
+const SWAP_1_3: u8 = 2;
+const ROTATE_LEFT: u8 = 3;
+const ROTATE_RIGHT: u8 = 4;
+const REVERSE: u8 = 5;
+
+/// Loads a 4-byte pixel as a little-endian `u32` with the bytes permuted by `permutation`.
+#[inline(always)]
+fn load_permuted(permutation: u8, pixel: &[u8]) -> u32 {
+    let value = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+    match permutation {
+        SWAP_0_2 => u32::from_le_bytes([pixel[2], pixel[1], pixel[0], pixel[3]]),
+        SWAP_1_3 => u32::from_le_bytes([pixel[0], pixel[3], pixel[2], pixel[1]]),
+        ROTATE_LEFT => value.rotate_left(8),
+        ROTATE_RIGHT => value.rotate_right(8),
+        REVERSE => value.swap_bytes(),
+        _ => value,
+    }
+}
+
+/// Returns the byte permutation that implements the channel `map` for 4-byte pixels, and the mask
+/// of the destination bytes that are set to 255.
+fn permutation_u32(map: [usize; 4]) -> Option<(u8, u32)> {
+    let opaque = (0..4)
+        .filter(|&i| map[i] == OPAQUE)
+        .fold(0, |mask, i| mask | 0xff << (8 * i));
+    let permutation = [
+        IDENTITY,
+        SWAP_0_2,
+        SWAP_1_3,
+        ROTATE_LEFT,
+        ROTATE_RIGHT,
+        REVERSE,
+    ]
+    .into_iter()
+    .find(|&permutation| {
+        // the bytes that are set to 255 can come from any source byte
+        let bytes = load_permuted(permutation, &[0, 1, 2, 3]).to_le_bytes();
+        (0..4).all(|i| map[i] == OPAQUE || map[i] == bytes[i] as usize)
+    })?;
+    Some((permutation, opaque))
+}
+
+fn convert_row_u32(src: &[u8], dst: &mut [u8], permutation: u8, opaque: u32) {
+    match permutation {
+        SWAP_0_2 => convert_row_permuted::<SWAP_0_2>(src, dst, opaque),
+        SWAP_1_3 => convert_row_permuted::<SWAP_1_3>(src, dst, opaque),
+        ROTATE_LEFT => convert_row_permuted::<ROTATE_LEFT>(src, dst, opaque),
+        ROTATE_RIGHT => convert_row_permuted::<ROTATE_RIGHT>(src, dst, opaque),
+        REVERSE => convert_row_permuted::<REVERSE>(src, dst, opaque),
+        _ => convert_row_permuted::<IDENTITY>(src, dst, opaque),
+    }
+}
+
+fn convert_row_permuted<const P: u8>(src: &[u8], dst: &mut [u8], opaque: u32) {
+    for (src, dst) in src.chunks_exact(4).zip(dst.chunks_exact_mut(4)) {
+        dst.copy_from_slice(&(load_permuted(P, src) | opaque).to_le_bytes());
+    }
+}
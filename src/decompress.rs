@@ -1,6 +1,6 @@
 use crate::common::{Colorspace, Error, PixelFormat, Result, Subsamp};
 use crate::handle::Handle;
-use crate::{ffi, Image, YuvImage};
+use crate::{ffi, markers, Image, YuvImage};
 use std::convert::TryInto as _;
 
 /// Decompresses JPEG data into ffi pixels.
@@ -8,6 +8,10 @@ use std::convert::TryInto as _;
 #[doc(alias = "tjhandle")]
 pub struct Decompressor {
     handle: Handle,
+    comments: Vec<String>,
+    save_comments: bool,
+    icc_profile: Option<Vec<u8>>,
+    save_icc_profile: bool,
 }
 
 unsafe impl Send for Decompressor {}
@@ -34,7 +38,13 @@ impl Decompressor {
     #[doc(alias = "tj3Init")]
     pub fn new() -> Result<Decompressor> {
         let handle = Handle::new(ffi::TJINIT_TJINIT_DECOMPRESS)?;
-        Ok(Self { handle })
+        Ok(Self {
+            handle,
+            comments: Vec::new(),
+            save_comments: false,
+            icc_profile: None,
+            save_icc_profile: false,
+        })
     }
 
     /// Read the JPEG header without decompressing the image.
@@ -56,35 +66,103 @@ impl Decompressor {
     /// ```
     #[doc(alias = "tj3DecompressHeader")]
     pub fn read_header(&mut self, jpeg_data: &[u8]) -> Result<DecompressHeader> {
-        let jpeg_data_len = jpeg_data
-            .len()
-            .try_into()
-            .map_err(|_| Error::IntegerOverflow("jpeg_data.len()"))?;
-        let res = unsafe {
-            ffi::tj3DecompressHeader(self.handle.as_ptr(), jpeg_data.as_ptr(), jpeg_data_len)
-        };
-        if res != 0 {
-            return Err(self.handle.get_error());
+        self.icc_profile = None;
+        if self.save_icc_profile {
+            // TurboJPEG keeps the profile extracted by a previous header (which may have been read
+            // by one of the decompress methods) if the new image has no profile, so discard it
+            // first
+            self.handle.take_icc_profile()?;
+        }
+        let header = read_header_with(&mut self.handle, jpeg_data)?;
+        if self.save_icc_profile {
+            self.icc_profile = self.handle.take_icc_profile()?;
         }
 
-        let width = self
-            .handle
-            .get(ffi::TJPARAM_TJPARAM_JPEGWIDTH)
-            .try_into()
-            .map_err(|_| Error::IntegerOverflow("width"))?;
-        let height = self
-            .handle
-            .get(ffi::TJPARAM_TJPARAM_JPEGHEIGHT)
-            .try_into()
-            .map_err(|_| Error::IntegerOverflow("height"))?;
-        let subsamp = Subsamp::from_int(self.handle.get(ffi::TJPARAM_TJPARAM_SUBSAMP))?;
-        let colorspace = Colorspace::from_int(self.handle.get(ffi::TJPARAM_TJPARAM_COLORSPACE))?;
-        Ok(DecompressHeader {
-            width,
-            height,
-            subsamp,
-            colorspace,
-        })
+        self.comments.clear();
+        if self.save_comments {
+            for segment in markers::segments(jpeg_data) {
+                match segment {
+                    Ok(segment) if segment.marker == markers::COM => {
+                        let comment = String::from_utf8_lossy(segment.payload);
+                        self.comments.push(comment.into_owned());
+                    }
+                    Ok(segment) if segment.marker != markers::SOS => {}
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(header)
+    }
+
+    /// Enable/disable collecting the comments (COM segments) when reading a header.
+    ///
+    /// When enabled, [`read_header()`][Self::read_header] scans the segments of the image and
+    /// stores its comments, which can then be obtained with [`comments()`][Self::comments]. This
+    /// is disabled by default, so that reading a header does not allocate the comments when they
+    /// are not needed.
+    pub fn set_save_comments(&mut self, save_comments: bool) {
+        self.save_comments = save_comments;
+    }
+
+    /// Get the comments (COM segments) of the JPEG image whose header was read last.
+    ///
+    /// The comments are collected by [`read_header()`][Self::read_header] only if enabled with
+    /// [`set_save_comments()`][Self::set_save_comments], otherwise this is empty. Comments that
+    /// are not valid UTF-8 are converted lossily.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut decompressor = turbojpeg::Decompressor::new()?;
+    /// decompressor.set_save_comments(true);
+    /// decompressor.read_header(&jpeg_data)?;
+    /// assert!(decompressor.comments().is_empty());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// Enable/disable extracting the ICC color management profile when reading a header.
+    ///
+    /// When enabled, [`read_header()`][Self::read_header] copies the profile out of TurboJPEG, so
+    /// that it can be obtained with [`icc_profile()`][Self::icc_profile]. This is disabled by
+    /// default, so that reading a header does not copy a profile that is not needed.
+    #[doc(alias = "tj3GetICCProfile")]
+    pub fn set_save_icc_profile(&mut self, save_icc_profile: bool) {
+        self.save_icc_profile = save_icc_profile;
+    }
+
+    /// Get the ICC color management profile of the JPEG image whose header was read last.
+    ///
+    /// The profile is extracted from the APP2 segments by [`read_header()`][Self::read_header]
+    /// only if enabled with [`set_save_icc_profile()`][Self::set_save_icc_profile]. Returns `None`
+    /// if the image does not contain a profile or if extracting it is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut decompressor = turbojpeg::Decompressor::new()?;
+    /// decompressor.set_save_icc_profile(true);
+    /// decompressor.read_header(&jpeg_data)?;
+    ///
+    /// // the image contains an sRGB profile
+    /// let icc_profile = decompressor.icc_profile().unwrap();
+    /// assert_eq!(&icc_profile[36..40], b"acsp");
+    ///
+    /// // the profile is cleared when reading the header of an image without a profile
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// decompressor.read_header(&jpeg_data)?;
+    /// assert_eq!(decompressor.icc_profile(), None);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3GetICCProfile")]
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.icc_profile.as_deref()
     }
 
     /// Decompress a JPEG image in `jpeg_data` into `output`.
@@ -175,6 +253,95 @@ impl Decompressor {
         Ok(())
     }
 
+    /// Decompress a JPEG image with 12-bit precision in `jpeg_data` into `output`.
+    ///
+    /// Every sample of the `output` image is stored in one `u16` (with values smaller than 4096),
+    /// and the [pitch][Image::pitch] of the image is measured in samples, not in bytes. Apart
+    /// from that, this works like [`decompress()`][Self::decompress]. JPEG images with 8-bit
+    /// precision cannot be decompressed with this method (and vice versa).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB)
+    ///     .map_pixels(|pixels| pixels.into_iter().map(|x| (x as u16) << 4).collect::<Vec<_>>());
+    /// let mut jpeg_data = turbojpeg::OutputBuf::new_owned();
+    /// turbojpeg::Compressor::new()?.compress_12bit(image.as_deref(), &mut jpeg_data)?;
+    ///
+    /// let mut output = turbojpeg::Image::alloc16(64, 48, turbojpeg::PixelFormat::RGB);
+    /// turbojpeg::Decompressor::new()?.decompress_12bit(&jpeg_data, output.as_deref_mut())?;
+    /// assert!(output.pixels.iter().all(|&sample| sample < 4096));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3Decompress12")]
+    pub fn decompress_12bit(&mut self, jpeg_data: &[u8], output: Image<&mut [u16]>) -> Result<()> {
+        self.decompress_samples(jpeg_data, output, 12)
+    }
+
+    /// Decompress a lossless JPEG image with 16-bit precision in `jpeg_data` into `output`.
+    ///
+    /// The [pitch][Image::pitch] of the `output` image is measured in samples, not in bytes. See
+    /// [`Compressor::compress_16bit()`][crate::Compressor::compress_16bit] for an example.
+    #[doc(alias = "tj3Decompress16")]
+    pub fn decompress_16bit(&mut self, jpeg_data: &[u8], output: Image<&mut [u16]>) -> Result<()> {
+        self.decompress_samples(jpeg_data, output, 16)
+    }
+
+    /// Decompresses an image with 12-bit or 16-bit samples (`precision`).
+    fn decompress_samples(
+        &mut self,
+        jpeg_data: &[u8],
+        output: Image<&mut [u16]>,
+        precision: u8,
+    ) -> Result<()> {
+        output.assert_valid(output.pixels.len());
+        let Image {
+            pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = output;
+        let pitch: libc::c_int = pitch
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("pitch"))?;
+
+        let header = read_header_with(&mut self.handle, jpeg_data)?;
+        if width < header.width || height < header.height {
+            return Err(Error::OutputTooSmall(
+                header.width as i32,
+                header.height as i32,
+            ));
+        }
+
+        let res = unsafe {
+            if precision == 12 {
+                ffi::tj3Decompress12(
+                    self.handle.as_ptr(),
+                    jpeg_data.as_ptr(),
+                    jpeg_data.len() as ffi::size_t,
+                    pixels.as_mut_ptr() as *mut libc::c_short,
+                    pitch,
+                    format as i32,
+                )
+            } else {
+                ffi::tj3Decompress16(
+                    self.handle.as_ptr(),
+                    jpeg_data.as_ptr(),
+                    jpeg_data.len() as ffi::size_t,
+                    pixels.as_mut_ptr(),
+                    pitch,
+                    format as i32,
+                )
+            }
+        };
+        if res != 0 {
+            return Err(self.handle.get_error());
+        }
+
+        Ok(())
+    }
+
     /// Decompress a JPEG image in `jpeg_data` into `output` as YUV without changing color space.
     ///
     /// The decompressed image is stored in the pixel data of the given `output` image, which must
@@ -226,40 +393,114 @@ impl Decompressor {
             height,
             subsamp: _,
         } = output;
-        let width: libc::c_int = width
-            .try_into()
-            .map_err(|_| Error::IntegerOverflow("width"))?;
         let align = align
             .try_into()
             .map_err(|_| Error::IntegerOverflow("align"))?;
-        let height: libc::c_int = height
-            .try_into()
-            .map_err(|_| Error::IntegerOverflow("height"))?;
         let jpeg_data_len = jpeg_data
             .len()
             .try_into()
             .map_err(|_| Error::IntegerOverflow("jpeg_data.len()"))?;
 
+        let header = read_header_with(&mut self.handle, jpeg_data)?;
+        if width < header.width || height < header.height {
+            return Err(Error::OutputTooSmall(
+                header.width as i32,
+                header.height as i32,
+            ));
+        }
+
         let res = unsafe {
-            ffi::tj3DecompressHeader(self.handle.as_ptr(), jpeg_data.as_ptr(), jpeg_data_len)
+            ffi::tj3DecompressToYUV8(
+                self.handle.as_ptr(),
+                jpeg_data.as_ptr(),
+                jpeg_data_len,
+                pixels.as_mut_ptr(),
+                align,
+            )
         };
         if res != 0 {
             return Err(self.handle.get_error());
         }
 
-        let jpeg_width = self.handle.get(ffi::TJPARAM_TJPARAM_JPEGWIDTH);
-        let jpeg_height = self.handle.get(ffi::TJPARAM_TJPARAM_JPEGHEIGHT);
-        if width < jpeg_width || height < jpeg_height {
-            return Err(Error::OutputTooSmall(jpeg_width as i32, jpeg_height as i32));
-        }
+        Ok(())
+    }
+
+    /// Convert a YUV image in `input` into the packed RGB (or grayscale) image `output`.
+    ///
+    /// This uses the color conversion and upsampling of TurboJPEG (accelerated with SIMD
+    /// instructions) without decompressing any JPEG data, so it can be used to convert YUV
+    /// images from other sources, such as cameras or video decoders.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `output` does not have the same width and height as the `input`, or if one
+    /// of the images is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 95, turbojpeg::Subsamp::None)?;
+    /// let yuv_image = turbojpeg::decompress_to_yuv(&jpeg_data)?;
+    ///
+    /// let mut decompressor = turbojpeg::Decompressor::new()?;
+    /// let mut rgb_image = turbojpeg::Image::alloc(64, 48, turbojpeg::PixelFormat::RGB);
+    /// decompressor.decode_yuv(yuv_image.as_deref(), rgb_image.as_deref_mut())?;
+    ///
+    /// // without chrominance subsampling, the result is the same as decompressing the JPEG into
+    /// // RGB (with subsampling, the chrominance may be upsampled slightly differently)
+    /// let decompressed = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::RGB)?;
+    /// assert_eq!(rgb_image.pixels, decompressed.pixels);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3DecodeYUV8")]
+    pub fn decode_yuv(&mut self, input: YuvImage<&[u8]>, output: Image<&mut [u8]>) -> Result<()> {
+        input.assert_valid(input.pixels.len());
+        output.assert_valid(output.pixels.len());
+        assert!(
+            (output.width, output.height) == (input.width, input.height),
+            "output image {}x{} does not have the size of the input image {}x{}",
+            output.width,
+            output.height,
+            input.width,
+            input.height
+        );
 
+        let Image {
+            pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = output;
+        let width: libc::c_int = width
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("width"))?;
+        let pitch: libc::c_int = pitch
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("pitch"))?;
+        let height: libc::c_int = height
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("height"))?;
+        let align = input
+            .align
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("align"))?;
+
+        self.handle.set(
+            ffi::TJPARAM_TJPARAM_SUBSAMP,
+            input.subsamp as i32 as libc::c_int,
+        )?;
         let res = unsafe {
-            ffi::tj3DecompressToYUV8(
+            ffi::tj3DecodeYUV8(
                 self.handle.as_ptr(),
-                jpeg_data.as_ptr(),
-                jpeg_data_len,
-                pixels.as_mut_ptr(),
+                input.pixels.as_ptr(),
                 align,
+                pixels.as_mut_ptr(),
+                width,
+                pitch,
+                height,
+                format as i32,
             )
         };
         if res != 0 {
@@ -270,6 +511,29 @@ impl Decompressor {
     }
 }
 
+/// Convert a YUV image into a packed RGB (or grayscale) image.
+///
+/// Returns a newly allocated image with the given pixel `format`. See
+/// [`Decompressor::decode_yuv()`] for details.
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let yuv_image = turbojpeg::decompress_to_yuv(&jpeg_data)?;
+///
+/// let image = turbojpeg::decode_yuv(yuv_image.as_deref(), turbojpeg::PixelFormat::BGRA)?;
+/// assert_eq!((image.width, image.height), (384, 256));
+/// assert_eq!(image.format, turbojpeg::PixelFormat::BGRA);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn decode_yuv(image: YuvImage<&[u8]>, format: PixelFormat) -> Result<Image<Vec<u8>>> {
+    let mut decompressor = Decompressor::new()?;
+    let mut output = Image::alloc(image.width, image.height, format);
+    decompressor.decode_yuv(image, output.as_deref_mut())?;
+    Ok(output)
+}
+
 /// Decompress a JPEG image.
 ///
 /// Returns a newly allocated image with the given pixel `format`. If you have specific
@@ -345,7 +609,8 @@ pub fn decompress_to_yuv(jpeg_data: &[u8]) -> Result<YuvImage<Vec<u8>>> {
 /// Determine size in bytes of a YUV image.
 ///
 /// Calculates the size for [`YuvImage::pixels`] based on the image width, height, chrominance
-/// subsampling and row alignment.
+/// subsampling and row alignment. Use [`yuv_plane_sizes()`] to get the sizes of the individual
+/// planes.
 ///
 /// Returns an error on integer overflow. You can just `.unwrap()` the result if you don't care
 /// about this edge case.
@@ -388,6 +653,106 @@ pub fn yuv_pixels_len(
     Ok(len)
 }
 
+/// Determine the row strides in bytes of the Y, U and V planes of a YUV image.
+///
+/// Each plane width is computed by TurboJPEG (which pads the image width to the horizontal
+/// subsampling factor) and then padded to the nearest multiple of `align`, which must be a power
+/// of 2. These are the strides used by [`YuvImage`] with the same `align`, and they can be used to
+/// allocate separate planes for [`YuvPlanes`][crate::YuvPlanes]. For [`Subsamp::Gray`], the
+/// strides of the U and V planes are 0.
+///
+/// Returns [`Error::InvalidParam`] if `align` is not a power of 2 or if TurboJPEG rejects the
+/// `width` or `subsamp`, and an error on integer overflow.
+///
+/// # Example
+///
+/// ```
+/// let strides = turbojpeg::yuv_plane_strides(101, turbojpeg::Subsamp::Sub2x2, 16)?;
+/// assert_eq!(strides, [112, 64, 64]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3YUVPlaneWidth")]
+pub fn yuv_plane_strides(width: usize, subsamp: Subsamp, align: usize) -> Result<[usize; 3]> {
+    if !align.is_power_of_two() {
+        return Err(Error::InvalidParam("align"));
+    }
+    let width = width
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("width"))?;
+
+    let mut strides = [0; 3];
+    for (component, stride) in strides.iter_mut().enumerate().take(yuv_planes(subsamp)) {
+        let plane_width = unsafe {
+            ffi::tj3YUVPlaneWidth(component as libc::c_int, width, subsamp as libc::c_int)
+        };
+        let plane_width: usize = plane_width
+            .try_into()
+            .map_err(|_| Error::InvalidParam("width"))?;
+        *stride = plane_width
+            .checked_next_multiple_of(align)
+            .ok_or(Error::IntegerOverflow("stride"))?;
+    }
+    Ok(strides)
+}
+
+/// Determine the sizes in bytes of the Y, U and V planes of a YUV image.
+///
+/// The rows of each plane are padded to the nearest multiple of `align` (see
+/// [`yuv_plane_strides()`]), so the sizes add up to [`yuv_pixels_len()`] with the same alignment.
+/// For [`Subsamp::Gray`], the sizes of the U and V planes are 0.
+///
+/// Returns [`Error::InvalidParam`] if `align` is not a power of 2 or if TurboJPEG rejects the
+/// `width`, `height` or `subsamp`, and an error on integer overflow.
+///
+/// # Example
+///
+/// ```
+/// use turbojpeg::Subsamp;
+/// let sizes = turbojpeg::yuv_plane_sizes(101, 75, Subsamp::Sub2x2, 16)?;
+/// assert_eq!(sizes, [112 * 76, 64 * 38, 64 * 38]);
+/// assert_eq!(sizes.iter().sum::<usize>(), turbojpeg::yuv_pixels_len(101, 16, 75, Subsamp::Sub2x2)?);
+///
+/// // allocate the planes separately
+/// let planes = sizes.map(|size| vec![0u8; size]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3YUVPlaneSize")]
+#[doc(alias = "tj3YUVPlaneHeight")]
+pub fn yuv_plane_sizes(
+    width: usize,
+    height: usize,
+    subsamp: Subsamp,
+    align: usize,
+) -> Result<[usize; 3]> {
+    let strides = yuv_plane_strides(width, subsamp, align)?;
+    let height = height
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("height"))?;
+
+    let mut sizes = [0; 3];
+    for (component, size) in sizes.iter_mut().enumerate().take(yuv_planes(subsamp)) {
+        let plane_height = unsafe {
+            ffi::tj3YUVPlaneHeight(component as libc::c_int, height, subsamp as libc::c_int)
+        };
+        let plane_height: usize = plane_height
+            .try_into()
+            .map_err(|_| Error::InvalidParam("height"))?;
+        *size = strides[component]
+            .checked_mul(plane_height)
+            .ok_or(Error::IntegerOverflow("plane size"))?;
+    }
+    Ok(sizes)
+}
+
+/// Number of planes of a YUV image with the given subsampling.
+fn yuv_planes(subsamp: Subsamp) -> usize {
+    if subsamp == Subsamp::Gray {
+        1
+    } else {
+        3
+    }
+}
+
 /// Read the JPEG header without decompressing the image.
 ///
 /// # Example
@@ -406,3 +771,60 @@ pub fn read_header(jpeg_data: &[u8]) -> Result<DecompressHeader> {
     let mut decompressor = Decompressor::new()?;
     decompressor.read_header(jpeg_data)
 }
+
+/// Reads the header of `jpeg_data` with `handle`, which may be a decompressor or a transformer.
+pub(crate) fn read_header_with(handle: &mut Handle, jpeg_data: &[u8]) -> Result<DecompressHeader> {
+    let jpeg_data_len = jpeg_data
+        .len()
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("jpeg_data.len()"))?;
+    let res =
+        unsafe { ffi::tj3DecompressHeader(handle.as_ptr(), jpeg_data.as_ptr(), jpeg_data_len) };
+    if res != 0 {
+        return Err(handle.get_error());
+    }
+
+    let width = handle
+        .get(ffi::TJPARAM_TJPARAM_JPEGWIDTH)
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("width"))?;
+    let height = handle
+        .get(ffi::TJPARAM_TJPARAM_JPEGHEIGHT)
+        .try_into()
+        .map_err(|_| Error::IntegerOverflow("height"))?;
+    let subsamp = Subsamp::from_int(handle.get(ffi::TJPARAM_TJPARAM_SUBSAMP))?;
+    let colorspace = Colorspace::from_int(handle.get(ffi::TJPARAM_TJPARAM_COLORSPACE))?;
+    Ok(DecompressHeader {
+        width,
+        height,
+        subsamp,
+        colorspace,
+    })
+}
+
+/// Read the ICC color management profile embedded in a JPEG image.
+///
+/// Returns `None` if the image does not contain a profile. Use
+/// [`Decompressor::set_save_icc_profile()`] and [`Decompressor::icc_profile()`] to obtain the
+/// profile together with the header.
+///
+/// # Example
+///
+/// ```
+/// # let icc_profile = vec![42u8; 100_000];
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// // large profiles are split into multiple APP2 segments
+/// compressor.set_icc_profile(&icc_profile)?;
+/// let jpeg_data = compressor.compress_to_owned(image.as_deref())?;
+///
+/// assert_eq!(turbojpeg::read_icc_profile(&jpeg_data)?, Some(icc_profile));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[doc(alias = "tj3GetICCProfile")]
+pub fn read_icc_profile(jpeg_data: &[u8]) -> Result<Option<Vec<u8>>> {
+    let mut decompressor = Decompressor::new()?;
+    decompressor.set_save_icc_profile(true);
+    decompressor.read_header(jpeg_data)?;
+    Ok(decompressor.icc_profile.take())
+}
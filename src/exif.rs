@@ -0,0 +1,392 @@
+use crate::common::{Error, Result};
+use crate::markers::{self, APP0, SOS};
+use crate::TransformOp;
+use std::ops::Range;
+
+/// The EXIF orientation tag.
+const ORIENTATION_TAG: u16 = 0x0112;
+/// The tag pointing to the GPS IFD.
+const GPS_IFD_TAG: u16 = 0x8825;
+/// The tag pointing to the Exif IFD.
+const EXIF_IFD_TAG: u16 = 0x8769;
+/// The tag with the width of the image in the Exif IFD.
+const PIXEL_X_DIMENSION_TAG: u16 = 0xa002;
+/// The tag with the height of the image in the Exif IFD.
+const PIXEL_Y_DIMENSION_TAG: u16 = 0xa003;
+/// The tag pointing to the JPEG thumbnail in IFD1.
+const THUMBNAIL_OFFSET_TAG: u16 = 0x0201;
+/// The tag with the length of the JPEG thumbnail in IFD1.
+const THUMBNAIL_LENGTH_TAG: u16 = 0x0202;
+/// The TIFF type of 16-bit unsigned integers.
+const SHORT_TYPE: u16 = 3;
+
+/// Header of the EXIF payload in an APP1 segment.
+pub(crate) const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+/// Location of the orientation value in a JPEG datastream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct OrientationField {
+    /// Offset of the 16-bit value in the datastream.
+    pub offset: usize,
+    /// Byte order of the TIFF structure.
+    pub big_endian: bool,
+    /// The orientation value.
+    pub value: u16,
+}
+
+/// Returns true if the segment is an EXIF segment (APP1 starting with `Exif\0\0`).
+pub(crate) fn is_exif(segment: &markers::Segment<'_>) -> bool {
+    segment.marker == APP0 + 1 && segment.payload.starts_with(EXIF_HEADER)
+}
+
+/// Finds the orientation tag in the EXIF segment of a JPEG datastream.
+///
+/// Returns `None` if the image has no EXIF segment or if the segment has no orientation tag in
+/// its first IFD. Malformed EXIF data are reported as [`Error::InvalidJpeg`].
+pub(crate) fn find_orientation(jpeg: &[u8]) -> Result<Option<OrientationField>> {
+    for segment in markers::segments(jpeg) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            break;
+        }
+        if !is_exif(&segment) {
+            continue;
+        }
+
+        // the payload runs to the end of the segment, whose marker may be preceded by fill bytes
+        let payload_offset = segment.offset + segment.len - segment.payload.len();
+        let tiff_offset = payload_offset + EXIF_HEADER.len();
+        let tiff = Tiff::new(&segment.payload[EXIF_HEADER.len()..])?;
+        let ifd0 = tiff.read_u32(4)?;
+        return Ok(tiff.find_entry(ifd0, ORIENTATION_TAG)?.and_then(|entry| {
+            let value = tiff.read_u16(entry + 8).ok()?;
+            (tiff.read_u16(entry + 2).ok()? == SHORT_TYPE).then_some(OrientationField {
+                offset: tiff_offset + entry + 8,
+                big_endian: tiff.big_endian,
+                value,
+            })
+        }));
+    }
+    Ok(None)
+}
+
+/// Read the EXIF orientation of a JPEG image.
+///
+/// The orientation tag is read from the first IFD of the EXIF segment (APP1) by a minimal TIFF
+/// parser, without decompressing the image. It is returned as the [`TransformOp`] that makes the
+/// image upright (see [`TransformOp::from_exif_orientation()`]), so [`TransformOp::None`] means
+/// that the image is already upright; use [`TransformOp::to_exif_orientation()`] to obtain the
+/// numeric value of the tag.
+///
+/// Returns `None` if the image has no EXIF segment, if the segment has no orientation tag, or if
+/// the tag has an invalid value. Malformed EXIF data are reported as [`Error::InvalidJpeg`].
+///
+/// # Example
+///
+/// ```
+/// use turbojpeg::TransformOp;
+///
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// assert_eq!(turbojpeg::exif_orientation(&jpeg_data)?, Some(TransformOp::None));
+///
+/// // EXIF data with orientation 6 (the camera was rotated by 90 degrees): a big-endian TIFF
+/// // header and an IFD with one SHORT entry for the orientation tag (0x0112)
+/// let tiff_header = b"MM\0\x2a\0\0\0\x08";
+/// let ifd = b"\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0";
+/// let exif = [&b"Exif\0\0"[..], tiff_header, ifd].concat();
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// compressor.add_app_marker(1, &exif)?;
+/// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+/// let op = turbojpeg::exif_orientation(&jpeg_data)?;
+/// assert_eq!(op, Some(TransformOp::Rot90));
+/// assert_eq!(op.unwrap().to_exif_orientation(), 6);
+///
+/// // images without EXIF data have no orientation
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+/// assert_eq!(turbojpeg::exif_orientation(&jpeg_data)?, None);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn exif_orientation(jpeg_data: &[u8]) -> Result<Option<TransformOp>> {
+    let field = find_orientation(jpeg_data)?;
+    Ok(field.and_then(|field| TransformOp::from_exif_orientation(field.value)))
+}
+
+/// Overwrites the orientation value in a JPEG datastream.
+pub(crate) fn write_orientation(jpeg: &mut [u8], field: OrientationField, value: u16) {
+    let bytes = if field.big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    jpeg[field.offset..field.offset + 2].copy_from_slice(&bytes);
+}
+
+/// Removes the GPS data and/or the thumbnail image from the payload of an EXIF segment.
+///
+/// The GPS IFD is unlinked from IFD0 and overwritten with zeros together with its values. The
+/// thumbnail is removed by unlinking IFD1 and overwriting it with zeros; if the thumbnail image is
+/// stored at the end of the segment (as is usual), the segment is also shortened.
+pub(crate) fn strip_exif(payload: &[u8], gps: bool, thumbnail: bool) -> Result<Vec<u8>> {
+    let header_len = EXIF_HEADER.len();
+    let tiff = Tiff::new(&payload[header_len..])?;
+    let mut output = payload.to_vec();
+    let ifd0 = tiff.read_u32(4)?;
+    let count = tiff.read_u16(ifd0)? as usize;
+
+    let mut end = payload.len();
+    if thumbnail {
+        let next_pos = ifd0 + 2 + 12 * count;
+        let ifd1 = tiff.read_u32(next_pos)?;
+        if ifd1 != 0 {
+            if let Some(thumbnail) = tiff.thumbnail(ifd1)? {
+                let range = header_len + thumbnail.start..header_len + thumbnail.end;
+                output[range.clone()].fill(0);
+                if range.end == payload.len() {
+                    end = range.start;
+                }
+            }
+            tiff.clear_ifd(ifd1, &mut output[header_len..])?;
+            output[header_len + next_pos..header_len + next_pos + 4].fill(0);
+        }
+    }
+
+    if gps {
+        if let Some(entry) = tiff.find_entry(ifd0, GPS_IFD_TAG)? {
+            let gps_ifd = tiff.read_value(entry)?;
+            tiff.clear_ifd(gps_ifd, &mut output[header_len..])?;
+
+            // remove the entry by moving the following entries and the link to IFD1 over it
+            tiff.read_u32(ifd0 + 2 + 12 * count)?;
+            let ifd_end = header_len + ifd0 + 2 + 12 * count + 4;
+            let entry = header_len + entry;
+            output.copy_within(entry + 12..ifd_end, entry);
+            output[ifd_end - 12..ifd_end].fill(0);
+            let count = tiff.encode_u16(count as u16 - 1);
+            output[header_len + ifd0..header_len + ifd0 + 2].copy_from_slice(&count);
+        }
+    }
+
+    output.truncate(end);
+    Ok(output)
+}
+
+/// Sets the width and height tags of the Exif IFD in the payload of an EXIF segment.
+///
+/// Only the tags that exist are overwritten.
+pub(crate) fn write_dimensions(payload: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    let header_len = EXIF_HEADER.len();
+    let tiff = Tiff::new(&payload[header_len..])?;
+    let mut output = payload.to_vec();
+    let ifd0 = tiff.read_u32(4)?;
+    if let Some(entry) = tiff.find_entry(ifd0, EXIF_IFD_TAG)? {
+        let exif_ifd = tiff.read_value(entry)?;
+        for (tag, value) in [
+            (PIXEL_X_DIMENSION_TAG, width),
+            (PIXEL_Y_DIMENSION_TAG, height),
+        ] {
+            if let Some(entry) = tiff.find_entry(exif_ifd, tag)? {
+                let entry = header_len + entry;
+                let entry = output
+                    .get_mut(entry..entry + 12)
+                    .ok_or(Error::InvalidJpeg("truncated EXIF segment"))?;
+                tiff.write_value(entry, value)?;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Returns the position of the JPEG thumbnail image in the payload of an EXIF segment.
+pub(crate) fn find_thumbnail(payload: &[u8]) -> Result<Option<Range<usize>>> {
+    let header_len = EXIF_HEADER.len();
+    let tiff = Tiff::new(&payload[header_len..])?;
+    let ifd0 = tiff.read_u32(4)?;
+    let ifd1 = tiff.read_u32(ifd0 + 2 + 12 * tiff.read_u16(ifd0)? as usize)?;
+    if ifd1 == 0 {
+        return Ok(None);
+    }
+    Ok(tiff
+        .thumbnail(ifd1)?
+        .map(|thumbnail| header_len + thumbnail.start..header_len + thumbnail.end))
+}
+
+/// Replaces the JPEG thumbnail image in the payload of an EXIF segment.
+///
+/// The new thumbnail is stored in place of the old one if it is stored at the end of the segment
+/// or if the new thumbnail is not larger than the old one. Otherwise, the new thumbnail is
+/// appended to the segment. The payload must contain a thumbnail (see [`find_thumbnail()`]).
+pub(crate) fn replace_thumbnail(payload: &[u8], thumbnail: &[u8]) -> Result<Vec<u8>> {
+    let header_len = EXIF_HEADER.len();
+    let tiff = Tiff::new(&payload[header_len..])?;
+    let ifd0 = tiff.read_u32(4)?;
+    let ifd1 = tiff.read_u32(ifd0 + 2 + 12 * tiff.read_u16(ifd0)? as usize)?;
+    let old = tiff
+        .thumbnail(ifd1)?
+        .ok_or(Error::InvalidJpeg("missing EXIF thumbnail"))?;
+
+    let mut output = payload.to_vec();
+    let start = if old.end + header_len == payload.len() {
+        output.truncate(header_len + old.start);
+        output.extend_from_slice(thumbnail);
+        old.start
+    } else if thumbnail.len() <= old.len() {
+        let range = header_len + old.start..header_len + old.end;
+        output[range].fill(0);
+        output[header_len + old.start..][..thumbnail.len()].copy_from_slice(thumbnail);
+        old.start
+    } else {
+        output.extend_from_slice(thumbnail);
+        payload.len() - header_len
+    };
+
+    let offset_entry = tiff.find_entry(ifd1, THUMBNAIL_OFFSET_TAG)?;
+    let length_entry = tiff.find_entry(ifd1, THUMBNAIL_LENGTH_TAG)?;
+    for (entry, value) in [(offset_entry, start), (length_entry, thumbnail.len())] {
+        // both entries exist, because the old thumbnail was found
+        let entry = header_len + entry.unwrap_or_default();
+        tiff.write_value(&mut output[entry..entry + 12], value)?;
+    }
+    Ok(output)
+}
+
+/// A TIFF structure, which stores the EXIF data.
+#[derive(Debug, Copy, Clone)]
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Result<Tiff<'a>> {
+        let big_endian = match data.get(..4) {
+            Some(b"II*\0") => false,
+            Some(b"MM\0*") => true,
+            _ => return Err(Error::InvalidJpeg("invalid TIFF header in EXIF segment")),
+        };
+        Ok(Tiff { data, big_endian })
+    }
+
+    fn read_u16(&self, pos: usize) -> Result<u16> {
+        match self.data.get(pos..pos + 2) {
+            Some(&[a, b]) => Ok(self.decode_u16([a, b])),
+            _ => Err(Error::InvalidJpeg("truncated EXIF segment")),
+        }
+    }
+
+    fn decode_u16(&self, bytes: [u8; 2]) -> u16 {
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn read_u32(&self, pos: usize) -> Result<usize> {
+        let (hi, lo) = (
+            self.read_u16(pos)? as usize,
+            self.read_u16(pos + 2)? as usize,
+        );
+        Ok(if self.big_endian {
+            hi << 16 | lo
+        } else {
+            lo << 16 | hi
+        })
+    }
+
+    fn encode_u16(&self, value: u16) -> [u8; 2] {
+        if self.big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    /// Overwrites the integer value of a SHORT or LONG entry.
+    fn write_value(&self, entry: &mut [u8], value: usize) -> Result<()> {
+        let entry_type = self.decode_u16([entry[2], entry[3]]);
+        let overflow = Error::IntegerOverflow("EXIF value");
+        if entry_type == SHORT_TYPE {
+            let value = u16::try_from(value).map_err(|_| overflow)?;
+            entry[8..10].copy_from_slice(&self.encode_u16(value));
+            entry[10..12].fill(0);
+        } else {
+            let value = u32::try_from(value).map_err(|_| overflow)?;
+            let bytes = if self.big_endian {
+                value.to_be_bytes()
+            } else {
+                value.to_le_bytes()
+            };
+            entry[8..12].copy_from_slice(&bytes);
+        }
+        Ok(())
+    }
+
+    /// Reads the integer value of a SHORT or LONG entry.
+    fn read_value(&self, entry: usize) -> Result<usize> {
+        if self.read_u16(entry + 2)? == SHORT_TYPE {
+            Ok(self.read_u16(entry + 8)? as usize)
+        } else {
+            self.read_u32(entry + 8)
+        }
+    }
+
+    /// Returns the position of the JPEG thumbnail image given the offset of IFD1.
+    fn thumbnail(&self, ifd1: usize) -> Result<Option<Range<usize>>> {
+        let value = |tag| -> Result<Option<usize>> {
+            match self.find_entry(ifd1, tag)? {
+                Some(entry) => self.read_value(entry).map(Some),
+                None => Ok(None),
+            }
+        };
+        match (value(THUMBNAIL_OFFSET_TAG)?, value(THUMBNAIL_LENGTH_TAG)?) {
+            (Some(offset), Some(len)) if offset + len <= self.data.len() => {
+                Ok(Some(offset..offset + len))
+            }
+            (Some(_), Some(_)) => Err(Error::InvalidJpeg("truncated EXIF segment")),
+            _ => Ok(None),
+        }
+    }
+
+    /// Finds the entry with the given tag in an IFD and returns its position.
+    fn find_entry(&self, ifd: usize, tag: u16) -> Result<Option<usize>> {
+        let count = self.read_u16(ifd)? as usize;
+        for i in 0..count {
+            let entry = ifd + 2 + 12 * i;
+            if self.read_u16(entry)? == tag {
+                return Ok(Some(entry));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Overwrites an IFD and the values that are stored outside of its entries with zeros.
+    fn clear_ifd(&self, ifd: usize, output: &mut [u8]) -> Result<()> {
+        let count = self.read_u16(ifd)? as usize;
+        for i in 0..count {
+            let entry = ifd + 2 + 12 * i;
+            let type_size = match self.read_u16(entry + 2)? {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 => 4,
+                5 | 10 | 12 => 8,
+                _ => continue,
+            };
+            let len = self.read_u32(entry + 4)?.saturating_mul(type_size);
+            if len > 4 {
+                let offset = self.read_u32(entry + 8)?;
+                let value = offset
+                    .checked_add(len)
+                    .and_then(|end| output.get_mut(offset..end));
+                if let Some(value) = value {
+                    value.fill(0);
+                }
+            }
+        }
+        output
+            .get_mut(ifd..ifd + 2 + 12 * count + 4)
+            .ok_or(Error::InvalidJpeg("truncated EXIF segment"))?
+            .fill(0);
+        Ok(())
+    }
+}
@@ -22,7 +22,7 @@ impl Handle {
         Error::TurboJpegError(msg.to_string_lossy().into_owned())
     }
 
-    pub fn get(&mut self, param: ffi::TJPARAM) -> libc::c_int {
+    pub fn get(&self, param: ffi::TJPARAM) -> libc::c_int {
         unsafe { ffi::tj3Get(self.ptr, param as libc::c_int) }
     }
 
@@ -34,6 +34,41 @@ impl Handle {
         Ok(())
     }
 
+    pub fn set_icc_profile(&mut self, icc_profile: &[u8]) -> Result<()> {
+        // TurboJPEG makes a copy of the profile, the pointer is not mutated
+        let res = unsafe {
+            ffi::tj3SetICCProfile(
+                self.ptr,
+                icc_profile.as_ptr() as *mut libc::c_uchar,
+                icc_profile.len() as ffi::size_t,
+            )
+        };
+        if res != 0 {
+            return Err(self.get_error());
+        }
+        Ok(())
+    }
+
+    /// Takes the ICC profile that was extracted by the last call to `tj3DecompressHeader`.
+    ///
+    /// TurboJPEG hands out the profile only once, subsequent calls return `None` until another
+    /// header with a profile is read.
+    pub fn take_icc_profile(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut icc_ptr = std::ptr::null_mut();
+        let mut icc_len: ffi::size_t = 0;
+        let res = unsafe { ffi::tj3GetICCProfile(self.ptr, &mut icc_ptr, &mut icc_len) };
+        if icc_ptr.is_null() {
+            // TurboJPEG reports a missing profile as a (non-fatal) error
+            return Ok(None);
+        }
+        let icc_profile = unsafe { std::slice::from_raw_parts(icc_ptr, icc_len as usize).to_vec() };
+        unsafe { ffi::tj3Free(icc_ptr as *mut libc::c_void) };
+        if res != 0 {
+            return Err(self.get_error());
+        }
+        Ok(Some(icc_profile))
+    }
+
     pub unsafe fn as_ptr(&mut self) -> ffi::tjhandle {
         self.ptr
     }
@@ -46,3 +81,71 @@ impl Drop for Handle {
         }
     }
 }
+
+/// Compressor of the libjpeg API that accepts the rows of an image one at a time (see
+/// `ffi/rows.h`).
+#[cfg(feature = "libjpeg")]
+#[derive(Debug)]
+pub struct RowsHandle {
+    ptr: *mut ffi::tjrs_rows,
+}
+
+#[cfg(feature = "libjpeg")]
+impl RowsHandle {
+    pub fn new() -> Result<Self> {
+        let ptr = unsafe { ffi::tjrs_rows_new() };
+        if ptr.is_null() {
+            return Err(Error::Null);
+        }
+        Ok(Self { ptr })
+    }
+
+    pub fn get_error(&self) -> Error {
+        let msg = unsafe { CStr::from_ptr(ffi::tjrs_rows_error(self.ptr)) };
+        Error::TurboJpegError(msg.to_string_lossy().into_owned())
+    }
+
+    pub fn start(&mut self, params: &ffi::tjrs_rows_params) -> Result<()> {
+        self.check(unsafe { ffi::tjrs_rows_start(self.ptr, params) })
+    }
+
+    /// Passes the compressed data to `write` in chunks as soon as they are produced, instead of
+    /// collecting them in the handle (see `tjrs_rows_set_writer()`).
+    ///
+    /// # Safety
+    ///
+    /// `ctx` must be valid for `write` until the handle is dropped.
+    pub unsafe fn set_writer(&mut self, write: ffi::tjrs_write_fn, ctx: *mut libc::c_void) {
+        ffi::tjrs_rows_set_writer(self.ptr, write, ctx);
+    }
+
+    /// Compresses the next row, which must contain a whole row of pixels in the format given to
+    /// `start()`.
+    pub fn write(&mut self, row: &[u8]) -> Result<()> {
+        self.check(unsafe { ffi::tjrs_rows_write(self.ptr, row.as_ptr()) })
+    }
+
+    /// Finishes the image and returns the compressed data, which are owned by the handle.
+    pub fn finish(&mut self) -> Result<&[u8]> {
+        let mut jpeg_ptr = std::ptr::null();
+        let mut jpeg_len: ffi::size_t = 0;
+        self.check(unsafe { ffi::tjrs_rows_finish(self.ptr, &mut jpeg_ptr, &mut jpeg_len) })?;
+        Ok(unsafe { std::slice::from_raw_parts(jpeg_ptr, jpeg_len as usize) })
+    }
+
+    fn check(&self, res: libc::c_int) -> Result<()> {
+        if res != 0 {
+            return Err(self.get_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "libjpeg")]
+impl Drop for RowsHandle {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::tjrs_rows_free(self.ptr);
+        }
+    }
+}
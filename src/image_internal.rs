@@ -1,6 +1,8 @@
 use std::ops::{Deref, DerefMut};
-use crate::common::{PixelFormat, Subsamp};
+use std::sync::Arc;
+use crate::common::{Error, PackedYuvFormat, PixelFormat, Result, SemiPlanarFormat, Subsamp};
 use crate::decompress::yuv_pixels_len;
+use crate::transform::TransformScale;
 
 /// An image with pixels of type `T`.
 ///
@@ -21,8 +23,9 @@ pub struct Image<T> {
     pub pixels: T,
     /// Width of the image in pixels (number of columns).
     pub width: usize,
-    /// Pitch (stride) defines the size of one image row in bytes. Overlapping rows are not
-    /// supported, we require that `pitch >= width * format.size()`.
+    /// Pitch (stride) defines the size of one image row in bytes (in samples for images with
+    /// 12-bit or 16-bit samples, such as [`Image16`]). Overlapping rows are not supported, we
+    /// require that `pitch >= width * format.size()`.
     pub pitch: usize,
     /// Height of the image in pixels (number of rows).
     pub height: usize,
@@ -31,6 +34,67 @@ pub struct Image<T> {
     pub format: PixelFormat,
 }
 
+/// Image with 12-bit or 16-bit samples.
+///
+/// Every sample (color component) is stored in one `u16`, and the [pitch][Image::pitch] is
+/// measured in samples. Such images are compressed by
+/// [`Compressor::compress_12bit()`][crate::Compressor::compress_12bit] and
+/// [`Compressor::compress_16bit()`][crate::Compressor::compress_16bit] and decompressed by
+/// [`Decompressor::decompress_12bit()`][crate::Decompressor::decompress_12bit] and
+/// [`Decompressor::decompress_16bit()`][crate::Decompressor::decompress_16bit]. Use
+/// [`as_deref()`][Image::as_deref] and [`as_deref_mut()`][Image::as_deref_mut] to borrow the
+/// samples as `Image<&[u16]>` or `Image<&mut [u16]>`, as with 8-bit images.
+pub type Image16 = Image<Vec<u16>>;
+
+/// Image that owns its pixels.
+///
+/// This is just an alias for `Image<Vec<u8>>`, the type of the images returned by
+/// [`decompress()`][crate::decompress] and [`Image::alloc()`], so it can be used in signatures
+/// that pass decompressed images around. Use [`Image::into_parts()`] and [`Image::from_parts()`]
+/// to move the pixels in and out of the image.
+pub type ImageOwned = Image<Vec<u8>>;
+
+/// Borrowed view of an image or of a rectangle inside an image.
+///
+/// This is just an alias for `Image<&[u8]>`, so views are accepted by all functions that take an
+/// input image. A view of a rectangle refers to the pixels of the original image with an offset
+/// and the original pitch, so no pixels are copied; views are usually obtained from
+/// [`Image::as_deref()`] or [`Image::region()`].
+///
+/// ```
+/// let desktop = turbojpeg::Image::mandelbrot(800, 600, turbojpeg::PixelFormat::RGBA);
+/// let view: turbojpeg::ImageView = desktop.region(100, 100, 320, 240);
+/// assert_eq!((view.width, view.height, view.pitch), (320, 240, 800*4));
+/// ```
+pub type ImageView<'a> = Image<&'a [u8]>;
+
+/// Mutable view of an image or of a rectangle inside an image.
+///
+/// This is just an alias for `Image<&mut [u8]>`, so views are accepted by all functions that take
+/// an output image. Views are usually obtained from [`Image::as_deref_mut()`] or
+/// [`Image::region_mut()`]. For example, a JPEG image can be decompressed directly into a part of
+/// a larger image:
+///
+/// ```
+/// let tile = turbojpeg::Image::mandelbrot(64, 64, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(tile.as_deref(), 90, turbojpeg::Subsamp::None)?;
+///
+/// let mut canvas = turbojpeg::Image {
+///     pixels: vec![0; 256*256*3],
+///     width: 256,
+///     pitch: 256*3,
+///     height: 256,
+///     format: turbojpeg::PixelFormat::RGB,
+/// };
+/// let view: turbojpeg::ImageViewMut = canvas.region_mut(64, 128, 64, 64);
+/// turbojpeg::Decompressor::new()?.decompress(&jpeg_data, view)?;
+///
+/// assert!(canvas.pixels[..128*256*3].iter().all(|&x| x == 0));
+/// assert!(canvas.pixels[128*256*3..].iter().any(|&x| x != 0));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub type ImageViewMut<'a> = Image<&'a mut [u8]>;
+
 impl<T> Image<T> {
     /// Converts from `&Image<T>` to `Image<&T::Target>`.
     ///
@@ -58,6 +122,367 @@ impl<T> Image<T> {
         }
     }
 
+    /// Borrows the pixels of an image with any storage that implements `AsRef<[u8]>`.
+    ///
+    /// This works like [`as_deref()`][Self::as_deref], but also for storage types that do not
+    /// implement `Deref`. Pixels can be stored in `Vec<u8>`, `Box<[u8]>`, `Arc<[u8]>` (to share
+    /// a decoded image between threads without copying it), [`OwnedBuf`][crate::OwnedBuf] or
+    /// any other type that implements `AsRef<[u8]>`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    ///
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let shared: turbojpeg::Image<Arc<[u8]>> = image.into_shared();
+    ///
+    /// let threads = (0..4).map(|_| {
+    ///     let image = shared.clone();
+    ///     std::thread::spawn(move || turbojpeg::compress(image.view(), 90, turbojpeg::Subsamp::Sub2x2))
+    /// }).collect::<Vec<_>>();
+    /// for thread in threads {
+    ///     thread.join().unwrap()?;
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn view(&self) -> Image<&[u8]> where T: AsRef<[u8]> {
+        Image {
+            pixels: self.pixels.as_ref(),
+            width: self.width,
+            pitch: self.pitch,
+            height: self.height,
+            format: self.format,
+        }
+    }
+
+    /// Mutably borrows the pixels of an image with any storage that implements `AsMut<[u8]>`.
+    ///
+    /// This works like [`as_deref_mut()`][Self::as_deref_mut], but also for storage types that
+    /// do not implement `DerefMut`.
+    pub fn view_mut(&mut self) -> Image<&mut [u8]> where T: AsMut<[u8]> {
+        Image {
+            pixels: self.pixels.as_mut(),
+            width: self.width,
+            pitch: self.pitch,
+            height: self.height,
+            format: self.format,
+        }
+    }
+
+    /// Converts the storage of the pixels with the function `f`, keeping the other fields.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let boxed: turbojpeg::Image<Box<[u8]>> = image.map_pixels(Vec::into_boxed_slice);
+    /// let vec: turbojpeg::Image<Vec<u8>> = boxed.map_pixels(Vec::from);
+    /// assert_eq!((vec.width, vec.height), (64, 48));
+    /// ```
+    pub fn map_pixels<U, F>(self, f: F) -> Image<U> where F: FnOnce(T) -> U {
+        Image {
+            pixels: f(self.pixels),
+            width: self.width,
+            pitch: self.pitch,
+            height: self.height,
+            format: self.format,
+        }
+    }
+
+    /// Moves the pixels into an `Arc<[u8]>`, so that the image can be cheaply cloned and shared
+    /// between threads (see [`view()`][Self::view]).
+    pub fn into_shared(self) -> Image<Arc<[u8]>> where T: Into<Arc<[u8]>> {
+        self.map_pixels(Into::into)
+    }
+
+    /// Borrows a rectangular region of the image without copying.
+    ///
+    /// Returns an image that refers to the pixels of `self` in columns `x..x + width` and rows
+    /// `y..y + height`. The returned image uses the same pitch as `self`, so it can be passed
+    /// directly to [`Compressor::compress()`][crate::Compressor::compress] to compress just this
+    /// region, without cropping it into a temporary buffer first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region does not fit into the image.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // a capture of the full desktop
+    /// let desktop = turbojpeg::Image::mandelbrot(1920, 1080, turbojpeg::PixelFormat::BGRA);
+    ///
+    /// // compress a single window
+    /// let window = desktop.region(100, 200, 640, 480);
+    /// let jpeg_data = turbojpeg::compress(window, 90, turbojpeg::Subsamp::Sub2x2)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (640, 480));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn region(&self, x: usize, y: usize, width: usize, height: usize) -> Image<&[u8]>
+        where T: Deref<Target = [u8]>
+    {
+        self.assert_region(x, y, width, height);
+        let offset = usize::min(y*self.pitch + x*self.format.size(), self.pixels.len());
+        Image {
+            pixels: &self.pixels[offset..],
+            width,
+            pitch: self.pitch,
+            height,
+            format: self.format,
+        }
+    }
+
+    /// Mutably borrows a rectangular region of the image without copying.
+    ///
+    /// This is the same as [`region()`][Self::region], but the returned image can be modified
+    /// (for example, it can be used as an output image for decompression).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the region does not fit into the image.
+    pub fn region_mut(&mut self, x: usize, y: usize, width: usize, height: usize) -> Image<&mut [u8]>
+        where T: DerefMut<Target = [u8]>
+    {
+        self.assert_region(x, y, width, height);
+        let offset = usize::min(y*self.pitch + x*self.format.size(), self.pixels.len());
+        Image {
+            pixels: &mut self.pixels[offset..],
+            width,
+            pitch: self.pitch,
+            height,
+            format: self.format,
+        }
+    }
+
+    /// Returns the pixels in row `y` (without the padding at the end of the row).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is out of bounds.
+    pub fn row(&self, y: usize) -> &[u8] where T: Deref<Target = [u8]> {
+        assert!(y < self.height, "row {} is out of bounds for image height {}", y, self.height);
+        &self.pixels[y*self.pitch..][..self.width*self.format.size()]
+    }
+
+    /// Returns the pixels in row `y` as a mutable slice (without the padding at the end of the
+    /// row).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `y` is out of bounds.
+    pub fn row_mut(&mut self, y: usize) -> &mut [u8] where T: DerefMut<Target = [u8]> {
+        assert!(y < self.height, "row {} is out of bounds for image height {}", y, self.height);
+        let row_len = self.width*self.format.size();
+        &mut self.pixels[y*self.pitch..][..row_len]
+    }
+
+    /// Returns an iterator over the rows of the image (see [`row()`][Self::row]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(100, 50, turbojpeg::PixelFormat::RGBA);
+    /// assert!(image.pitch > 400);
+    /// for row in image.rows() {
+    ///     assert_eq!(row.len(), 400);
+    /// }
+    /// assert_eq!(image.rows().len(), 50);
+    /// ```
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[u8]> + '_ where T: Deref<Target = [u8]> {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    /// Returns an iterator over the mutable rows of the image (see [`row_mut()`][Self::row_mut]).
+    ///
+    /// # Example
+    ///
+    /// Draw a red horizontal line, ignoring the padding at the end of each row:
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::alloc_aligned(30, 20, turbojpeg::PixelFormat::RGB, 64);
+    /// for row in image.rows_mut().skip(5).take(2) {
+    ///     for pixel in row.chunks_exact_mut(3) {
+    ///         pixel.copy_from_slice(&[255, 0, 0]);
+    ///     }
+    /// }
+    /// assert_eq!(image.pixel(10, 6), &[255, 0, 0]);
+    /// assert_eq!(image.pixel(10, 7), &[0, 0, 0]);
+    /// ```
+    pub fn rows_mut(&mut self) -> impl ExactSizeIterator<Item = &mut [u8]> + '_
+        where T: DerefMut<Target = [u8]>
+    {
+        let Image { ref mut pixels, width, pitch, height, format } = *self;
+        let row_len = width*format.size();
+        let mut rest: &mut [u8] = pixels;
+        (0..height).map(move |_| {
+            let rows = std::mem::take(&mut rest);
+            let (row, tail) = rows.split_at_mut(usize::min(pitch, rows.len()));
+            rest = tail;
+            &mut row[..row_len]
+        })
+    }
+
+    /// Returns the bytes of the pixel in column `x` and row `y` (`format.size()` bytes).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::BGRA);
+    /// let [_b, _g, _r, a] = *image.pixel(10, 20) else { unreachable!() };
+    /// assert_eq!(a, 255);
+    /// ```
+    pub fn pixel(&self, x: usize, y: usize) -> &[u8] where T: Deref<Target = [u8]> {
+        assert!(x < self.width, "column {} is out of bounds for image width {}", x, self.width);
+        let size = self.format.size();
+        &self.row(y)[x*size..][..size]
+    }
+
+    /// Returns the bytes of the pixel in column `x` and row `y` as a mutable slice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` or `y` is out of bounds.
+    pub fn pixel_mut(&mut self, x: usize, y: usize) -> &mut [u8] where T: DerefMut<Target = [u8]> {
+        assert!(x < self.width, "column {} is out of bounds for image width {}", x, self.width);
+        let size = self.format.size();
+        &mut self.row_mut(y)[x*size..][..size]
+    }
+
+    /// Borrows the rectangle `rect` of the image without copying.
+    ///
+    /// This is the same as [`region()`][Self::region], but the rectangle is given as a
+    /// [`TransformCrop`][crate::TransformCrop], so the same value can be used to crop a JPEG image
+    /// losslessly and to crop a decompressed image. If the width or height of `rect` is `None`,
+    /// the rectangle extends to the right or bottom boundary of the image. Unlike lossless
+    /// transforms, the rectangle does not need to be aligned on MCU boundaries.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rectangle does not fit into the image.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let rect = turbojpeg::TransformCrop { x: 10, y: 20, width: Some(30), height: None };
+    /// let cropped = image.cropped(rect);
+    /// assert_eq!((cropped.width, cropped.height), (30, 28));
+    /// assert_eq!(cropped.pixel(0, 0), image.pixel(10, 20));
+    /// ```
+    pub fn cropped(&self, rect: crate::TransformCrop) -> ImageView<'_> where T: Deref<Target = [u8]> {
+        let width = rect.width.unwrap_or(self.width.saturating_sub(rect.x));
+        let height = rect.height.unwrap_or(self.height.saturating_sub(rect.y));
+        self.region(rect.x, rect.y, width, height)
+    }
+
+    /// Copies the pixels of `src` into the image, with the top-left corner at column `dst_x` and
+    /// row `dst_y`.
+    ///
+    /// Both images must have the same pixel format; use
+    /// [`convert_into()`][Self::convert_into] with a [`region_mut()`][Self::region_mut] of this
+    /// image to copy pixels between different formats.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel formats differ, if `src` does not fit into the image at the given
+    /// position, or if `src` is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let tile = turbojpeg::Image::mandelbrot(16, 16, turbojpeg::PixelFormat::RGB);
+    /// let mut canvas = turbojpeg::Image::alloc_aligned(64, 48, turbojpeg::PixelFormat::RGB, 64);
+    /// canvas.copy_from(tile.as_deref(), 40, 30);
+    /// assert_eq!(canvas.pixel(45, 31), tile.pixel(5, 1));
+    /// assert_eq!(canvas.pixel(39, 30), &[0, 0, 0]);
+    /// ```
+    pub fn copy_from(&mut self, src: Image<&[u8]>, dst_x: usize, dst_y: usize)
+        where T: DerefMut<Target = [u8]>
+    {
+        assert_eq!(src.format, self.format, "pixel formats of the images differ");
+        src.assert_valid(src.pixels.len());
+        let mut dst = self.region_mut(dst_x, dst_y, src.width, src.height);
+        for (dst_row, src_row) in dst.rows_mut().zip(src.rows()) {
+            dst_row.copy_from_slice(src_row);
+        }
+    }
+
+    /// Sets all pixels of the image to `color`, without touching the padding at the end of the
+    /// rows.
+    ///
+    /// `color` contains the bytes of one pixel in the pixel format of the image. Combine this
+    /// with [`region_mut()`][Self::region_mut] to fill a rectangle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `color` is not `format.size()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::alloc(64, 48, turbojpeg::PixelFormat::BGRA);
+    /// image.fill(&[255, 0, 0, 255]);
+    /// image.region_mut(8, 8, 16, 16).fill(&[0, 0, 255, 255]);
+    /// assert_eq!(image.pixel(0, 0), &[255, 0, 0, 255]);
+    /// assert_eq!(image.pixel(10, 10), &[0, 0, 255, 255]);
+    /// ```
+    pub fn fill(&mut self, color: &[u8]) where T: DerefMut<Target = [u8]> {
+        assert_eq!(color.len(), self.format.size(),
+            "color has {} bytes, but pixel format {:?} has {} bytes", color.len(), self.format,
+            self.format.size());
+        for row in self.rows_mut() {
+            for pixel in row.chunks_exact_mut(color.len()) {
+                pixel.copy_from_slice(color);
+            }
+        }
+    }
+
+    /// Copies the image into a new image with the given `pitch`.
+    ///
+    /// This converts between padded and tightly packed row layouts, for example to satisfy the
+    /// row pitch required by a GPU upload. The padding at the end of the rows of the new image is
+    /// filled with zeros. Use [`tighten()`][Image::tighten] to remove the padding of an owned
+    /// image in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pitch` is smaller than `width * format.size()`, if the size of the new image
+    /// overflows `usize`, or if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(100, 50, turbojpeg::PixelFormat::RGB);
+    /// let padded = image.repitch(512);
+    /// assert_eq!((padded.pitch, padded.pixels.len()), (512, 512 * 50));
+    /// assert_eq!(padded.row(20), image.row(20));
+    /// ```
+    pub fn repitch(&self, pitch: usize) -> Image<Vec<u8>> where T: Deref<Target = [u8]> {
+        self.as_deref().assert_valid(self.pixels.len());
+        let row_len = self.width*self.format.size();
+        assert!(pitch >= row_len,
+            "pitch {} is too small for width {} and pixel format {:?}", pitch, self.width, self.format);
+        let len = pitch.checked_mul(self.height).expect("image size overflowed");
+        let mut output = Image { pixels: vec![0; len], width: self.width, pitch, height: self.height, format: self.format };
+        for (dst_row, src_row) in output.rows_mut().zip(self.rows()) {
+            dst_row.copy_from_slice(src_row);
+        }
+        output
+    }
+
+    fn assert_region(&self, x: usize, y: usize, width: usize, height: usize) {
+        assert!(x + width <= self.width && y + height <= self.height,
+            "region {}x{} at ({}, {}) does not fit into image {}x{}",
+            width, height, x, y, self.width, self.height);
+    }
+
     pub(crate) fn assert_valid(&self, pixels_len: usize) {
         let Image { pixels: _, width, pitch, height, format } = *self;
         assert!(pitch >= width*format.size(),
@@ -68,6 +493,23 @@ impl<T> Image<T> {
     }
 }
 
+/// Overwrites the pixels with zeros (for example when the image contains sensitive data).
+///
+/// ```
+/// use zeroize::Zeroize;
+///
+/// let mut image = turbojpeg::Image::mandelbrot(32, 32, turbojpeg::PixelFormat::RGB);
+/// image.zeroize();
+/// assert!(image.pixels.iter().all(|&x| x == 0));
+/// ```
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<T: zeroize::Zeroize> zeroize::Zeroize for Image<T> {
+    fn zeroize(&mut self) {
+        self.pixels.zeroize();
+    }
+}
+
 impl Image<Vec<u8>> {
     /// Generates an image of the Mandelbrot set.
     ///
@@ -166,6 +608,214 @@ impl Image<Vec<u8>> {
 
         Image { pixels, width, pitch, height, format }
     }
+
+    /// Allocates a zero-filled image with tightly packed rows.
+    ///
+    /// The pitch is `width * format.size()` and the pixel buffer has exactly `pitch * height`
+    /// bytes, so the image can be used as an output image for decompression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the image overflows `usize`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    ///
+    /// let mut image = turbojpeg::Image::alloc(header.width, header.height, turbojpeg::PixelFormat::RGB);
+    /// assert_eq!(image.pitch, 3*header.width);
+    /// turbojpeg::Decompressor::new()?.decompress(&jpeg_data, image.as_deref_mut())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn alloc(width: usize, height: usize, format: PixelFormat) -> Image<Vec<u8>> {
+        Self::alloc_aligned(width, height, format, 1)
+    }
+
+    /// Allocates a zero-filled image with rows padded to a multiple of `row_align` bytes.
+    ///
+    /// This is useful when the rows must be aligned for SIMD code or for a graphics API. The pitch
+    /// is `width * format.size()` rounded up to a multiple of `row_align`, and the pixel buffer has
+    /// `pitch * height` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `row_align` is zero or if the size of the image overflows `usize`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::alloc_aligned(35, 10, turbojpeg::PixelFormat::RGB, 64);
+    /// assert_eq!(image.pitch, 128);
+    /// assert_eq!(image.pixels.len(), 1280);
+    /// ```
+    pub fn alloc_aligned(width: usize, height: usize, format: PixelFormat, row_align: usize) -> Image<Vec<u8>> {
+        assert!(row_align > 0, "row alignment must not be zero");
+        let pitch = width.checked_mul(format.size())
+            .and_then(|row_len| row_len.checked_next_multiple_of(row_align))
+            .expect("image pitch overflowed");
+        let len = pitch.checked_mul(height).expect("image size overflowed");
+        Image { pixels: vec![0; len], width, pitch, height, format }
+    }
+
+    /// Removes the padding at the end of the rows in place, so that the pitch becomes
+    /// `width * format.size()`.
+    ///
+    /// The rows are moved to the front of the buffer and the buffer is truncated (its capacity is
+    /// kept). Use [`repitch()`][Image::repitch] to convert to any other pitch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::alloc_aligned(30, 20, turbojpeg::PixelFormat::RGB, 64);
+    /// image.fill(&[1, 2, 3]);
+    /// image.tighten();
+    /// assert_eq!(image.pitch, 90);
+    /// assert_eq!(image.pixels, [1, 2, 3].repeat(30 * 20));
+    /// ```
+    pub fn tighten(&mut self) {
+        self.as_deref().assert_valid(self.pixels.len());
+        let row_len = self.width*self.format.size();
+        if self.pitch != row_len {
+            for y in 1..self.height {
+                self.pixels.copy_within(y*self.pitch..y*self.pitch + row_len, y*row_len);
+            }
+            self.pitch = row_len;
+        }
+        self.pixels.truncate(row_len*self.height);
+    }
+
+    /// Creates an image from its parts, checking that they describe a valid image.
+    ///
+    /// This is the inverse of [`into_parts()`][Self::into_parts].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pitch` is smaller than `width * format.size()` or if `pixels` is too short for
+    /// the given size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::{ImageOwned, PixelFormat};
+    /// let image = ImageOwned::from_parts(vec![0; 30*20*3], 30, 30*3, 20, PixelFormat::RGB);
+    /// assert_eq!(image.pixel(29, 19), &[0, 0, 0]);
+    /// ```
+    pub fn from_parts(pixels: Vec<u8>, width: usize, pitch: usize, height: usize, format: PixelFormat)
+        -> Image<Vec<u8>>
+    {
+        let image = Image { pixels, width, pitch, height, format };
+        image.as_deref().assert_valid(image.pixels.len());
+        image
+    }
+
+    /// Decomposes the image into its parts: the pixels, the width, the pitch, the height and the
+    /// pixel format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// use turbojpeg::{ImageOwned, PixelFormat};
+    /// let image: ImageOwned = turbojpeg::decompress(&jpeg_data, PixelFormat::RGB)?;
+    /// let (pixels, width, pitch, height, format) = image.into_parts();
+    /// assert_eq!(pixels.len(), pitch * height);
+    /// assert_eq!((width, height, format), (384, 256, PixelFormat::RGB));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn into_parts(self) -> (Vec<u8>, usize, usize, usize, PixelFormat) {
+        let Image { pixels, width, pitch, height, format } = self;
+        (pixels, width, pitch, height, format)
+    }
+}
+
+impl<'a> Image<&'a [u8]> {
+    /// Creates an image that borrows the pixels from a raw memory region.
+    ///
+    /// This is intended for frames in memory that is owned by something other than Rust, such as
+    /// a mapped DMA-BUF, a GPU buffer mapped into the address space or a POSIX shared memory
+    /// segment, so that they can be compressed without copying them into Rust-owned storage. The
+    /// region starts at `ptr` and has `len` bytes; the image layout is given by `width`,
+    /// `pitch`, `height` and `format` as in [`Image`] (and [`Image::from_parts()`]).
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads of `len` bytes for the lifetime `'a`, and the memory must
+    /// be initialized and must not be written (by this process, another process or a device)
+    /// while the image exists. `len` must not be larger than `isize::MAX`. If `len` is 0, `ptr`
+    /// may be null.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layout does not fit into `len` bytes (see [`Image::pitch`]).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::{Image, PixelFormat};
+    ///
+    /// // a frame that was mapped from a device
+    /// let frame = vec![128u8; 640 * 4 * 480];
+    /// let (ptr, len) = (frame.as_ptr(), frame.len());
+    ///
+    /// let image = unsafe { Image::from_raw_parts(ptr, len, 640, 640 * 4, 480, PixelFormat::BGRX) };
+    /// let jpeg_data = turbojpeg::compress(image, 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub unsafe fn from_raw_parts(ptr: *const u8, len: usize, width: usize, pitch: usize,
+        height: usize, format: PixelFormat) -> Image<&'a [u8]>
+    {
+        let pixels = if len == 0 { &[][..] } else { std::slice::from_raw_parts(ptr, len) };
+        let image = Image { pixels, width, pitch, height, format };
+        image.assert_valid(len);
+        image
+    }
+}
+
+impl<'a> Image<&'a mut [u8]> {
+    /// Creates an image that mutably borrows the pixels from a raw memory region.
+    ///
+    /// This is the same as [`Image::from_raw_parts()`], but the image can be written, for
+    /// example to decompress directly into memory that is owned by something other than Rust.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be valid for reads and writes of `len` bytes for the lifetime `'a`, and the
+    /// memory must be initialized and must not be accessed in any other way (by this process,
+    /// another process or a device) while the image exists. `len` must not be larger than
+    /// `isize::MAX`. If `len` is 0, `ptr` may be null.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layout does not fit into `len` bytes (see [`Image::pitch`]).
+    pub unsafe fn from_raw_parts_mut(ptr: *mut u8, len: usize, width: usize, pitch: usize,
+        height: usize, format: PixelFormat) -> Image<&'a mut [u8]>
+    {
+        let pixels = if len == 0 { &mut [][..] } else { std::slice::from_raw_parts_mut(ptr, len) };
+        let image = Image { pixels, width, pitch, height, format };
+        image.as_deref().assert_valid(len);
+        image
+    }
+}
+
+impl Image<Vec<u16>> {
+    /// Allocates a zero-filled image with 12-bit or 16-bit samples and tightly packed rows.
+    ///
+    /// The pitch is `width * format.size()` samples. See [`Image::alloc()`] for 8-bit images.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size of the image overflows `usize`.
+    pub fn alloc16(width: usize, height: usize, format: PixelFormat) -> Image<Vec<u16>> {
+        let pitch = width.checked_mul(format.size()).expect("image pitch overflowed");
+        let len = pitch.checked_mul(height).expect("image size overflowed");
+        Image { pixels: vec![0; len], width, pitch, height, format }
+    }
 }
 
 /// A YUV (YCbCr) planar image with pixels of type `T`.
@@ -180,6 +830,9 @@ impl Image<Vec<u8>> {
 /// - `YuvImage<Vec<u8>>`: owned YUV image data (you can convert it to a reference using
 /// [`.as_deref()`][YuvImage::as_deref] or [`.as_deref_mut()`][YuvImage::as_deref_mut]).
 ///
+/// The planes of a `YuvImage` are stored contiguously. Images that are stored in three separate
+/// buffers can be wrapped without copying using [`YuvPlanes::from_planes()`].
+///
 /// # Image format
 ///
 /// The size of each image plane is determined by the [width][Self::width], [height][Self::height],
@@ -232,6 +885,15 @@ pub struct YuvImage<T> {
     pub subsamp: Subsamp,
 }
 
+/// Overwrites the pixels with zeros (for example when the image contains sensitive data).
+#[cfg(feature = "zeroize")]
+#[cfg_attr(docsrs, doc(cfg(feature = "zeroize")))]
+impl<T: zeroize::Zeroize> zeroize::Zeroize for YuvImage<T> {
+    fn zeroize(&mut self) {
+        self.pixels.zeroize();
+    }
+}
+
 impl<T> YuvImage<T> {
     /// Converts from `&YuvImage<T>` to `YuvImage<&T::Target>`.
     ///
@@ -264,8 +926,8 @@ impl<T> YuvImage<T> {
     /// This is the [image width][Self::width] padded to the nearest multiple of the [horizontal subsampling
     /// factor][Subsamp::width()] and then aligned to the [row alignment][Self::align].
     pub fn y_width(&self) -> usize {
-        let width = next_multiple_of(self.width, self.subsamp.width());
-        next_multiple_of(width, self.align)
+        let width = self.width.next_multiple_of(self.subsamp.width());
+        width.next_multiple_of(self.align)
     }
 
     /// Computes height of the luminance (Y) plane.
@@ -273,7 +935,7 @@ impl<T> YuvImage<T> {
     /// This is the [image height][Self::height] padded to the nearest multiple of the [vertical
     /// subsampling factor][Subsamp::height()].
     pub fn y_height(&self) -> usize {
-        next_multiple_of(self.height, self.subsamp.height())
+        self.height.next_multiple_of(self.subsamp.height())
     }
 
     /// Computes size of the luminance (Y) plane.
@@ -286,8 +948,8 @@ impl<T> YuvImage<T> {
     /// This is the [Y plane width][Self::y_width()] divided by the [horizontal subsampling
     /// factor][Subsamp::width()] and then aligned to the [row alignment][Self::align].
     pub fn uv_width(&self) -> usize {
-        let width = div_ceil(self.width, self.subsamp.width());
-        next_multiple_of(width, self.align)
+        let width = self.width.div_ceil(self.subsamp.width());
+        width.next_multiple_of(self.align)
     }
 
     /// Computes height of each chrominance (U, V) plane.
@@ -295,7 +957,7 @@ impl<T> YuvImage<T> {
     /// This is the [Y plane height][Self::y_height()] divided by the [vertical subsampling
     /// factor][Subsamp::height()].
     pub fn uv_height(&self) -> usize {
-        div_ceil(self.height, self.subsamp.height())
+        self.height.div_ceil(self.subsamp.height())
     }
 
     /// Computes size of each chrominance (U, V) plane.
@@ -303,6 +965,196 @@ impl<T> YuvImage<T> {
         (self.uv_width(), self.uv_height())
     }
 
+    /// Downscales the image to half of its width and height, returning a new image.
+    ///
+    /// This is the same as [`downscale(TransformScale::HALF)`][Self::downscale].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// Produce a preview of a video frame without converting it to RGB:
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(640, 480, turbojpeg::PixelFormat::RGB);
+    /// let frame = turbojpeg::encode_yuv(image.as_deref(), turbojpeg::Subsamp::Sub2x2)?;
+    ///
+    /// let preview = frame.downscale_by_2();
+    /// assert_eq!((preview.width, preview.height), (320, 240));
+    /// let jpeg_data = turbojpeg::compress_yuv(preview.as_deref(), 80)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn downscale_by_2(&self) -> YuvImage<Vec<u8>> where T: Deref<Target = [u8]> {
+        self.downscale(TransformScale::HALF).expect("size of downscaled image overflowed")
+    }
+
+    /// Downscales the image by the factor `scale`, returning a new image.
+    ///
+    /// [`TransformScale`] only admits downscaling factors, so unsupported factors are rejected by
+    /// [`TransformScale::new()`]. The width and height of the result are rounded up (as by
+    /// [`TransformScale::apply()`]), and the image keeps its level of chrominance subsampling and
+    /// its row alignment. Each plane is resampled separately at its own resolution (so the U and V
+    /// planes are not converted to full resolution and back) by averaging the covered samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(100, 60, turbojpeg::PixelFormat::RGB);
+    /// let yuv = turbojpeg::encode_yuv(image.as_deref(), turbojpeg::Subsamp::Sub2x1)?;
+    ///
+    /// let smaller = yuv.downscale(turbojpeg::TransformScale::new(3, 8)?)?;
+    /// assert_eq!((smaller.width, smaller.height), (38, 23));
+    /// assert_eq!(smaller.uv_size(), (20, 23));
+    ///
+    /// // upscaling is not supported
+    /// assert!(turbojpeg::TransformScale::new(3, 2).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn downscale(&self, scale: TransformScale) -> Result<YuvImage<Vec<u8>>>
+        where T: Deref<Target = [u8]>
+    {
+        let resize = |size: usize| size.checked_mul(scale.numerator())
+            .map(|size| size.div_ceil(scale.denominator()))
+            .ok_or(Error::IntegerOverflow("size"));
+        let (width, height) = (resize(self.width)?, resize(self.height)?);
+        let mut output = YuvImage {
+            pixels: vec![0; yuv_pixels_len(width, self.align, height, self.subsamp)?],
+            width,
+            align: self.align,
+            height,
+            subsamp: self.subsamp,
+        };
+
+        let (sub_width, sub_height) = self.subsamp.size();
+        let y_plane = ((self.width, self.height), (width, height), output.y_size());
+        let uv_plane = (
+            (self.width.div_ceil(sub_width), self.height.div_ceil(sub_height)),
+            (width.div_ceil(sub_width), height.div_ceil(sub_height)),
+            output.uv_size(),
+        );
+        let planes = if self.subsamp == Subsamp::Gray { 1 } else { 3 };
+
+        let input = self.as_planes();
+        let mut rest: &mut [u8] = &mut output.pixels;
+        let plane_sizes = [y_plane, uv_plane, uv_plane];
+        for (i, &(src_size, dst_size, (stride, rows))) in plane_sizes.iter().enumerate().take(planes) {
+            let (plane, tail) = std::mem::take(&mut rest).split_at_mut(stride*rows);
+            rest = tail;
+            crate::scale::resample_plane(input.planes[i], input.strides[i], src_size,
+                plane, stride, dst_size, rows);
+        }
+        Ok(output)
+    }
+
+    /// Converts a 4:2:0 image into a semi-planar image (NV12 or NV21).
+    ///
+    /// The U and V planes are interleaved into one UV plane in the order given by `format`. The
+    /// planes of the returned image are not padded (the strides are `y_size().0` and
+    /// `2*uv_size().0`). Returns [`Error::InvalidParam`] if the image does not use
+    /// [`Subsamp::Sub2x2`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let yuv = turbojpeg::decompress_to_yuv(&jpeg_data)?;
+    ///
+    /// let nv12 = yuv.to_semi_planar(turbojpeg::SemiPlanarFormat::NV12)?;
+    /// assert_eq!(nv12.strides, [64, 64]);
+    /// assert_eq!(nv12.planes[1][..2], [yuv.pixels[64*48], yuv.pixels[64*48 + 32*24]]);
+    ///
+    /// // convert it back
+    /// let planar = nv12.to_yuv(1);
+    /// assert_eq!(planar.pixels, yuv.pixels);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn to_semi_planar(&self, format: SemiPlanarFormat) -> Result<SemiPlanarImage<Vec<u8>>>
+        where T: Deref<Target = [u8]>
+    {
+        if self.subsamp != Subsamp::Sub2x2 {
+            return Err(Error::InvalidParam("subsamp"));
+        }
+        self.assert_valid(self.pixels.len());
+
+        let mut output = SemiPlanarImage {
+            planes: [Vec::new(), Vec::new()],
+            strides: [0, 0],
+            width: self.width,
+            height: self.height,
+            format,
+        };
+        let (y_width, y_height) = output.y_size();
+        let (uv_width, uv_height) = output.uv_size();
+        output.strides = [y_width, 2*uv_width];
+
+        let y_stride = self.y_width();
+        let uv_stride = self.uv_width();
+        let u_plane = &self.pixels[y_stride*self.y_height()..];
+        let v_plane = &u_plane[uv_stride*self.uv_height()..];
+
+        let mut y_out = Vec::with_capacity(y_width*y_height);
+        for row in 0..y_height {
+            y_out.extend_from_slice(&self.pixels[row*y_stride..][..y_width]);
+        }
+
+        let [u_offset, v_offset] = format.offsets();
+        let mut uv_out = vec![0; 2*uv_width*uv_height];
+        for (row, uv_row) in uv_out.chunks_exact_mut(2*uv_width.max(1)).take(uv_height).enumerate() {
+            let u_row = &u_plane[row*uv_stride..][..uv_width];
+            let v_row = &v_plane[row*uv_stride..][..uv_width];
+            for ((pair, &u), &v) in uv_row.chunks_exact_mut(2).zip(u_row).zip(v_row) {
+                pair[u_offset] = u;
+                pair[v_offset] = v;
+            }
+        }
+
+        output.planes = [y_out, uv_out];
+        Ok(output)
+    }
+
+    /// Borrows the Y, U and V planes of the image as [`YuvPlanes`].
+    ///
+    /// This is the opposite of packing separate planes into a `YuvImage`: the returned planes
+    /// refer to the contiguous pixel data without copying. For grayscale images, the U and V
+    /// planes are empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let yuv_image = turbojpeg::decompress_to_yuv(&jpeg_data)?;
+    ///
+    /// let planes = yuv_image.as_planes();
+    /// assert_eq!(planes.strides, [384, 384, 384]);
+    /// assert_eq!(planes.planes[1].as_ptr(), yuv_image.pixels[384*256..].as_ptr());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn as_planes(&self) -> YuvPlanes<&[u8]> where T: Deref<Target = [u8]> {
+        self.assert_valid(self.pixels.len());
+        let y_len = self.y_width()*self.y_height();
+        let (y_plane, uv_planes) = self.pixels.split_at(y_len);
+        let (u_plane, v_plane) = if self.subsamp == Subsamp::Gray {
+            (&uv_planes[..0], &uv_planes[..0])
+        } else {
+            let uv_len = self.uv_width()*self.uv_height();
+            (&uv_planes[..uv_len], &uv_planes[uv_len..][..uv_len])
+        };
+        YuvPlanes {
+            planes: [y_plane, u_plane, v_plane],
+            strides: [self.y_width(), self.uv_width(), self.uv_width()],
+            width: self.width,
+            height: self.height,
+            subsamp: self.subsamp,
+        }
+    }
+
     pub(crate) fn assert_valid(&self, pixels_len: usize) {
         let YuvImage { pixels: _, width, align, height, subsamp } = *self;
         let min_yuv_pixels_len = yuv_pixels_len(width, align, height, subsamp).unwrap();
@@ -312,12 +1164,365 @@ impl<T> YuvImage<T> {
     }
 }
 
-// TODO: these two functions will eventually be stabilized into the standard library
+/// A YUV (YCbCr) planar image stored in three separate planes with pixels of type `T`.
+///
+/// This is similar to [`YuvImage`], but each of the Y, U (Cb) and V (Cr) planes is stored in its
+/// own buffer with its own stride, as is common for frames produced by video decoders or capture
+/// pipelines. Images in this form can be compressed by
+/// [`Compressor::compress_yuv_planes()`][crate::Compressor::compress_yuv_planes] without packing
+/// them into one contiguous buffer first.
+///
+/// The size of each plane is determined in the same way as for [`YuvImage`] (without the row
+/// alignment). Data for the sample in column `x` and row `y` of plane `i` is stored in `planes[i]`
+/// at offset `y*strides[i] + x`.
+///
+/// # Example
+///
+/// ```
+/// let img = turbojpeg::YuvPlanes {
+///     planes: [(), (), ()],
+///     strides: [64, 32, 32],
+///     width: 35,
+///     height: 35,
+///     subsamp: turbojpeg::Subsamp::Sub2x2,
+/// };
+/// assert_eq!(img.y_size(), (36, 36));
+/// assert_eq!(img.uv_size(), (18, 18));
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct YuvPlanes<T> {
+    /// Pixel data of the Y, U and V planes (typically `&[u8]` or `Vec<u8>`). If the image is
+    /// grayscale ([`Subsamp::Gray`]), only the Y plane is used and the U and V planes may be empty.
+    pub planes: [T; 3],
+    /// Strides of the Y, U and V planes, the size of one plane row in bytes. We require that
+    /// each stride is at least the width of the corresponding plane.
+    pub strides: [usize; 3],
+    /// Width of the image in pixels (number of columns).
+    pub width: usize,
+    /// Height of the image in pixels (number of rows).
+    pub height: usize,
+    /// The level of chrominance subsampling used in the YUV image.
+    pub subsamp: Subsamp,
+}
+
+impl<T> YuvPlanes<T> {
+    /// Wraps three separate Y, U and V planes (I420 for [`Subsamp::Sub2x2`]) without copying.
+    ///
+    /// This is the usual way to wrap a frame from a video decoder or a camera that provides a
+    /// separate buffer (or pointer) for every plane. `T` is typically `&[u8]` (borrowed planes) or
+    /// `Vec<u8>` (owned planes). `size` is the width and height of the image in pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a stride is smaller than the width of its plane or if a plane is too short.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // planes of a 1280x720 I420 frame, as produced by a video decoder
+    /// let y = vec![128; 1280*720];
+    /// let u = vec![64; 640*360];
+    /// let v = vec![192; 640*360];
+    ///
+    /// let frame = turbojpeg::YuvPlanes::from_planes(
+    ///     &y[..], &u[..], &v[..], [1280, 640, 640], turbojpeg::Subsamp::Sub2x2, (1280, 720));
+    /// let jpeg_data = turbojpeg::Compressor::new()?.compress_yuv_planes_to_vec(frame)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (1280, 720));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn from_planes(y: T, u: T, v: T, strides: [usize; 3], subsamp: Subsamp, size: (usize, usize))
+        -> YuvPlanes<T> where T: AsRef<[u8]>
+    {
+        let (width, height) = size;
+        let planes = YuvPlanes { planes: [y, u, v], strides, width, height, subsamp };
+        let [y, u, v] = &planes.planes;
+        planes.assert_valid([y.as_ref().len(), u.as_ref().len(), v.as_ref().len()]);
+        planes
+    }
+
+    /// Converts from `&YuvPlanes<T>` to `YuvPlanes<&T::Target>`.
+    ///
+    /// In particular, you can use this to get `YuvPlanes<&[u8]>` from `YuvPlanes<Vec<u8>>`.
+    pub fn as_deref(&self) -> YuvPlanes<&T::Target> where T: Deref {
+        let [y, u, v] = &self.planes;
+        YuvPlanes {
+            planes: [y.deref(), u.deref(), v.deref()],
+            strides: self.strides,
+            width: self.width,
+            height: self.height,
+            subsamp: self.subsamp,
+        }
+    }
+
+    /// Computes size of the luminance (Y) plane.
+    ///
+    /// This is the image size padded to the nearest multiple of the [subsampling
+    /// factors][Subsamp::size()].
+    pub fn y_size(&self) -> (usize, usize) {
+        (self.width.next_multiple_of(self.subsamp.width()),
+            self.height.next_multiple_of(self.subsamp.height()))
+    }
+
+    /// Computes size of each chrominance (U, V) plane.
+    ///
+    /// This is the [Y plane size][Self::y_size()] divided by the [subsampling
+    /// factors][Subsamp::size()]. The size is zero for grayscale images.
+    pub fn uv_size(&self) -> (usize, usize) {
+        if self.subsamp == Subsamp::Gray {
+            return (0, 0);
+        }
+        (self.width.div_ceil(self.subsamp.width()), self.height.div_ceil(self.subsamp.height()))
+    }
+
+    pub(crate) fn assert_valid(&self, plane_lens: [usize; 3]) {
+        let sizes = [self.y_size(), self.uv_size(), self.uv_size()];
+        for (i, name) in ["Y", "U", "V"].into_iter().enumerate() {
+            let (plane_width, plane_height) = sizes[i];
+            let stride = self.strides[i];
+            assert!(stride >= plane_width,
+                "{} plane stride {} is too small for plane width {}", name, stride, plane_width);
+            assert!(plane_height == 0 || stride*(plane_height - 1) + plane_width <= plane_lens[i],
+                "{} plane length {} is too small for plane width {}, height {} and stride {}",
+                name, plane_lens[i], plane_width, plane_height, stride);
+        }
+    }
+}
+
+/// A packed YUV 4:2:2 image with pixels of type `T`.
+///
+/// Each row of the image consists of 4-byte macropixels, every macropixel stores two horizontally
+/// adjacent pixels in the given [format][PackedYuvFormat]. Data for the macropixel that contains
+/// pixels in columns `2*x` and `2*x + 1` and row `y` is stored in `pixels` at offset `y*pitch +
+/// 4*x`. If the width of the image is odd, the last macropixel in each row is only half used.
+///
+/// Images in this format can be compressed using
+/// [`Compressor::compress_packed_yuv()`][crate::Compressor::compress_packed_yuv] or converted to
+/// planar form using [`to_yuv_planes()`][Self::to_yuv_planes].
+#[derive(Debug, Copy, Clone)]
+pub struct PackedYuvImage<T> {
+    /// Pixel data of the image (typically `&[u8]` or `Vec<u8>`).
+    pub pixels: T,
+    /// Width of the image in pixels (number of columns).
+    pub width: usize,
+    /// Pitch (stride) defines the size of one image row in bytes. We require that rows contain
+    /// whole macropixels, so `pitch >= 4 * ceil(width / 2)`.
+    pub pitch: usize,
+    /// Height of the image in pixels (number of rows).
+    pub height: usize,
+    /// Order of the components in each macropixel.
+    pub format: PackedYuvFormat,
+}
+
+impl<T> PackedYuvImage<T> {
+    /// Converts from `&PackedYuvImage<T>` to `PackedYuvImage<&T::Target>`.
+    ///
+    /// In particular, you can use this to get `PackedYuvImage<&[u8]>` from
+    /// `PackedYuvImage<Vec<u8>>`.
+    pub fn as_deref(&self) -> PackedYuvImage<&T::Target> where T: Deref {
+        PackedYuvImage {
+            pixels: self.pixels.deref(),
+            width: self.width,
+            pitch: self.pitch,
+            height: self.height,
+            format: self.format,
+        }
+    }
+
+    /// Converts the image to separate Y, U and V planes with 4:2:2 subsampling.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // two rows of two pixels each
+    /// let image = turbojpeg::PackedYuvImage {
+    ///     pixels: &[16, 128, 32, 130, 48, 100, 64, 150][..],
+    ///     width: 2,
+    ///     pitch: 4,
+    ///     height: 2,
+    ///     format: turbojpeg::PackedYuvFormat::YUYV,
+    /// };
+    /// let planes = image.to_yuv_planes();
+    /// assert_eq!(planes.subsamp, turbojpeg::Subsamp::Sub2x1);
+    /// assert_eq!(planes.planes, [vec![16, 32, 48, 64], vec![128, 100], vec![130, 150]]);
+    /// ```
+    pub fn to_yuv_planes(&self) -> YuvPlanes<Vec<u8>> where T: Deref<Target = [u8]> {
+        self.assert_valid(self.pixels.len());
+        let [y0_offset, u_offset, y1_offset, v_offset] = self.format.offsets();
+        let macropixels = self.width.div_ceil(2);
+
+        let mut y_plane = Vec::with_capacity(2*macropixels*self.height);
+        let mut u_plane = Vec::with_capacity(macropixels*self.height);
+        let mut v_plane = Vec::with_capacity(macropixels*self.height);
+        for row in 0..self.height {
+            let row = &self.pixels[row*self.pitch..][..4*macropixels];
+            for macropixel in row.chunks_exact(4) {
+                y_plane.push(macropixel[y0_offset]);
+                y_plane.push(macropixel[y1_offset]);
+                u_plane.push(macropixel[u_offset]);
+                v_plane.push(macropixel[v_offset]);
+            }
+        }
+
+        YuvPlanes {
+            planes: [y_plane, u_plane, v_plane],
+            strides: [2*macropixels, macropixels, macropixels],
+            width: self.width,
+            height: self.height,
+            subsamp: Subsamp::Sub2x1,
+        }
+    }
 
-fn next_multiple_of(n: usize, divisor: usize) -> usize {
-    div_ceil(n, divisor) * divisor
+    pub(crate) fn assert_valid(&self, pixels_len: usize) {
+        let PackedYuvImage { pixels: _, width, pitch, height, format } = *self;
+        let row_len = width.div_ceil(2)*format.size();
+        assert!(pitch >= row_len,
+            "pitch {} is too small for width {} and packed YUV format {:?}", pitch, width, format);
+        assert!(height == 0 || pitch*(height - 1) + row_len <= pixels_len,
+            "pixels length {} is too small for width {}, height {}, pitch {} and packed YUV format {:?}",
+            pixels_len, width, height, pitch, format);
+    }
 }
 
-fn div_ceil(n: usize, divisor: usize) -> usize {
-    (n + divisor - 1) / divisor
+/// A semi-planar YUV 4:2:0 image with pixels of type `T`.
+///
+/// The image is stored in two planes: the luminance (Y) plane, with one sample for every pixel,
+/// and the chrominance (UV) plane, with one interleaved pair of U and V samples for every 2x2
+/// block of pixels. The order of the U and V samples is given by the [format][SemiPlanarFormat].
+///
+/// The Y plane is [`y_size()`][Self::y_size] samples large, and the UV plane is
+/// [`uv_size()`][Self::uv_size] sample pairs large (each pair takes two bytes). Data for the
+/// sample in column `x` and row `y` of the Y plane is stored in `planes[0]` at offset
+/// `y*strides[0] + x`, and data for the sample pair in column `x` and row `y` of the UV plane is
+/// stored in `planes[1]` at offset `y*strides[1] + 2*x`.
+///
+/// Images in this format can be compressed using
+/// [`Compressor::compress_semi_planar()`][crate::Compressor::compress_semi_planar].
+#[derive(Debug, Copy, Clone)]
+pub struct SemiPlanarImage<T> {
+    /// Pixel data of the Y and UV planes (typically `&[u8]` or `Vec<u8>`).
+    pub planes: [T; 2],
+    /// Strides of the Y and UV planes, the size of one plane row in bytes. We require that the Y
+    /// plane stride is at least `y_size().0` and the UV plane stride is at least `2*uv_size().0`.
+    pub strides: [usize; 2],
+    /// Width of the image in pixels (number of columns).
+    pub width: usize,
+    /// Height of the image in pixels (number of rows).
+    pub height: usize,
+    /// Order of the components in the UV plane.
+    pub format: SemiPlanarFormat,
+}
+
+impl<T> SemiPlanarImage<T> {
+    /// Converts from `&SemiPlanarImage<T>` to `SemiPlanarImage<&T::Target>`.
+    ///
+    /// In particular, you can use this to get `SemiPlanarImage<&[u8]>` from
+    /// `SemiPlanarImage<Vec<u8>>`.
+    pub fn as_deref(&self) -> SemiPlanarImage<&T::Target> where T: Deref {
+        let [y, uv] = &self.planes;
+        SemiPlanarImage {
+            planes: [y.deref(), uv.deref()],
+            strides: self.strides,
+            width: self.width,
+            height: self.height,
+            format: self.format,
+        }
+    }
+
+    /// Computes size of the luminance (Y) plane.
+    ///
+    /// This is the image size padded to the nearest multiple of 2.
+    pub fn y_size(&self) -> (usize, usize) {
+        (self.width.next_multiple_of(2), self.height.next_multiple_of(2))
+    }
+
+    /// Computes size of the chrominance (UV) plane in sample pairs.
+    ///
+    /// This is the image size divided by 2 and rounded up.
+    pub fn uv_size(&self) -> (usize, usize) {
+        (self.width.div_ceil(2), self.height.div_ceil(2))
+    }
+
+    /// Splits the interleaved UV plane into separate U and V planes.
+    ///
+    /// The returned planes are [`uv_size()`][Self::uv_size] samples large, without any padding.
+    pub(crate) fn deinterleave_uv(&self) -> [Vec<u8>; 2] where T: Deref<Target = [u8]> {
+        let [u_offset, v_offset] = self.format.offsets();
+        let (uv_width, uv_height) = self.uv_size();
+
+        let mut u_plane = Vec::with_capacity(uv_width*uv_height);
+        let mut v_plane = Vec::with_capacity(uv_width*uv_height);
+        for row in 0..uv_height {
+            let row = &self.planes[1][row*self.strides[1]..][..2*uv_width];
+            for pair in row.chunks_exact(2) {
+                u_plane.push(pair[u_offset]);
+                v_plane.push(pair[v_offset]);
+            }
+        }
+        [u_plane, v_plane]
+    }
+
+    /// Converts the image into a planar 4:2:0 [`YuvImage`] with rows aligned to `align` bytes.
+    ///
+    /// The interleaved UV plane is split into separate U and V planes. See
+    /// [`YuvImage::to_semi_planar()`] for the opposite conversion.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid or if `align` is not a power of 2.
+    pub fn to_yuv(&self, align: usize) -> YuvImage<Vec<u8>> where T: Deref<Target = [u8]> {
+        self.assert_valid([self.planes[0].len(), self.planes[1].len()]);
+        assert!(align.is_power_of_two(), "align {} is not a power of 2", align);
+
+        let mut output = YuvImage {
+            pixels: Vec::new(),
+            width: self.width,
+            align,
+            height: self.height,
+            subsamp: Subsamp::Sub2x2,
+        };
+        let pixels_len = yuv_pixels_len(self.width, align, self.height, Subsamp::Sub2x2).unwrap();
+        let mut pixels = vec![0; pixels_len];
+
+        let (y_width, y_height) = self.y_size();
+        let y_stride = output.y_width();
+        for row in 0..y_height {
+            pixels[row*y_stride..][..y_width]
+                .copy_from_slice(&self.planes[0][row*self.strides[0]..][..y_width]);
+        }
+
+        let (uv_width, uv_height) = self.uv_size();
+        let uv_stride = output.uv_width();
+        let (_, uv_planes) = pixels.split_at_mut(y_stride*y_height);
+        let (u_plane, v_plane) = uv_planes.split_at_mut(uv_stride*uv_height);
+        let [u_offset, v_offset] = self.format.offsets();
+        for row in 0..uv_height {
+            let uv_row = &self.planes[1][row*self.strides[1]..][..2*uv_width];
+            let u_row = &mut u_plane[row*uv_stride..][..uv_width];
+            let v_row = &mut v_plane[row*uv_stride..][..uv_width];
+            for ((pair, u), v) in uv_row.chunks_exact(2).zip(u_row).zip(v_row) {
+                *u = pair[u_offset];
+                *v = pair[v_offset];
+            }
+        }
+
+        output.pixels = pixels;
+        output
+    }
+
+    pub(crate) fn assert_valid(&self, plane_lens: [usize; 2]) {
+        let (y_width, y_height) = self.y_size();
+        let (uv_width, uv_height) = self.uv_size();
+        let sizes = [(y_width, y_height), (2*uv_width, uv_height)];
+        for (i, name) in ["Y", "UV"].into_iter().enumerate() {
+            let (plane_width, plane_height) = sizes[i];
+            let stride = self.strides[i];
+            assert!(stride >= plane_width,
+                "{} plane stride {} is too small for plane width {}", name, stride, plane_width);
+            assert!(plane_height == 0 || stride*(plane_height - 1) + plane_width <= plane_lens[i],
+                "{} plane length {} is too small for plane width {}, height {} and stride {}",
+                name, plane_lens[i], plane_width, plane_height, stride);
+        }
+    }
 }
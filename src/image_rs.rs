@@ -1,7 +1,7 @@
 use crate::Image;
 use crate::buf::OwnedBuf;
 use crate::compress::Compressor;
-use crate::common::{PixelFormat, Result, Subsamp};
+use crate::common::{Error, PixelFormat, Result, Subsamp};
 use crate::decompress::Decompressor;
 
 /// Decompresses image from JPEG into an [`image::ImageBuffer`].
@@ -108,3 +108,185 @@ impl JpegPixel for image::Rgba<u8> {
 impl JpegPixel for image::Luma<u8> {
     const PIXEL_FORMAT: PixelFormat = PixelFormat::GRAY;
 }
+
+/// Compresses an [`image::DynamicImage`] into JPEG.
+///
+/// Images with 8-bit RGB, RGBA or grayscale pixels are compressed directly, other images are
+/// first converted to 8-bit RGB (or RGBA, if they have an alpha channel). `quality` and `subsamp`
+/// work as in [`compress_image()`].
+///
+/// # Example
+///
+/// ```
+/// let image = image::DynamicImage::ImageRgb16(
+///     image::ImageBuffer::from_fn(64, 48, |x, y| image::Rgb([x as u16 * 1000, y as u16 * 1000, 0])),
+/// );
+/// let jpeg_data = turbojpeg::compress_dynamic_image(&image, 95, turbojpeg::Subsamp::Sub2x2)?;
+/// assert_eq!(turbojpeg::read_header(&jpeg_data)?.width, 64);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+pub fn compress_dynamic_image(
+    image: &image::DynamicImage,
+    quality: i32,
+    subsamp: Subsamp,
+) -> Result<OwnedBuf> {
+    let mut compressor = Compressor::new()?;
+    compressor.set_quality(quality)?;
+    compressor.set_subsamp(subsamp)?;
+    match Image::try_from(image) {
+        Ok(image) => compressor.compress_to_owned(image),
+        Err(_) if image.color().has_alpha() => {
+            compressor.compress_to_owned((&image.to_rgba8()).into())
+        },
+        Err(_) => compressor.compress_to_owned((&image.to_rgb8()).into()),
+    }
+}
+
+/// Moves the pixels of an [`image::ImageBuffer`] into an [`Image`] without copying.
+///
+/// # Example
+///
+/// ```
+/// let buffer = image::RgbImage::from_pixel(32, 16, image::Rgb([10, 20, 30]));
+/// let image = turbojpeg::Image::from(buffer);
+/// assert_eq!((image.width, image.height, image.format), (32, 16, turbojpeg::PixelFormat::RGB));
+/// assert_eq!(image.pixel(3, 4), &[10, 20, 30]);
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+impl<P> From<image::ImageBuffer<P, Vec<u8>>> for Image<Vec<u8>>
+    where P: JpegPixel + 'static
+{
+    fn from(image_buf: image::ImageBuffer<P, Vec<u8>>) -> Image<Vec<u8>> {
+        let (width, height) = image_buf.dimensions();
+        let format = P::PIXEL_FORMAT;
+        Image {
+            pixels: image_buf.into_raw(),
+            width: width as usize,
+            pitch: format.size() * width as usize,
+            height: height as usize,
+            format,
+        }
+    }
+}
+
+/// Borrows the pixels of an [`image::ImageBuffer`] as an [`Image`], for example to compress it.
+///
+/// # Example
+///
+/// ```
+/// let buffer = image::GrayImage::from_fn(64, 48, |x, y| image::Luma([(x ^ y) as u8]));
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// let jpeg_data = compressor.compress_to_owned((&buffer).into())?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+impl<'a, P> From<&'a image::ImageBuffer<P, Vec<u8>>> for Image<&'a [u8]>
+    where P: JpegPixel + 'static
+{
+    fn from(image_buf: &'a image::ImageBuffer<P, Vec<u8>>) -> Image<&'a [u8]> {
+        let (width, height) = image_buf.dimensions();
+        let format = P::PIXEL_FORMAT;
+        Image {
+            pixels: &image_buf.as_raw()[..],
+            width: width as usize,
+            pitch: format.size() * width as usize,
+            height: height as usize,
+            format,
+        }
+    }
+}
+
+/// Converts an [`Image`] (such as the result of [`decompress()`][crate::decompress]) into an
+/// [`image::ImageBuffer`].
+///
+/// If the image already has the pixel format of `P` and tightly packed rows, its pixels are
+/// moved without copying. Otherwise, the rows are repacked, and RGB images in other pixel
+/// formats are converted as by [`Image::convert()`]. Returns [`Error::InvalidParam`] if the pixel
+/// format cannot be converted (for example, from RGB to grayscale).
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let image = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::BGRA)?;
+/// let buffer = image::RgbImage::try_from(image)?;
+/// assert_eq!(buffer.dimensions(), (384, 256));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+impl<P> TryFrom<Image<Vec<u8>>> for image::ImageBuffer<P, Vec<u8>>
+    where P: JpegPixel + 'static
+{
+    type Error = Error;
+
+    fn try_from(image: Image<Vec<u8>>) -> Result<image::ImageBuffer<P, Vec<u8>>> {
+        image.as_deref().assert_valid(image.pixels.len());
+        let width = image.width.try_into().map_err(|_| Error::IntegerOverflow("width"))?;
+        let height = image.height.try_into().map_err(|_| Error::IntegerOverflow("height"))?;
+
+        let mut pixels = if image.format != P::PIXEL_FORMAT {
+            if image.format == PixelFormat::GRAY || P::PIXEL_FORMAT == PixelFormat::GRAY {
+                return Err(Error::InvalidParam("format"));
+            }
+            image.convert(P::PIXEL_FORMAT)?.pixels
+        } else if image.pitch != image.width * image.format.size() {
+            image.rows().flatten().copied().collect()
+        } else {
+            image.pixels
+        };
+
+        pixels.truncate(image.width * P::PIXEL_FORMAT.size() * image.height);
+        Ok(image::ImageBuffer::from_raw(width, height, pixels).unwrap())
+    }
+}
+
+/// Converts an [`Image`] (such as the result of [`decompress()`][crate::decompress]) into an
+/// [`image::DynamicImage`].
+///
+/// Grayscale images are converted into [`ImageLuma8`][image::DynamicImage::ImageLuma8], images
+/// with an alpha channel into [`ImageRgba8`][image::DynamicImage::ImageRgba8] and all other
+/// images into [`ImageRgb8`][image::DynamicImage::ImageRgb8]. Returns [`Error::InvalidParam`] for
+/// CMYK images.
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let image = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::RGB)?;
+/// let image = image::DynamicImage::try_from(image)?;
+/// assert!(matches!(image, image::DynamicImage::ImageRgb8(_)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+impl TryFrom<Image<Vec<u8>>> for image::DynamicImage {
+    type Error = Error;
+
+    fn try_from(image: Image<Vec<u8>>) -> Result<image::DynamicImage> {
+        Ok(match image.format {
+            PixelFormat::GRAY => image::DynamicImage::ImageLuma8(image.try_into()?),
+            PixelFormat::RGBA | PixelFormat::BGRA | PixelFormat::ARGB | PixelFormat::ABGR =>
+                image::DynamicImage::ImageRgba8(image.try_into()?),
+            PixelFormat::CMYK => return Err(Error::InvalidParam("format")),
+            _ => image::DynamicImage::ImageRgb8(image.try_into()?),
+        })
+    }
+}
+
+/// Borrows the pixels of an [`image::DynamicImage`] as an [`Image`], for example to compress it.
+///
+/// Only images with 8-bit RGB, RGBA or grayscale pixels can be borrowed, other images return
+/// [`Error::InvalidParam`]. Use [`compress_dynamic_image()`] to compress any image.
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+impl<'a> TryFrom<&'a image::DynamicImage> for Image<&'a [u8]> {
+    type Error = Error;
+
+    fn try_from(image: &'a image::DynamicImage) -> Result<Image<&'a [u8]>> {
+        match image {
+            image::DynamicImage::ImageRgb8(image_buf) => Ok(image_buf.into()),
+            image::DynamicImage::ImageRgba8(image_buf) => Ok(image_buf.into()),
+            image::DynamicImage::ImageLuma8(image_buf) => Ok(image_buf.into()),
+            _ => Err(Error::InvalidParam("image")),
+        }
+    }
+}
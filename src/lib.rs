@@ -1,17 +1,77 @@
+//! Rust bindings for [libjpeg-turbo](https://libjpeg-turbo.org/).
+//!
+//! # Linking
+//!
+//! The build script compiles the bundled libjpeg-turbo with CMake and links to its TurboJPEG
+//! library. This crate only uses the TurboJPEG API, except for `Compressor::compress_rows()`,
+//! smoothing (`Compressor::set_smoothing()`) and the streaming output of
+//! `Compressor::compress_to_writer()`, which need the incremental libjpeg API that TurboJPEG does
+//! not export. They are only available with the `libjpeg` feature: the build
+//! script then also compiles a small C wrapper (`ffi/rows.c`) with the [`cc`](https://docs.rs/cc)
+//! crate and links to the libjpeg library (`-ljpeg`) installed next to TurboJPEG, so both
+//! libraries must be available when the final binary is linked and run.
+
 mod ffi;
 
+#[cfg(feature = "rayon")]
+mod batch;
+mod bitmap;
 mod buf;
 mod common;
 mod compress;
+mod convert;
 mod decompress;
+mod exif;
 mod handle;
 mod image_internal;
+#[cfg(feature = "image")]
+mod image_rs;
+mod markers;
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "ndarray")]
+mod ndarray_rs;
+mod quality;
+mod rotate;
+mod scale;
+mod tables;
 mod transform;
-pub use self::buf::{OutputBuf, OwnedBuf};
-pub use self::common::{Colorspace, Error, PixelFormat, Result, Subsamp};
-pub use self::compress::{compress, compress_yuv, compressed_buf_len, Compressor};
+mod xmp;
+#[cfg(feature = "rayon")]
+pub use self::batch::{
+    compress_batch, compress_batch_arena, compress_tiles_parallel, transform_batch,
+    transform_batch_arena,
+};
+pub use self::bitmap::{load_image, load_image16, save_image, save_image16};
+pub use self::buf::{BufAllocator, BufPool, OutputArena, OutputBuf, OwnedBuf};
+pub use self::common::{
+    Colorspace, DensityUnit, Error, PackedYuvFormat, PixelFormat, Result, SemiPlanarFormat, Subsamp,
+};
+pub use self::compress::{
+    choose_subsamp, compress, compress_file, compress_gray, compress_yuv, compressed_buf_len,
+    encode_yuv, CompressParams, Compressor, CompressorBuilder, QualityTarget, Tile, TileGrid,
+};
 pub use self::decompress::{
-    decompress, decompress_to_yuv, read_header, yuv_pixels_len, DecompressHeader, Decompressor,
+    decode_yuv, decompress, decompress_to_yuv, read_header, read_icc_profile, yuv_pixels_len,
+    yuv_plane_sizes, yuv_plane_strides, DecompressHeader, Decompressor,
+};
+pub use self::exif::exif_orientation;
+pub use self::image_internal::{
+    Image, Image16, ImageOwned, ImageView, ImageViewMut, PackedYuvImage, SemiPlanarImage, YuvImage,
+    YuvPlanes,
+};
+#[cfg(feature = "image")]
+pub use self::image_rs::{compress_dynamic_image, compress_image, decompress_image, JpegPixel};
+pub use self::markers::{markers, Segment, Segments};
+pub use self::metrics::{psnr, ssim};
+#[cfg(feature = "mmap")]
+pub use self::mmap::MmapInput;
+pub use self::quality::{estimate_quality, recompress};
+pub use self::tables::JpegTables;
+pub use self::transform::{
+    losslessly_optimize, strip_metadata, transform, transform_to_slice, CropAdjustment, DctBlocks,
+    Gravity, KeepMarkers, RoundMode, Transform, TransformChain, TransformCrop, TransformOp,
+    TransformPipeline, TransformScale, TransformStats, Transformer,
 };
-pub use self::image_internal::{Image, YuvImage};
-pub use self::transform::{transform, Transform, TransformCrop, TransformOp, Transformer};
+pub use self::xmp::{read_xmp, replace_xmp};
@@ -0,0 +1,308 @@
+use crate::common::{Error, Result};
+
+/// Start of image.
+pub(crate) const SOI: u8 = 0xd8;
+/// End of image.
+pub(crate) const EOI: u8 = 0xd9;
+/// Start of scan.
+pub(crate) const SOS: u8 = 0xda;
+/// Define quantization tables.
+pub(crate) const DQT: u8 = 0xdb;
+/// Define Huffman tables.
+pub(crate) const DHT: u8 = 0xc4;
+/// Application segment 0 (APP1 to APP15 follow).
+pub(crate) const APP0: u8 = 0xe0;
+/// Comment.
+pub(crate) const COM: u8 = 0xfe;
+
+/// Identifier of ICC profile segments (APP2).
+pub(crate) const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+/// Identifier of XMP segments (APP1).
+pub(crate) const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Identifier of extended XMP segments (APP1).
+pub(crate) const XMP_EXTENSION_HEADER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+/// Identifier of Photoshop segments (APP13), which store IPTC data.
+pub(crate) const IPTC_HEADER: &[u8] = b"Photoshop 3.0\0";
+
+/// Returns true if the marker is a start of frame (SOF0 to SOF15, except DHT, JPG and DAC).
+pub(crate) fn is_sof(marker: u8) -> bool {
+    (0xc0..=0xcf).contains(&marker) && !matches!(marker, 0xc4 | 0xc8 | 0xcc)
+}
+
+/// Maximal length of the payload of a segment (the length field includes itself).
+pub(crate) const MAX_PAYLOAD_LEN: usize = 65533;
+
+/// A segment of a JPEG datastream, see [`markers()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Segment<'a> {
+    /// The marker code (the byte following `0xff`).
+    pub marker: u8,
+    /// Offset of the segment (the `0xff` byte of the marker) in the datastream.
+    pub offset: usize,
+    /// Total length of the segment in bytes, including the marker and the length field.
+    pub len: usize,
+    /// The payload of the segment (excluding the marker and the length field).
+    pub payload: &'a [u8],
+}
+
+impl Segment<'_> {
+    /// Returns true if the segment is an application segment (APP0 to APP15).
+    ///
+    /// The index of the application segment is `marker - 0xe0`.
+    pub fn is_app(&self) -> bool {
+        (APP0..=APP0 + 15).contains(&self.marker)
+    }
+
+    /// Returns true if the segment is written by the encoder to describe the encoding of the
+    /// image (the JFIF and Adobe segments).
+    pub(crate) fn is_encoder_header(&self) -> bool {
+        (self.marker == APP0 && self.payload.starts_with(b"JFIF\0"))
+            || (self.marker == APP0 + 14 && self.payload.starts_with(b"Adobe"))
+    }
+
+    /// Returns true if the segment is an extra marker, i.e. an application segment that is not
+    /// written by the encoder, or a comment.
+    pub(crate) fn is_extra(&self) -> bool {
+        (self.is_app() && !self.is_encoder_header()) || self.marker == COM
+    }
+}
+
+/// Iterator over the segments of a JPEG datastream, see [`markers()`].
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    data: &'a [u8],
+    pos: usize,
+    in_scan: bool,
+    done: bool,
+}
+
+/// Iterates over the segments of a JPEG datastream.
+///
+/// The iterator yields all segments from SOI to EOI (both inclusive). The entropy-coded data that
+/// follows each SOS segment is skipped. If the datastream is malformed, the iterator yields an
+/// error and stops.
+pub(crate) fn segments(jpeg: &[u8]) -> Segments<'_> {
+    Segments {
+        data: jpeg,
+        pos: 0,
+        in_scan: false,
+        done: false,
+    }
+}
+
+/// Enumerate the segments (markers) of a JPEG image.
+///
+/// This parses the datastream in pure Rust, without decompressing the image. The iterator yields
+/// every segment from SOI to EOI (both inclusive), including all APPn and COM segments, with its
+/// marker code, its offset in `jpeg_data` and its payload. The entropy-coded data that follows
+/// each SOS segment is skipped. If the datastream is malformed, the iterator yields an
+/// [`Error::InvalidJpeg`] and stops.
+///
+/// # Example
+///
+/// Check whether an image contains an ICC profile:
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let mut has_icc_profile = false;
+/// for segment in turbojpeg::markers(&jpeg_data) {
+///     let segment = segment?;
+///     if segment.marker == 0xe2 && segment.payload.starts_with(b"ICC_PROFILE\0") {
+///         has_icc_profile = true;
+///     }
+/// }
+/// assert!(has_icc_profile);
+///
+/// // the first segment is always SOI, the last one EOI
+/// let segments = turbojpeg::markers(&jpeg_data).collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!((segments[0].marker, segments[0].offset), (0xd8, 0));
+/// assert_eq!(segments.last().unwrap().marker, 0xd9);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn markers(jpeg_data: &[u8]) -> Segments<'_> {
+    segments(jpeg_data)
+}
+
+impl<'a> Segments<'a> {
+    fn next_segment(&mut self) -> Result<Segment<'a>> {
+        let data = self.data;
+        if self.pos == 0 {
+            if !data.starts_with(&[0xff, SOI]) {
+                return Err(Error::InvalidJpeg("missing SOI marker"));
+            }
+            self.pos = 2;
+            return Ok(Segment {
+                marker: SOI,
+                offset: 0,
+                len: 2,
+                payload: &[],
+            });
+        }
+
+        if self.in_scan {
+            // skip entropy-coded data, which may contain stuffed zeros and RSTn markers
+            loop {
+                match data.get(self.pos..self.pos + 2) {
+                    Some(&[0xff, next]) if next != 0 && !(0xd0..=0xd7).contains(&next) => break,
+                    Some(_) => self.pos += 1,
+                    None => return Err(Error::InvalidJpeg("unexpected end of entropy-coded data")),
+                }
+            }
+            self.in_scan = false;
+        }
+
+        let offset = self.pos;
+        if data.get(offset) != Some(&0xff) {
+            return Err(Error::InvalidJpeg("expected a marker"));
+        }
+        // a marker may be preceded by any number of fill bytes
+        let mut pos = offset + 1;
+        while data.get(pos) == Some(&0xff) {
+            pos += 1;
+        }
+        let marker = *data
+            .get(pos)
+            .ok_or(Error::InvalidJpeg("unexpected end of data"))?;
+        pos += 1;
+
+        let standalone = marker == 0x01 || (0xd0..=0xd9).contains(&marker);
+        let payload = if standalone {
+            &data[pos..pos]
+        } else {
+            let len = match data.get(pos..pos + 2) {
+                Some(&[hi, lo]) => u16::from_be_bytes([hi, lo]) as usize,
+                _ => return Err(Error::InvalidJpeg("unexpected end of data")),
+            };
+            if len < 2 || pos + len > data.len() {
+                return Err(Error::InvalidJpeg("invalid segment length"));
+            }
+            let payload = &data[pos + 2..pos + len];
+            pos += len;
+            payload
+        };
+
+        self.pos = pos;
+        match marker {
+            SOS => self.in_scan = true,
+            EOI => self.done = true,
+            _ => {}
+        }
+        Ok(Segment {
+            marker,
+            offset,
+            len: pos - offset,
+            payload,
+        })
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Result<Segment<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_segment();
+        if res.is_err() {
+            self.done = true;
+        }
+        Some(res)
+    }
+}
+
+/// Appends a segment with the given marker and payload to `output`.
+///
+/// The payload must not be longer than [`MAX_PAYLOAD_LEN`].
+pub(crate) fn write_segment(output: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    debug_assert!(payload.len() <= MAX_PAYLOAD_LEN);
+    output.extend_from_slice(&[0xff, marker]);
+    output.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    output.extend_from_slice(payload);
+}
+
+/// Reads the quantization tables (in zigzag order) that are defined before the first scan.
+///
+/// The result is indexed by the table identifier (0 to 3); later definitions of a table override
+/// earlier ones.
+pub(crate) fn quant_tables(jpeg: &[u8]) -> Result<[Option<[u16; 64]>; 4]> {
+    let mut tables = [None; 4];
+    for segment in segments(jpeg) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            break;
+        } else if segment.marker != DQT {
+            continue;
+        }
+
+        // a DQT segment may define several tables
+        let mut payload = segment.payload;
+        while let Some((&pq_tq, rest)) = payload.split_first() {
+            let sample_size = if pq_tq >> 4 == 0 { 1 } else { 2 };
+            if rest.len() < 64 * sample_size || pq_tq & 0x0f > 3 {
+                return Err(Error::InvalidJpeg("invalid DQT segment"));
+            }
+            let (values, rest) = rest.split_at(64 * sample_size);
+            let mut table = [0; 64];
+            for (i, value) in values.chunks_exact(sample_size).enumerate() {
+                table[i] = match *value {
+                    [value] => value as u16,
+                    [hi, lo] => u16::from_be_bytes([hi, lo]),
+                    _ => unreachable!(),
+                };
+            }
+            tables[(pq_tq & 0x0f) as usize] = Some(table);
+            payload = rest;
+        }
+    }
+    Ok(tables)
+}
+
+/// Reads the sample precision (in bits) of the frame.
+pub(crate) fn frame_precision(jpeg: &[u8]) -> Result<u8> {
+    for segment in segments(jpeg) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            break;
+        } else if is_sof(segment.marker) {
+            return segment
+                .payload
+                .first()
+                .copied()
+                .ok_or(Error::InvalidJpeg("invalid SOF segment"));
+        }
+    }
+    Err(Error::InvalidJpeg("missing SOF segment"))
+}
+
+/// Reads the quantization table (in zigzag order) of every component of the frame.
+pub(crate) fn component_quant_tables(jpeg: &[u8]) -> Result<Vec<[u16; 64]>> {
+    let tables = quant_tables(jpeg)?;
+    for segment in segments(jpeg) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            break;
+        } else if !is_sof(segment.marker) {
+            continue;
+        }
+
+        // precision (1), height (2), width (2), number of components (1), then 3 bytes per
+        // component: identifier, sampling factors and quantization table selector
+        let components = segment
+            .payload
+            .get(6..)
+            .ok_or(Error::InvalidJpeg("invalid SOF segment"))?;
+        return components
+            .chunks_exact(3)
+            .map(|component| {
+                tables
+                    .get(component[2] as usize)
+                    .copied()
+                    .flatten()
+                    .ok_or(Error::InvalidJpeg("missing quantization table"))
+            })
+            .collect();
+    }
+    Err(Error::InvalidJpeg("missing SOF segment"))
+}
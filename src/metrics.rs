@@ -0,0 +1,147 @@
+use crate::common::PixelFormat;
+use crate::Image;
+
+/// Peak signal-to-noise ratio between two images in decibels.
+///
+/// The ratio is computed over all color channels (the X and alpha channels are ignored). Returns
+/// infinity if the images are identical. The images may have different pitches, so a
+/// decompressed image can be compared with a [region][Image::region] of a larger image. This is
+/// the metric used by [`QualityTarget::Psnr`][crate::QualityTarget::Psnr].
+///
+/// # Panics
+///
+/// Panics if the images differ in width, height or pixel format, or if one of them is not valid.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(256, 256, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::None)?;
+/// let decompressed = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::RGB)?;
+///
+/// let psnr = turbojpeg::psnr(image.as_deref(), decompressed.as_deref());
+/// assert!(psnr > 25. && psnr.is_finite());
+/// assert_eq!(turbojpeg::psnr(image.as_deref(), image.as_deref()), f64::INFINITY);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn psnr(a: Image<&[u8]>, b: Image<&[u8]>) -> f64 {
+    assert_comparable(&a, &b);
+    let channels = color_channels(a.format);
+    let pixel_size = a.format.size();
+
+    let mut sum_sq = 0u64;
+    for y in 0..a.height {
+        let row_a = &a.pixels[y * a.pitch..];
+        let row_b = &b.pixels[y * b.pitch..];
+        for x in 0..a.width {
+            for &c in channels {
+                let diff = row_a[x * pixel_size + c] as i64 - row_b[x * pixel_size + c] as i64;
+                sum_sq += (diff * diff) as u64;
+            }
+        }
+    }
+
+    let count = a.width * a.height * channels.len();
+    if sum_sq == 0 || count == 0 {
+        return f64::INFINITY;
+    }
+    let mse = sum_sq as f64 / count as f64;
+    10. * f64::log10(255. * 255. / mse)
+}
+
+/// Structural similarity index between two images.
+///
+/// The index is computed separately for every color channel over 8x8 windows placed at every 4
+/// pixels in both directions, and the results are averaged. It ranges from -1 to 1, where 1 means
+/// that the images are identical. As with [`psnr()`], the images may have different pitches. This
+/// is the metric used by [`QualityTarget::Ssim`][crate::QualityTarget::Ssim].
+///
+/// # Panics
+///
+/// Panics if the images differ in width, height or pixel format, or if one of them is not valid.
+///
+/// # Example
+///
+/// Compare the quality of two settings in a regression test:
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(256, 256, turbojpeg::PixelFormat::RGB);
+/// let ssim_at = |quality| -> Result<f64, turbojpeg::Error> {
+///     let jpeg_data = turbojpeg::compress(image.as_deref(), quality, turbojpeg::Subsamp::Sub2x2)?;
+///     let decompressed = turbojpeg::decompress(&jpeg_data, turbojpeg::PixelFormat::RGB)?;
+///     Ok(turbojpeg::ssim(image.as_deref(), decompressed.as_deref()))
+/// };
+/// assert!(ssim_at(95)? > ssim_at(30)?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn ssim(a: Image<&[u8]>, b: Image<&[u8]>) -> f64 {
+    assert_comparable(&a, &b);
+    let channels = color_channels(a.format);
+    let pixel_size = a.format.size();
+    if a.width == 0 || a.height == 0 {
+        return 1.;
+    }
+
+    const C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+    const C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+    let win_w = usize::min(8, a.width);
+    let win_h = usize::min(8, a.height);
+    let win_len = (win_w * win_h) as f64;
+
+    let mut sum = 0.;
+    let mut count = 0;
+    for &c in channels {
+        for win_y in (0..=a.height - win_h).step_by(4) {
+            for win_x in (0..=a.width - win_w).step_by(4) {
+                let (mut sum_a, mut sum_b) = (0., 0.);
+                let (mut sum_aa, mut sum_bb, mut sum_ab) = (0., 0., 0.);
+                for y in win_y..win_y + win_h {
+                    for x in win_x..win_x + win_w {
+                        let va = a.pixels[y * a.pitch + x * pixel_size + c] as f64;
+                        let vb = b.pixels[y * b.pitch + x * pixel_size + c] as f64;
+                        sum_a += va;
+                        sum_b += vb;
+                        sum_aa += va * va;
+                        sum_bb += vb * vb;
+                        sum_ab += va * vb;
+                    }
+                }
+
+                let (mean_a, mean_b) = (sum_a / win_len, sum_b / win_len);
+                let var_a = sum_aa / win_len - mean_a * mean_a;
+                let var_b = sum_bb / win_len - mean_b * mean_b;
+                let cov = sum_ab / win_len - mean_a * mean_b;
+                sum += ((2. * mean_a * mean_b + C1) * (2. * cov + C2))
+                    / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+                count += 1;
+            }
+        }
+    }
+    sum / count as f64
+}
+
+fn assert_comparable(a: &Image<&[u8]>, b: &Image<&[u8]>) {
+    assert!(
+        a.width == b.width && a.height == b.height && a.format == b.format,
+        "cannot compare image {}x{} {:?} with image {}x{} {:?}",
+        a.width,
+        a.height,
+        a.format,
+        b.width,
+        b.height,
+        b.format
+    );
+    a.assert_valid(a.pixels.len());
+    b.assert_valid(b.pixels.len());
+}
+
+/// Offsets of the color channels (excluding X and alpha) in a pixel.
+fn color_channels(format: PixelFormat) -> &'static [usize] {
+    match format {
+        PixelFormat::RGB | PixelFormat::BGR => &[0, 1, 2],
+        PixelFormat::RGBX | PixelFormat::BGRX | PixelFormat::RGBA | PixelFormat::BGRA => &[0, 1, 2],
+        PixelFormat::XRGB | PixelFormat::XBGR | PixelFormat::ARGB | PixelFormat::ABGR => &[1, 2, 3],
+        PixelFormat::GRAY => &[0],
+        PixelFormat::CMYK => &[0, 1, 2, 3],
+    }
+}
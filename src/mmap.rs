@@ -0,0 +1,84 @@
+use crate::common::Result;
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+/// JPEG data mapped into memory from a file.
+///
+/// The file is mapped with `mmap()` on Unix and with `CreateFileMappingW()`/`MapViewOfFile()` on
+/// Windows, so the data is read from the page cache on demand instead of being copied into a heap
+/// buffer. `MmapInput` derefs to `&[u8]`, so it can be passed to every function that accepts JPEG
+/// data, such as [`decompress()`][crate::decompress()], [`read_header()`][crate::read_header()] or
+/// [`Transformer::transform()`][crate::Transformer::transform()].
+///
+/// The mapping is read-only. If the file is modified or truncated by another process while it is
+/// mapped, the data change under your hands, which is undefined behavior for a `&[u8]` (and on
+/// Unix, accessing a truncated part of the mapping raises `SIGBUS`). This is why the constructors
+/// are `unsafe`: the caller must ensure that the file is not modified while it is mapped.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+/// let path = std::env::temp_dir().join("turbojpeg-mmap-example.jpg");
+/// std::fs::write(&path, &jpeg_data)?;
+///
+/// // SAFETY: the file is not modified while it is mapped
+/// let input = unsafe { turbojpeg::MmapInput::open(&path)? };
+/// assert_eq!(&input[..], &jpeg_data[..]);
+/// let header = turbojpeg::read_header(&input)?;
+/// assert_eq!((header.width, header.height), (64, 48));
+/// # drop(input);
+/// # std::fs::remove_file(&path)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "mmap")))]
+pub struct MmapInput {
+    // empty files cannot be mapped
+    map: Option<memmap2::Mmap>,
+}
+
+impl MmapInput {
+    /// Opens the file at `path` and maps it into memory.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated (by this or any other process) until the
+    /// returned `MmapInput` is dropped.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<MmapInput> {
+        MmapInput::from_file(&File::open(path)?)
+    }
+
+    /// Maps an open `file` into memory.
+    ///
+    /// The mapping stays valid after the `file` is closed.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated (by this or any other process) until the
+    /// returned `MmapInput` is dropped.
+    pub unsafe fn from_file(file: &File) -> Result<MmapInput> {
+        if file.metadata()?.len() == 0 {
+            return Ok(MmapInput { map: None });
+        }
+        // SAFETY: the mapping is read-only, and the caller guarantees that the file is not
+        // modified while it is mapped
+        let map = memmap2::Mmap::map(file)?;
+        Ok(MmapInput { map: Some(map) })
+    }
+}
+
+impl Deref for MmapInput {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or(&[])
+    }
+}
+
+impl AsRef<[u8]> for MmapInput {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
@@ -0,0 +1,146 @@
+use crate::buf::OwnedBuf;
+use crate::common::{Error, PixelFormat, Result};
+use crate::compress::Compressor;
+use crate::Image;
+use ndarray::{ArrayView3, ArrayViewMut3, ShapeBuilder};
+use std::ops::{Deref, DerefMut};
+
+impl<T> Image<T> {
+    /// Borrows the pixels as an [`ndarray::ArrayView3`] without copying.
+    ///
+    /// The array has the shape `(height, width, channels)`, where `channels` is
+    /// [`format.size()`][PixelFormat::size]. The [pitch][Image::pitch] is mapped to the stride of
+    /// the first axis, so padding bytes at the end of the rows are not part of the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::alloc_aligned(30, 20, turbojpeg::PixelFormat::RGB, 64);
+    /// let array = image.as_array();
+    /// assert_eq!(array.shape(), &[20, 30, 3]);
+    /// assert_eq!(array.strides(), &[image.pitch as isize, 3, 1]);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn as_array(&self) -> ArrayView3<'_, u8>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        let (shape, strides) = self.array_layout();
+        let pixels = &self.pixels[..self.array_len()];
+        ArrayView3::from_shape(shape.strides(strides), pixels).unwrap()
+    }
+
+    /// Borrows the pixels as an [`ndarray::ArrayViewMut3`] without copying.
+    ///
+    /// See [`as_array()`][Self::as_array] for the layout of the array.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let mut image = turbojpeg::Image::alloc(64, 48, turbojpeg::PixelFormat::RGBA);
+    /// // fill the alpha channel
+    /// image.as_array_mut().slice_mut(ndarray::s![.., .., 3]).fill(255);
+    /// assert_eq!(image.pixel(10, 20), &[0, 0, 0, 255]);
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn as_array_mut(&mut self) -> ArrayViewMut3<'_, u8>
+    where
+        T: DerefMut<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        let (shape, strides) = self.array_layout();
+        let len = self.array_len();
+        let pixels = &mut self.pixels[..len];
+        ArrayViewMut3::from_shape(shape.strides(strides), pixels).unwrap()
+    }
+
+    fn array_layout(&self) -> ((usize, usize, usize), (usize, usize, usize)) {
+        let size = self.format.size();
+        ((self.height, self.width, size), (self.pitch, size, 1))
+    }
+
+    /// Returns the number of bytes spanned by the array, excluding the padding of the last row.
+    fn array_len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            height => self.pitch * (height - 1) + self.width * self.format.size(),
+        }
+    }
+}
+
+impl<'a> Image<&'a [u8]> {
+    /// Borrows an [`ndarray::ArrayView3`] as an image with the given pixel `format`.
+    ///
+    /// The array must have the shape `(height, width, format.size())` and be in standard
+    /// (row-major, contiguous) layout, so that the image can be borrowed without copying. Returns
+    /// [`Error::InvalidParam`] otherwise. Use [`Compressor::compress_array()`] to compress arrays
+    /// in any layout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let array = ndarray::Array3::from_shape_fn((48, 64, 3), |(y, x, c)| (x * y * c) as u8);
+    /// let image = turbojpeg::Image::from_array(array.view(), turbojpeg::PixelFormat::RGB)?;
+    /// assert_eq!((image.width, image.height, image.pitch), (64, 48, 64 * 3));
+    ///
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_to_owned(image)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn from_array(array: ArrayView3<'a, u8>, format: PixelFormat) -> Result<Image<&'a [u8]>> {
+        let (height, width, channels) = array.dim();
+        if channels != format.size() {
+            return Err(Error::InvalidParam("format"));
+        }
+        let pixels = array.to_slice().ok_or(Error::InvalidParam("array"))?;
+        Ok(Image {
+            pixels,
+            width,
+            pitch: width * channels,
+            height,
+            format,
+        })
+    }
+}
+
+impl Compressor {
+    /// Compresses an [`ndarray::ArrayView3`] with the given pixel `format` into an owned buffer.
+    ///
+    /// The array must have the shape `(height, width, format.size())`, otherwise
+    /// [`Error::InvalidParam`] is returned. Arrays in standard layout are compressed without
+    /// copying, other arrays (such as slices or transposed views) are first copied into standard
+    /// layout.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let array = ndarray::Array3::from_shape_fn((64, 48, 1), |(y, x, _)| (x ^ y) as u8);
+    /// // compress the transposed array, which has the shape (48, 64, 1)
+    /// let array = array.view().permuted_axes([1, 0, 2]);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// let jpeg_data = compressor.compress_array(array, turbojpeg::PixelFormat::GRAY)?;
+    ///
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    /// assert_eq!((header.width, header.height), (64, 48));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg_attr(docsrs, doc(cfg(feature = "ndarray")))]
+    pub fn compress_array(
+        &mut self,
+        array: ArrayView3<'_, u8>,
+        format: PixelFormat,
+    ) -> Result<OwnedBuf> {
+        let array = array.as_standard_layout();
+        self.compress_to_owned(Image::from_array(array.view(), format)?)
+    }
+}
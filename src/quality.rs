@@ -0,0 +1,128 @@
+use crate::buf::OwnedBuf;
+use crate::common::{Colorspace, PixelFormat, Result};
+use crate::compress::{CompressParams, Compressor};
+use crate::decompress::Decompressor;
+use crate::markers;
+use crate::Image;
+
+/// The luminance quantization table from the JPEG standard (Annex K), in natural order.
+///
+/// libjpeg scales this table to produce the quantization table for a given quality.
+const STD_LUMINANCE_TABLE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// Natural-order index of every coefficient in the zigzag order used by DQT segments.
+pub(crate) const ZIGZAG_TO_NATURAL: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Estimate the quality that was used to compress a JPEG image.
+///
+/// The quality is estimated by comparing the luminance quantization table of the image with the
+/// tables that libjpeg (and most other encoders) derive from the standard table for each quality
+/// from 1 to 100. The result is exact for images produced by libjpeg-based encoders (such as this
+/// crate), and an approximation for other encoders.
+///
+/// Returns `None` if the image does not contain a quantization table (for example, lossless JPEG
+/// images or abbreviated datastreams).
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 60, turbojpeg::Subsamp::Sub2x2)?;
+/// assert_eq!(turbojpeg::estimate_quality(&jpeg_data)?, Some(60));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn estimate_quality(jpeg_data: &[u8]) -> Result<Option<i32>> {
+    // libjpeg-based encoders use table 0 for the luminance
+    let table = match markers::quant_tables(jpeg_data)?[0] {
+        Some(table) => table,
+        None => return Ok(None),
+    };
+
+    let mut best = None;
+    for quality in 1..=100 {
+        let error: u32 = scaled_table(quality)
+            .iter()
+            .zip(table.iter())
+            .map(|(&expected, &actual)| u32::from(expected.abs_diff(actual)))
+            .sum();
+        if best.map_or(true, |(_, best_error)| error < best_error) {
+            best = Some((quality, error));
+        }
+    }
+    Ok(best.map(|(quality, _)| quality))
+}
+
+/// Decompress a JPEG image and compress it again, at most with the quality of the original.
+///
+/// The image is compressed using `params`, but the quality is capped to the
+/// [estimated quality][estimate_quality()] of `jpeg_data`: re-encoding an image with a higher
+/// quality than the original only wastes space, because the lost details cannot be restored.
+/// Grayscale and CMYK images are decompressed and compressed in their own pixel format, all other
+/// images in RGB.
+///
+/// # Example
+///
+/// ```
+/// let image = turbojpeg::Image::mandelbrot(500, 500, turbojpeg::PixelFormat::RGB);
+/// let jpeg_data = turbojpeg::compress(image.as_deref(), 60, turbojpeg::Subsamp::Sub2x2)?;
+///
+/// let mut params = turbojpeg::CompressParams::default();
+/// params.quality = 95;
+/// params.subsamp = turbojpeg::Subsamp::Sub2x2;
+/// let recompressed = turbojpeg::recompress(&jpeg_data, &params)?;
+/// assert_eq!(turbojpeg::estimate_quality(&recompressed)?, Some(60));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn recompress(jpeg_data: &[u8], params: &CompressParams) -> Result<OwnedBuf> {
+    let mut params = params.clone();
+    if let Some(quality) = estimate_quality(jpeg_data)? {
+        params.quality = i32::min(params.quality, quality);
+    }
+    let mut compressor = Compressor::new()?;
+    compressor.set_params(&params)?;
+
+    let mut decompressor = Decompressor::new()?;
+    let header = decompressor.read_header(jpeg_data)?;
+    let format = match header.colorspace {
+        Colorspace::Gray => PixelFormat::GRAY,
+        Colorspace::CMYK | Colorspace::YCCK => PixelFormat::CMYK,
+        _ => PixelFormat::RGB,
+    };
+    let pitch = header.width * format.size();
+    let mut image = Image {
+        pixels: vec![0; header.height * pitch],
+        width: header.width,
+        pitch,
+        height: header.height,
+        format,
+    };
+    decompressor.decompress(jpeg_data, image.as_deref_mut())?;
+
+    compressor.compress_to_owned(image.as_deref())
+}
+
+/// Computes the luminance quantization table that libjpeg uses for `quality`, in zigzag order.
+///
+/// This mirrors `jpeg_quality_scaling()` and `jpeg_add_quant_table()` with baseline-compatible
+/// values (which is what TurboJPEG uses).
+fn scaled_table(quality: i32) -> [u16; 64] {
+    let scale = if quality < 50 {
+        5000 / quality
+    } else {
+        200 - 2 * quality
+    };
+    let mut table = [0; 64];
+    for (zigzag, &natural) in ZIGZAG_TO_NATURAL.iter().enumerate() {
+        let value = (STD_LUMINANCE_TABLE[natural] as i32 * scale + 50) / 100;
+        table[zigzag] = value.clamp(1, 255) as u16;
+    }
+    table
+}
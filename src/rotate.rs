@@ -0,0 +1,180 @@
+use crate::{Image, TransformOp};
+use std::ops::{Deref, DerefMut};
+
+impl<T> Image<T> {
+    /// Applies the transform operation `op` to the pixels, returning a new image.
+    ///
+    /// This is the pixel-domain counterpart of the lossless transforms performed by
+    /// [`Transformer`][crate::Transformer]: it works on images that are already decompressed and
+    /// it has no restrictions on the image size (there are no partial MCU blocks). Operations
+    /// that transpose the image swap its width and height. The returned image has tightly packed
+    /// rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// Make a decompressed image upright according to its EXIF orientation:
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let orientation = 6; // rotated by 90 degrees counter-clockwise
+    /// let op = turbojpeg::TransformOp::from_exif_orientation(orientation).unwrap();
+    ///
+    /// let upright = image.transformed(op);
+    /// assert_eq!((upright.width, upright.height), (48, 64));
+    /// assert_eq!(upright.pixel(47, 0), image.pixel(0, 0));
+    /// ```
+    pub fn transformed(&self, op: TransformOp) -> Image<Vec<u8>>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        let (transpose, hflip, vflip) = op.to_flips();
+        let (width, height) = if transpose {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+
+        let size = self.format.size();
+        let mut output = Image::alloc(width, height, self.format);
+        for (y, row) in output.rows_mut().enumerate() {
+            let y = if vflip { height - 1 - y } else { y };
+            for (x, pixel) in row.chunks_exact_mut(size).enumerate() {
+                let x = if hflip { width - 1 - x } else { x };
+                let (src_x, src_y) = if transpose { (y, x) } else { (x, y) };
+                pixel.copy_from_slice(&self.pixels[src_y * self.pitch + src_x * size..][..size]);
+            }
+        }
+        output
+    }
+
+    /// Rotates the image clockwise by 90 degrees, returning a new image.
+    ///
+    /// This is the same as [`transformed(TransformOp::Rot90)`][Self::transformed].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::GRAY);
+    /// let rotated = image.rotate90();
+    /// assert_eq!((rotated.width, rotated.height), (48, 64));
+    /// assert_eq!(rotated.pixel(47, 0), image.pixel(0, 0));
+    /// assert_eq!(rotated.rotate270().pixels, image.pixels);
+    /// ```
+    pub fn rotate90(&self) -> Image<Vec<u8>>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        self.transformed(TransformOp::Rot90)
+    }
+
+    /// Rotates the image counter-clockwise by 90 degrees, returning a new image.
+    ///
+    /// This is the same as [`transformed(TransformOp::Rot270)`][Self::transformed].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    pub fn rotate270(&self) -> Image<Vec<u8>>
+    where
+        T: Deref<Target = [u8]>,
+    {
+        self.transformed(TransformOp::Rot270)
+    }
+
+    /// Rotates the image by 180 degrees in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::BGRA);
+    /// let mut rotated = image.clone();
+    /// rotated.rotate180();
+    /// assert_eq!(rotated.pixel(63, 47), image.pixel(0, 0));
+    /// assert_eq!(rotated.pixels, image.transformed(turbojpeg::TransformOp::Rot180).pixels);
+    /// ```
+    pub fn rotate180(&mut self)
+    where
+        T: DerefMut<Target = [u8]>,
+    {
+        self.hflip();
+        self.vflip();
+    }
+
+    /// Flips (mirrors) the image horizontally in place.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut flipped = image.clone();
+    /// flipped.hflip();
+    /// assert_eq!(flipped.pixel(63, 10), image.pixel(0, 10));
+    /// ```
+    pub fn hflip(&mut self)
+    where
+        T: DerefMut<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        let size = self.format.size();
+        for row in self.rows_mut() {
+            // reversing the bytes of the row reverses the order of the pixels, but also the order
+            // of the bytes within each pixel, which we have to restore
+            row.reverse();
+            for pixel in row.chunks_exact_mut(size) {
+                pixel.reverse();
+            }
+        }
+    }
+
+    /// Flips (mirrors) the image vertically in place.
+    ///
+    /// The padding at the end of the rows is not moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the image is not valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGBA);
+    /// let mut flipped = image.clone();
+    /// flipped.vflip();
+    /// assert_eq!(flipped.row(47), image.row(0));
+    /// ```
+    pub fn vflip(&mut self)
+    where
+        T: DerefMut<Target = [u8]>,
+    {
+        self.as_deref().assert_valid(self.pixels.len());
+        let Image {
+            ref mut pixels,
+            width,
+            pitch,
+            height,
+            format,
+        } = *self;
+        let row_len = width * format.size();
+        for y in 0..height / 2 {
+            let (top, bottom) = pixels.split_at_mut((height - 1 - y) * pitch);
+            top[y * pitch..][..row_len].swap_with_slice(&mut bottom[..row_len]);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::f64::consts::PI;
+
+/// Downscales DCT blocks by a rational factor `num / denom` without decompressing them.
+///
+/// Every sample of the output image is the average of the input samples that it covers, with
+/// fractional weights at the edges (the same box filter as [`resample_plane()`]). The input
+/// samples are given by the inverse DCT of the input blocks and the output block is the DCT of
+/// the averaged samples. All of these steps are linear, so in one dimension an output block is
+/// obtained by applying one 8×8 matrix to each input block that it covers.
+///
+/// `num` output blocks cover exactly `denom` input blocks, so the matrices only depend on the
+/// position of the output block modulo `num`.
+#[derive(Debug)]
+pub(crate) struct DctDownscaler {
+    num: usize,
+    denom: usize,
+    /// `phases[q]` describes the output blocks at position `q` modulo `num`: the index of the
+    /// first input block that they cover (relative to the start of the period), and one matrix
+    /// for each covered input block. `matrix[k][a]` maps the coefficient `a` of the input block to
+    /// the coefficient `k` of the output block (in one dimension).
+    phases: Vec<(usize, Vec<[[f64; 8]; 8]>)>,
+}
+
+impl DctDownscaler {
+    /// Creates a downscaler for the factor `num / denom`, where `1 <= num <= denom`.
+    pub fn new(num: usize, denom: usize) -> DctDownscaler {
+        assert!(
+            0 < num && num <= denom,
+            "unsupported scaling factor {}/{}",
+            num,
+            denom
+        );
+        // the output sample x covers the input samples in x * ratio..(x + 1) * ratio
+        let ratio = denom as f64 / num as f64;
+        let phases = (0..num)
+            .map(|q| {
+                let first = q * denom / num;
+                let last = ((q + 1) * denom).div_ceil(num);
+                let matrices = (first..last)
+                    .map(|block| {
+                        let mut matrix = [[0.0; 8]; 8];
+                        for (k, row) in matrix.iter_mut().enumerate() {
+                            for x in 0..8 {
+                                let start = (8 * q + x) as f64 * ratio;
+                                let end = start + ratio;
+                                for m in 0..8 {
+                                    let sample = (8 * block + m) as f64;
+                                    let weight = (end.min(sample + 1.0) - start.max(sample))
+                                        .max(0.0)
+                                        / ratio;
+                                    if weight == 0.0 {
+                                        continue;
+                                    }
+                                    let factor = dct_basis(8, k, x) * weight;
+                                    for (a, value) in row.iter_mut().enumerate() {
+                                        *value += factor * dct_basis(8, a, m);
+                                    }
+                                }
+                            }
+                        }
+                        matrix
+                    })
+                    .collect();
+                (first, matrices)
+            })
+            .collect();
+        DctDownscaler { num, denom, phases }
+    }
+
+    /// Returns the index of the first input block that is covered by the output block `index`
+    /// and the matrices of the covered blocks (in one dimension).
+    fn phase(&self, index: usize) -> (usize, &[[[f64; 8]; 8]]) {
+        let (first, ref matrices) = self.phases[index % self.num];
+        (index / self.num * self.denom + first, matrices)
+    }
+
+    /// Computes the output block in `row` and `column` from the input blocks that it covers.
+    ///
+    /// `source(i, j)` returns the input block in row `i` and column `j` of the input image. The
+    /// blocks are quantized with `quant_table` (all in natural order), and the output coefficients
+    /// are clamped to `-limit..=limit` before they are quantized.
+    pub fn downscale<'a>(
+        &self,
+        row: usize,
+        column: usize,
+        source: impl Fn(usize, usize) -> &'a [i16],
+        quant_table: &[u16; 64],
+        limit: i32,
+        output: &mut [i16],
+    ) {
+        let (first_row, row_matrices) = self.phase(row);
+        let (first_column, column_matrices) = self.phase(column);
+        let mut result = [[0.0f64; 8]; 8];
+        for (i, rows) in row_matrices.iter().enumerate() {
+            // horizontal pass over the blocks in row i: partial[a][u] for input row frequency a
+            let mut partial = [[0.0f64; 8]; 8];
+            for (j, columns) in column_matrices.iter().enumerate() {
+                let block = source(first_row + i, first_column + j);
+                for (a, partial_row) in partial.iter_mut().enumerate() {
+                    for b in 0..8 {
+                        let coef = block[8 * a + b] as f64 * quant_table[8 * a + b] as f64;
+                        if coef == 0.0 {
+                            continue;
+                        }
+                        for (u, value) in partial_row.iter_mut().enumerate() {
+                            *value += coef * columns[u][b];
+                        }
+                    }
+                }
+            }
+
+            // vertical pass
+            for (v, result_row) in result.iter_mut().enumerate() {
+                for (a, partial_row) in partial.iter().enumerate() {
+                    let factor = rows[v][a];
+                    for (value, partial) in result_row.iter_mut().zip(partial_row) {
+                        *value += factor * partial;
+                    }
+                }
+            }
+        }
+
+        let limit = limit as f64;
+        for (k, coef) in output.iter_mut().take(64).enumerate() {
+            let value = result[k / 8][k % 8].clamp(-limit, limit);
+            *coef = (value / quant_table[k] as f64).round() as i16;
+        }
+    }
+}
+
+/// Returns the orthonormal DCT-II basis function of frequency `k` at sample `m` for `n` samples.
+fn dct_basis(n: usize, k: usize, m: usize) -> f64 {
+    let scale = if k == 0 { 1.0 } else { 2.0 };
+    (scale / n as f64).sqrt() * (PI * (2 * m + 1) as f64 * k as f64 / (2 * n) as f64).cos()
+}
+
+/// Resamples one image plane by averaging the source samples that are covered by each
+/// destination sample (a box filter with fractional weights at the edges).
+///
+/// The plane has `src_size` samples (width, height) in `src` and `dst_size` samples in `dst`. The
+/// remaining columns of each destination row (up to `dst_stride`) and the remaining rows (up to
+/// `dst_rows`) are filled by replicating the last column and row, as TurboJPEG does for the
+/// padding of YUV planes.
+pub(crate) fn resample_plane(
+    src: &[u8],
+    src_stride: usize,
+    src_size: (usize, usize),
+    dst: &mut [u8],
+    dst_stride: usize,
+    dst_size: (usize, usize),
+    dst_rows: usize,
+) {
+    let (src_width, src_height) = src_size;
+    let (dst_width, dst_height) = dst_size;
+    if dst_width == 0 || dst_height == 0 {
+        return;
+    }
+
+    // horizontal pass: weighted sums of the samples in each source row
+    let columns = box_weights(src_width, dst_width);
+    let mut sums = vec![0u64; src_height * dst_width];
+    for (src_row, sums_row) in src.chunks(src_stride).zip(sums.chunks_exact_mut(dst_width)) {
+        for ((start, weights), sum) in columns.iter().zip(sums_row) {
+            *sum = weights
+                .iter()
+                .zip(&src_row[*start..])
+                .map(|(&weight, &sample)| weight * sample as u64)
+                .sum();
+        }
+    }
+
+    // vertical pass: weighted sums of the rows, normalized by the total weight
+    let total = (src_width * src_height) as u64;
+    for (y, (start, weights)) in box_weights(src_height, dst_height).iter().enumerate() {
+        let dst_row = &mut dst[y * dst_stride..][..dst_width];
+        for (x, sample) in dst_row.iter_mut().enumerate() {
+            let sum: u64 = weights
+                .iter()
+                .enumerate()
+                .map(|(i, &weight)| weight * sums[(start + i) * dst_width + x])
+                .sum();
+            *sample = ((sum + total / 2) / total) as u8;
+        }
+    }
+
+    // padding
+    for row in dst.chunks_mut(dst_stride).take(dst_height) {
+        let last = row[dst_width - 1];
+        row[dst_width..].fill(last);
+    }
+    let (rows, padding) = dst.split_at_mut(dst_height * dst_stride);
+    let last_row = &rows[(dst_height - 1) * dst_stride..];
+    for row in padding.chunks_mut(dst_stride).take(dst_rows - dst_height) {
+        row.copy_from_slice(&last_row[..row.len()]);
+    }
+}
+
+/// Computes the first covered source sample and the weights of the covered source samples for
+/// every destination sample. The weights of each destination sample sum to `src_len`.
+fn box_weights(src_len: usize, dst_len: usize) -> Vec<(usize, Vec<u64>)> {
+    (0..dst_len)
+        .map(|i| {
+            // destination sample i covers [i * src_len, (i + 1) * src_len) and source sample j
+            // covers [j * dst_len, (j + 1) * dst_len)
+            let (begin, end) = (i * src_len, (i + 1) * src_len);
+            let start = begin / dst_len;
+            let weights = (start..end.div_ceil(dst_len))
+                .map(|j| (end.min((j + 1) * dst_len) - begin.max(j * dst_len)) as u64)
+                .collect();
+            (start, weights)
+        })
+        .collect()
+}
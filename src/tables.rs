@@ -0,0 +1,148 @@
+use crate::common::{Error, Result};
+use crate::markers::{self, Segment};
+
+/// Quantization and Huffman tables shared by abbreviated JPEG datastreams.
+///
+/// A JPEG image normally contains all tables that are needed to decode it (an "interchange"
+/// datastream). When many images with the same tables are transmitted, such as frames of an
+/// MJPEG stream, the tables can be sent only once in a "tables-only" datastream, and the images
+/// can be sent as "abbreviated" datastreams without the tables. This saves a few hundred bytes
+/// per image.
+///
+/// The tables are obtained from a complete JPEG image using [`JpegTables::from_jpeg()`], images
+/// with the same tables are abbreviated using [`abbreviate()`][Self::abbreviate], and abbreviated
+/// images are converted back to complete images (which can be decompressed as usual) using
+/// [`expand()`][Self::expand].
+///
+/// Note that all images must be compressed with the same quality, and without
+/// [optimized][crate::Compressor::set_optimize] or [progressive][crate::Compressor::set_progressive]
+/// entropy coding, which compute different Huffman tables for every image.
+///
+/// # Example
+///
+/// ```
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// compressor.set_quality(80)?;
+///
+/// // send the tables once
+/// let first_frame = turbojpeg::Image::mandelbrot(320, 240, turbojpeg::PixelFormat::RGB);
+/// let first_jpeg = compressor.compress_to_vec(first_frame.as_deref())?;
+/// let tables = turbojpeg::JpegTables::from_jpeg(&first_jpeg)?;
+/// let tables_bytes = tables.as_bytes().to_vec();
+///
+/// // send the frames without the tables
+/// let frame = turbojpeg::Image::mandelbrot(320, 240, turbojpeg::PixelFormat::BGR);
+/// let jpeg_data = compressor.compress_to_vec(frame.as_deref())?;
+/// let abbreviated = tables.abbreviate(&jpeg_data)?;
+/// assert!(abbreviated.len() + 400 < jpeg_data.len());
+///
+/// // on the receiving side, restore the frames using the tables
+/// let tables = turbojpeg::JpegTables::from_bytes(&tables_bytes)?;
+/// let restored = tables.expand(&abbreviated)?;
+/// let image = turbojpeg::decompress(&restored, turbojpeg::PixelFormat::RGB)?;
+/// assert_eq!((image.width, image.height), (320, 240));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JpegTables {
+    data: Vec<u8>,
+}
+
+impl JpegTables {
+    /// Extracts the quantization and Huffman tables from a complete JPEG image.
+    ///
+    /// All tables defined before the first scan of the image are extracted.
+    pub fn from_jpeg(jpeg: &[u8]) -> Result<JpegTables> {
+        let mut data = vec![0xff, markers::SOI];
+        for segment in markers::segments(jpeg) {
+            let segment = segment?;
+            if segment.marker == markers::SOS {
+                break;
+            } else if is_table(&segment) {
+                data.extend_from_slice(&jpeg[segment.offset..][..segment.len]);
+            }
+        }
+        data.extend_from_slice(&[0xff, markers::EOI]);
+        Ok(JpegTables { data })
+    }
+
+    /// Parses a tables-only JPEG datastream (as returned by [`as_bytes()`][Self::as_bytes]).
+    pub fn from_bytes(data: &[u8]) -> Result<JpegTables> {
+        for segment in markers::segments(data) {
+            let segment = segment?;
+            if !matches!(segment.marker, markers::SOI | markers::EOI) && !is_table(&segment) {
+                return Err(Error::InvalidJpeg(
+                    "unexpected marker in tables-only datastream",
+                ));
+            }
+        }
+        Ok(JpegTables {
+            data: data.to_vec(),
+        })
+    }
+
+    /// Returns the tables as a tables-only JPEG datastream.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Removes the tables from a complete JPEG image, producing an abbreviated datastream.
+    ///
+    /// Returns [`Error::TablesMismatch`] if the image defines a table that is different from
+    /// these tables, because such image cannot be restored using these tables.
+    pub fn abbreviate(&self, jpeg: &[u8]) -> Result<Vec<u8>> {
+        let tables = self.segments();
+        let mut output = Vec::with_capacity(jpeg.len());
+        for segment in markers::segments(jpeg) {
+            let segment = segment?;
+            let bytes = &jpeg[segment.offset..][..segment.len];
+            if segment.marker == markers::SOS {
+                output.extend_from_slice(&jpeg[segment.offset..]);
+                return Ok(output);
+            } else if is_table(&segment) {
+                if !tables.iter().any(|table| *table == bytes) {
+                    return Err(Error::TablesMismatch);
+                }
+            } else {
+                output.extend_from_slice(bytes);
+            }
+        }
+        Err(Error::InvalidJpeg("missing SOS marker"))
+    }
+
+    /// Inserts the tables into an abbreviated JPEG datastream, producing a complete JPEG image.
+    ///
+    /// The tables are inserted after the application segments (such as JFIF) at the start of the
+    /// datastream. The result can be decompressed as any other JPEG image.
+    pub fn expand(&self, abbreviated: &[u8]) -> Result<Vec<u8>> {
+        let mut insert_at = None;
+        for segment in markers::segments(abbreviated) {
+            let segment = segment?;
+            if segment.marker != markers::SOI && !segment.is_app() {
+                insert_at = Some(segment.offset);
+                break;
+            }
+        }
+        let insert_at = insert_at.ok_or(Error::InvalidJpeg("unexpected end of data"))?;
+
+        let tables = self.segments().concat();
+        let mut output = Vec::with_capacity(abbreviated.len() + tables.len());
+        output.extend_from_slice(&abbreviated[..insert_at]);
+        output.extend_from_slice(&tables);
+        output.extend_from_slice(&abbreviated[insert_at..]);
+        Ok(output)
+    }
+
+    /// Returns the bytes of all table segments.
+    fn segments(&self) -> Vec<&[u8]> {
+        markers::segments(&self.data)
+            .filter_map(|segment| segment.ok())
+            .filter(is_table)
+            .map(|segment| &self.data[segment.offset..][..segment.len])
+            .collect()
+    }
+}
+
+fn is_table(segment: &Segment) -> bool {
+    matches!(segment.marker, markers::DQT | markers::DHT)
+}
@@ -1,10 +1,19 @@
 use crate::ffi;
 
-use crate::buf::{OutputBuf, OwnedBuf};
-use crate::common::{Error, Result};
+use crate::buf::{scratch_vec, OutputBuf, OwnedBuf, ScratchVec};
+use crate::common::{Error, Result, Subsamp};
+use crate::compress::{TileGrid, TileLayout};
+use crate::decompress::{read_header_with, DecompressHeader};
+use crate::exif;
 use crate::handle::Handle;
+use crate::markers;
+use crate::quality::ZIGZAG_TO_NATURAL;
+use crate::scale::DctDownscaler;
+use crate::xmp;
 use std::convert::TryInto as _;
+use std::fmt;
 use std::ptr;
+use std::str::FromStr;
 
 /// Transforms JPEG images without recompression.
 ///
@@ -16,6 +25,8 @@ pub struct Transformer {
     handle: Handle,
 }
 
+unsafe impl Send for Transformer {}
+
 /// Lossless transform of a JPEG image.
 ///
 /// When constructing an instance, you may start from the default transform
@@ -56,6 +67,32 @@ pub struct Transformer {
 /// let mut transform = Transform::default();
 /// transform.crop = Some(TransformCrop { x: 16, y: 32, width: Some(200), height: Some(100) });
 /// ```
+///
+/// Blank the region of size (64, 32) at pixel (128, 96) without recompressing the rest of the
+/// image:
+///
+/// ```
+/// # use turbojpeg::{Transform, TransformOp, TransformCrop};
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let mut transform = Transform::default();
+/// transform.redact.push(TransformCrop { x: 128, y: 96, width: Some(64), height: Some(32) });
+/// let redacted = turbojpeg::transform(&transform, &jpeg_data)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// Fail if the rotated image does not fit into 16 kB:
+///
+/// ```
+/// # use turbojpeg::{Error, Transform, TransformOp};
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let mut transform = Transform::op(TransformOp::Rot90);
+/// transform.max_output_len = Some(16 * 1024);
+/// match turbojpeg::transform(&transform, &jpeg_data) {
+///     Err(Error::OutputTooLarge(len)) => assert!(len > 16 * 1024),
+///     res => panic!("unexpected result {:?}", res),
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
 #[derive(Debug, Default, Clone)]
 #[doc(alias = "tjtransform")]
 #[non_exhaustive]
@@ -63,10 +100,69 @@ pub struct Transform {
     /// Transform operation that is applied.
     pub op: TransformOp,
 
+    /// Downscale the image before applying the transform, like the `-scale` option of `jpegtran`.
+    ///
+    /// The image is scaled in the DCT domain by any factor `M/N` (see [`TransformScale`]): every
+    /// block of the output image is computed from the coefficients of the blocks of the input
+    /// image that it covers, as if the decoded samples were averaged with a box filter, and it is
+    /// quantized with the same tables. This is much faster than decompressing, resizing and
+    /// compressing the image, and the quality is comparable, but unlike the other transforms it
+    /// is not lossless. The size of the scaled image is rounded up, and the [`crop`][Self::crop]
+    /// and [`redact`][Self::redact] regions are given in the scaled image.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Transform, TransformScale};
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transform = Transform::default();
+    /// transform.scale = TransformScale::HALF;
+    ///
+    /// let half = turbojpeg::transform(&transform, &jpeg_data)?;
+    /// let header = turbojpeg::read_header(&half)?;
+    /// assert_eq!((header.width, header.height), (192, 128));
+    /// assert!(half.len() < jpeg_data.len());
+    ///
+    /// transform.scale = TransformScale::new(3, 8)?;
+    /// let scaled = turbojpeg::transform(&transform, &jpeg_data)?;
+    /// let header = turbojpeg::read_header(&scaled)?;
+    /// assert_eq!((header.width, header.height), (144, 96));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub scale: TransformScale,
+
     /// Crop the input image before applying the transform.
     #[doc(alias = "TJXOPT_CROP")]
     pub crop: Option<TransformCrop>,
 
+    /// Align the [`crop`][Self::crop] to the MCU grid instead of failing.
+    ///
+    /// If None is given (the default), a crop that is not aligned on MCU boundaries is rejected by
+    /// TurboJPEG. Otherwise, its position is moved to the MCU grid of the output image using the
+    /// given [`RoundMode`] (see [`TransformCrop::align_to_mcu()`]):
+    /// [`RoundMode::Down`] expands the crop, [`RoundMode::Up`] shrinks it. The effective crop is
+    /// reported by [`Transformer::transform_with_stats()`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{OutputBuf, RoundMode, Transform, TransformCrop};
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transform = Transform::default();
+    /// transform.crop = Some(TransformCrop { x: 13, y: 13, width: Some(100), height: Some(100) });
+    /// transform.crop_rounding = Some(RoundMode::Down);
+    ///
+    /// let mut output = OutputBuf::new_owned();
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let stats = transformer.transform_with_stats(&transform, &jpeg_data, &mut output)?;
+    /// assert_eq!(
+    ///     stats.crop,
+    ///     Some(TransformCrop { x: 8, y: 8, width: Some(105), height: Some(105) }),
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub crop_rounding: Option<RoundMode>,
+
     /// Return an error if the transform is not perfect.
     ///
     /// Lossless transforms operate on MCU blocks, whose size depends on the level of chrominance
@@ -106,10 +202,113 @@ pub struct Transform {
     #[doc(alias = "TJXOPT_OPTIMIZE")]
     pub optimize: bool,
 
+    /// Enable arithmetic entropy coding in the JPEG image generated by this particular transform.
+    ///
+    /// Arithmetic entropy coding will generally improve compression relative to Huffman entropy
+    /// coding (the default), but it will reduce compression and decompression performance
+    /// considerably. Also, some older JPEG decoders do not support arithmetic coding at all. This
+    /// can be combined with [`progressive`][Self::progressive], but [`optimize`][Self::optimize]
+    /// has no effect with arithmetic coding.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transform = turbojpeg::Transform::default();
+    /// transform.arithmetic = true;
+    ///
+    /// let arithmetic = turbojpeg::transform(&transform, &jpeg_data)?;
+    /// assert!(arithmetic.len() < jpeg_data.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "TJXOPT_ARITHMETIC")]
+    pub arithmetic: bool,
+
     /// Do not copy any extra markers (including EXIF and ICC profile data) from the input image to
     /// the output image.
     #[doc(alias = "TJXOPT_COPYNONE")]
     pub copy_none: bool,
+
+    /// Regions of the output image that are blanked.
+    ///
+    /// The DCT coefficients of the blocks in these regions are set to zero after the transform
+    /// is applied, which produces uniform gray boxes without recompressing the rest of the image.
+    /// This can be used to losslessly redact faces or license plates. The regions are given in
+    /// the coordinates of the transformed (and cropped) image, and their position and size must
+    /// be aligned to the MCU size of the output image, otherwise the transform returns
+    /// [`Error::InvalidParam`]. A region without a width or height extends to the right or
+    /// bottom edge of the image.
+    pub redact: Vec<TransformCrop>,
+
+    /// Classes of extra markers that are copied from the input image to the output image.
+    ///
+    /// If None is given, all extra markers are copied (unless [`copy_none`][Self::copy_none] is
+    /// enabled). Otherwise, the markers produced by TurboJPEG are filtered according to the
+    /// [`KeepMarkers`] after the transform, which can also remove the GPS position and the
+    /// thumbnail from the EXIF data.
+    pub keep_markers: Option<KeepMarkers>,
+
+    /// Update the EXIF data of the output image to match the transform.
+    ///
+    /// The orientation tag is rewritten so that viewers which respect it display the result of
+    /// applying [`op`][Self::op] to the image as it was displayed before, and the embedded
+    /// thumbnail image is losslessly transformed (and cropped) in the same way as the main image.
+    /// Because the thumbnail is small, the crop can only be approximated on the MCU grid of the
+    /// thumbnail. If the transform blanks any regions (see [`redact`][Self::redact]) or if the
+    /// thumbnail cannot be transformed, the thumbnail is removed instead, so that it does not
+    /// reveal the blanked content or show a mismatched preview. The image width and height tags
+    /// of the Exif IFD are set to the size of the output image. Images without EXIF data are not
+    /// modified.
+    pub update_exif: bool,
+
+    /// Replace the XMP packet of the output image.
+    ///
+    /// All XMP segments copied from the input image are removed and a segment with this packet
+    /// is stored instead (see [`replace_xmp()`][crate::replace_xmp]). This is done even if
+    /// [`copy_none`][Self::copy_none] is enabled. The packet must not be longer than 65504 bytes,
+    /// otherwise the transform returns [`Error::InvalidParam`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+    /// let mut transform = turbojpeg::Transform::op(turbojpeg::TransformOp::Rot90);
+    /// transform.xmp = Some(xmp.to_vec());
+    ///
+    /// let rotated = turbojpeg::transform(&transform, &jpeg_data)?;
+    /// assert_eq!(turbojpeg::read_xmp(&rotated)?.as_deref(), Some(&xmp[..]));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub xmp: Option<Vec<u8>>,
+
+    /// Maximal size of the output image in bytes.
+    ///
+    /// If the transformed image would be larger, the transform fails with
+    /// [`Error::OutputTooLarge`], which reports the size of the image. TurboJPEG cannot stop at
+    /// the limit, so the limit is checked after the image was transformed. With a limit below
+    /// the maximal size of the output, an image transformed into a borrowed buffer is first
+    /// written into a temporary buffer, and the borrowed buffer is not modified if the image is
+    /// too large.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Error, Transform, TransformOp};
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let mut transform = Transform::op(TransformOp::Rot90);
+    /// transform.max_output_len = Some(1024);
+    ///
+    /// let mut output = vec![0xaa; 2 * jpeg_data.len()];
+    /// match transformer.transform_to_slice(&transform, &jpeg_data, &mut output) {
+    ///     Err(Error::OutputTooLarge(len)) => assert!(len > 1024),
+    ///     res => panic!("unexpected result {:?}", res),
+    /// }
+    /// assert!(output.iter().all(|&byte| byte == 0xaa));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub max_output_len: Option<usize>,
 }
 
 impl Transform {
@@ -128,6 +327,340 @@ impl Transform {
             ..Transform::default()
         }
     }
+
+    /// Combines this transform with the `next` one, which must not be preceded by cropping.
+    ///
+    /// The result is equivalent to applying both transforms if both of them are perfect.
+    fn then(&self, next: &Transform) -> Transform {
+        debug_assert!(self.crop.is_none() && self.redact.is_empty());
+        debug_assert!(next.scale == TransformScale::ONE);
+        Transform {
+            op: self.op.then(next.op),
+            scale: self.scale,
+            crop: next.crop,
+            crop_rounding: next.crop_rounding,
+            perfect: self.perfect || next.perfect,
+            trim: self.trim || next.trim,
+            gray: self.gray || next.gray,
+            progressive: next.progressive,
+            optimize: next.optimize,
+            arithmetic: next.arithmetic,
+            copy_none: self.copy_none || next.copy_none,
+            redact: next.redact.clone(),
+            keep_markers: match (self.keep_markers, next.keep_markers) {
+                (Some(keep), Some(next_keep)) => Some(keep.intersection(next_keep)),
+                (keep, next_keep) => keep.or(next_keep),
+            },
+            update_exif: self.update_exif || next.update_exif,
+            xmp: next.xmp.clone().or_else(|| self.xmp.clone()),
+            max_output_len: next.max_output_len,
+        }
+    }
+
+    /// Converts the regions in [`redact`][Self::redact] to MCU blocks, given the subsampling of
+    /// the input image.
+    fn redaction(&self, subsamp: Subsamp) -> Result<Redaction> {
+        let subsamp = self.output_subsamp(subsamp);
+        let (mcu_width, mcu_height) = subsamp.mcu_size();
+        let to_mcus = |pos: usize, size: Option<usize>, mcu_size: usize| {
+            let end = match size {
+                Some(size) if size % mcu_size != 0 => return None,
+                Some(size) => (pos + size) / mcu_size,
+                None => usize::MAX,
+            };
+            (pos % mcu_size == 0).then_some((pos / mcu_size, end))
+        };
+
+        let regions = self
+            .redact
+            .iter()
+            .map(|region| {
+                let (left, right) = to_mcus(region.x, region.width, mcu_width)
+                    .ok_or(Error::InvalidParam("redact"))?;
+                let (top, bottom) = to_mcus(region.y, region.height, mcu_height)
+                    .ok_or(Error::InvalidParam("redact"))?;
+                Ok([left, top, right, bottom])
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Redaction {
+            factors: subsamp.size(),
+            regions,
+        })
+    }
+
+    /// Returns the crop that is applied to an input image with the given subsampling, aligned
+    /// according to [`crop_rounding`][Self::crop_rounding].
+    fn effective_crop(&self, subsamp: Subsamp) -> Result<Option<TransformCrop>> {
+        match (self.crop, self.crop_rounding) {
+            (Some(crop), Some(mode)) => {
+                let (crop, _) = crop.align_to_mcu(self.output_subsamp(subsamp), mode)?;
+                Ok(Some(crop))
+            }
+            (crop, _) => Ok(crop),
+        }
+    }
+
+    /// Returns a copy of this transform with a different crop.
+    fn with_crop(&self, crop: Option<TransformCrop>) -> Transform {
+        Transform {
+            crop,
+            crop_rounding: None,
+            redact: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the number of columns and rows of the input image that are in partial MCU blocks
+    /// which this transform cannot move, given the header of the input image.
+    ///
+    /// This mirrors `jtransform_perfect_transform()` from libjpeg-turbo.
+    fn imperfect_edges(&self, header: &DecompressHeader) -> (usize, usize) {
+        // only the luminance component is transformed for grayscale output
+        let (mcu_width, mcu_height) = if self.gray {
+            Subsamp::Gray.mcu_size()
+        } else {
+            header.subsamp.mcu_size()
+        };
+        let (width, height) = (
+            self.scale.apply(header.width),
+            self.scale.apply(header.height),
+        );
+        let (columns, rows) = (width % mcu_width, height % mcu_height);
+        match self.op {
+            TransformOp::Hflip | TransformOp::Rot270 => (columns, 0),
+            TransformOp::Vflip | TransformOp::Rot90 => (0, rows),
+            TransformOp::Rot180 | TransformOp::Transverse => (columns, rows),
+            TransformOp::None | TransformOp::Transpose => (0, 0),
+        }
+    }
+
+    /// Returns the subsampling of the output image, given the subsampling of the input image.
+    fn output_subsamp(&self, subsamp: Subsamp) -> Subsamp {
+        if self.gray {
+            Subsamp::Gray
+        } else if self.op.to_flips().0 {
+            // transposition swaps the subsampling factors
+            match subsamp {
+                Subsamp::Sub2x1 => Subsamp::Sub1x2,
+                Subsamp::Sub1x2 => Subsamp::Sub2x1,
+                Subsamp::Sub4x1 => Subsamp::Sub1x4,
+                Subsamp::Sub1x4 => Subsamp::Sub4x1,
+                other => other,
+            }
+        } else {
+            subsamp
+        }
+    }
+
+    fn to_ffi(&self) -> Result<ffi::tjtransform> {
+        let mut options = 0;
+        if self.perfect {
+            options |= ffi::TJXOPT_PERFECT
+        }
+        if self.trim {
+            options |= ffi::TJXOPT_TRIM
+        }
+        if self.gray {
+            options |= ffi::TJXOPT_GRAY
+        }
+        if self.progressive {
+            options |= ffi::TJXOPT_PROGRESSIVE
+        }
+        if self.optimize {
+            options |= ffi::TJXOPT_OPTIMIZE
+        }
+        if self.arithmetic {
+            options |= ffi::TJXOPT_ARITHMETIC
+        }
+        if self.copy_none {
+            options |= ffi::TJXOPT_COPYNONE
+        }
+
+        let mut region = ffi::tjregion {
+            x: 0,
+            y: 0,
+            w: 0,
+            h: 0,
+        };
+        if let Some(crop) = self.crop {
+            region.x = crop
+                .x
+                .try_into()
+                .map_err(|_| Error::IntegerOverflow("crop.x"))?;
+            region.y = crop
+                .y
+                .try_into()
+                .map_err(|_| Error::IntegerOverflow("crop.y"))?;
+            if let Some(crop_w) = crop.width {
+                region.w = crop_w
+                    .try_into()
+                    .map_err(|_| Error::IntegerOverflow("crop.width"))?;
+            }
+            if let Some(crop_h) = crop.height {
+                region.h = crop_h
+                    .try_into()
+                    .map_err(|_| Error::IntegerOverflow("crop.height"))?;
+            }
+            options |= ffi::TJXOPT_CROP;
+        }
+
+        Ok(ffi::tjtransform {
+            r: region,
+            op: self.op as libc::c_int,
+            options: options as libc::c_int,
+            data: ptr::null_mut(),
+            customFilter: None,
+        })
+    }
+}
+
+impl fmt::Display for Transform {
+    /// Formats the transform in the compact form that is parsed by [`Transform::from_str()`].
+    /// Options that have their default value are omitted.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.op)?;
+        if self.scale != TransformScale::ONE {
+            write!(f, ",scale={}", self.scale)?;
+        }
+        if let Some(crop) = self.crop {
+            write!(f, ",crop={}", crop)?;
+        }
+        if let Some(mode) = self.crop_rounding {
+            let mode = match mode {
+                RoundMode::Down => "down",
+                RoundMode::Up => "up",
+                RoundMode::Nearest => "nearest",
+            };
+            write!(f, ",crop-round={}", mode)?;
+        }
+        let flags = [
+            (self.perfect, "perfect"),
+            (self.trim, "trim"),
+            (self.gray, "gray"),
+            (self.progressive, "progressive"),
+            (self.optimize, "optimize"),
+            (self.arithmetic, "arithmetic"),
+            (self.copy_none, "copy-none"),
+        ];
+        for (_, name) in flags.iter().filter(|(set, _)| *set) {
+            write!(f, ",{}", name)?;
+        }
+        for region in &self.redact {
+            write!(f, ",redact={}", region)?;
+        }
+        if let Some(keep) = self.keep_markers {
+            f.write_str(",keep=")?;
+            let mut names = keep.names();
+            match names.next() {
+                Some(name) => f.write_str(name)?,
+                None => f.write_str("none")?,
+            }
+            for name in names {
+                write!(f, "+{}", name)?;
+            }
+        }
+        if self.update_exif {
+            f.write_str(",update-exif")?;
+        }
+        if let Some(len) = self.max_output_len {
+            write!(f, ",max-len={}", len)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Transform {
+    type Err = Error;
+
+    /// Parses a transform from a compact, comma-separated list of options.
+    ///
+    /// The options correspond to the fields of [`Transform`]:
+    ///
+    /// | Option                          | Field                                         |
+    /// |---------------------------------|-----------------------------------------------|
+    /// | operation name, such as `rot90` | [`op`][Self::op] (see [`TransformOp`])        |
+    /// | `scale=M/N`, such as `1/2`      | [`scale`][Self::scale]                        |
+    /// | `crop=WxH+X+Y`                  | [`crop`][Self::crop] (see [`TransformCrop`])  |
+    /// | `crop-round=down\|up\|nearest`  | [`crop_rounding`][Self::crop_rounding]        |
+    /// | `perfect`                       | [`perfect`][Self::perfect]                    |
+    /// | `trim`                          | [`trim`][Self::trim]                          |
+    /// | `gray`                          | [`gray`][Self::gray]                          |
+    /// | `progressive`                   | [`progressive`][Self::progressive]            |
+    /// | `optimize`                      | [`optimize`][Self::optimize]                  |
+    /// | `arithmetic`                    | [`arithmetic`][Self::arithmetic]              |
+    /// | `copy-none`                     | [`copy_none`][Self::copy_none]                |
+    /// | `redact=WxH+X+Y` (repeatable)   | [`redact`][Self::redact]                      |
+    /// | `keep=exif+icc+...` or `keep=none` | [`keep_markers`][Self::keep_markers] (see [`KeepMarkers`]) |
+    /// | `update-exif`                   | [`update_exif`][Self::update_exif]            |
+    /// | `max-len=N`                     | [`max_output_len`][Self::max_output_len]      |
+    ///
+    /// The names of the marker classes in `keep` are the names of the fields of [`KeepMarkers`],
+    /// with `-` instead of `_`, or `all`. Options that are not given keep their default value, so
+    /// the empty string parses to [`Transform::default()`]. Returns [`Error::InvalidParam`] if an
+    /// option is unknown or malformed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Transform, TransformCrop, TransformOp};
+    /// let transform: Transform = "rot90,crop=200x100+16+32,trim,optimize".parse()?;
+    /// assert_eq!(transform.op, TransformOp::Rot90);
+    /// assert_eq!(
+    ///     transform.crop,
+    ///     Some(TransformCrop { x: 16, y: 32, width: Some(200), height: Some(100) }),
+    /// );
+    /// assert!(transform.trim && transform.optimize);
+    /// assert_eq!(transform.to_string(), "rot90,crop=200x100+16+32,trim,optimize");
+    ///
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let transform: Transform = "hflip,keep=icc+exif".parse()?;
+    /// let output = turbojpeg::transform(&transform, &jpeg_data)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<Transform> {
+        let mut transform = Transform::default();
+        for option in s
+            .split(',')
+            .map(str::trim)
+            .filter(|option| !option.is_empty())
+        {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim())),
+                None => (option, None),
+            };
+            match (name, value) {
+                ("scale", Some(value)) => transform.scale = value.parse()?,
+                ("crop", Some(value)) => transform.crop = Some(value.parse()?),
+                ("crop-round", Some(value)) => {
+                    transform.crop_rounding = Some(match value {
+                        "down" => RoundMode::Down,
+                        "up" => RoundMode::Up,
+                        "nearest" => RoundMode::Nearest,
+                        _ => return Err(Error::InvalidParam("crop-round")),
+                    })
+                }
+                ("perfect", None) => transform.perfect = true,
+                ("trim", None) => transform.trim = true,
+                ("gray", None) => transform.gray = true,
+                ("progressive", None) => transform.progressive = true,
+                ("optimize", None) => transform.optimize = true,
+                ("arithmetic", None) => transform.arithmetic = true,
+                ("copy-none", None) => transform.copy_none = true,
+                ("redact", Some(value)) => transform.redact.push(value.parse()?),
+                ("keep", Some(value)) => transform.keep_markers = Some(KeepMarkers::parse(value)?),
+                ("update-exif", None) => transform.update_exif = true,
+                ("max-len", Some(value)) => {
+                    let len = value.parse().map_err(|_| Error::InvalidParam("max-len"))?;
+                    transform.max_output_len = Some(len);
+                }
+                (name, None) => {
+                    transform.op = name.parse().map_err(|_| Error::InvalidParam("transform"))?
+                }
+                _ => return Err(Error::InvalidParam("transform")),
+            }
+        }
+        Ok(transform)
+    }
 }
 
 /// Transform operation.
@@ -194,156 +727,1867 @@ impl Default for TransformOp {
     }
 }
 
-/// Transform cropping region.
-///
-/// The [`x`][Self::x] and [`y`][Self::y] position of the region must be aligned on MCU boundaries.
-/// The size of the MCU depends on the chrominance subsampling option, which can be obtained using
-/// [`Decompressor::read_header()`][crate::Decompressor::read_header].
-///
-/// The default instance performs no cropping.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-#[doc(alias = "tjregion")]
-pub struct TransformCrop {
-    /// Left boundary of the region. This must be divisible by the MCU width (see
-    /// [`Subsamp::mcu_width()`][crate::Subsamp::mcu_width]).
-    pub x: usize,
-    /// Upper boundary of the region. This must be divisible by the MCU height (see
-    /// [`Subsamp::mcu_height()`][crate::Subsamp::mcu_height]).
-    pub y: usize,
-    /// Width of the region. If None is given, the region ends at the right boundary of the image.
-    pub width: Option<usize>,
-    /// Height of the region. If None is given, the region ends at the bottom boundary of the
-    /// image.
-    pub height: Option<usize>,
-}
-
-impl Transformer {
-    /// Create a new transformer instance.
-    #[doc(alias = "tj3Init")]
-    pub fn new() -> Result<Transformer> {
-        let handle = Handle::new(ffi::TJINIT_TJINIT_TRANSFORM)?;
-        Ok(Self { handle })
-    }
-
-    /// Apply a transformation to the compressed JPEG.
+impl TransformOp {
+    /// Get the operation that makes an image with the given EXIF orientation upright.
     ///
-    /// This is the main transformation method, which gives you full control of the output buffer. If
-    /// you don't need this level of control, you can use one of the convenience wrappers below.
-    ///
-    /// # Example
+    /// The EXIF orientation tag describes how the stored image must be transformed for display:
     ///
-    /// ```
-    /// // read JPEG data from file
-    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// | Orientation | Stored image                            | Operation                        |
+    /// |-------------|-----------------------------------------|----------------------------------|
+    /// | 1           | upright                                 | [`None`][Self::None]             |
+    /// | 2           | mirrored horizontally                   | [`Hflip`][Self::Hflip]           |
+    /// | 3           | rotated by 180 degrees                  | [`Rot180`][Self::Rot180]         |
+    /// | 4           | mirrored vertically                     | [`Vflip`][Self::Vflip]           |
+    /// | 5           | transposed                              | [`Transpose`][Self::Transpose]   |
+    /// | 6           | rotated by 90 degrees counter-clockwise | [`Rot90`][Self::Rot90]           |
+    /// | 7           | transversed                             | [`Transverse`][Self::Transverse] |
+    /// | 8           | rotated by 90 degrees clockwise         | [`Rot270`][Self::Rot270]         |
     ///
-    /// // initialize the transformer
-    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// Returns `None` for values outside of the range 1 to 8.
     ///
-    /// // define the transformation: flip vertically, trim partial MCU blocks on the bottom edge
-    /// let mut transform = turbojpeg::Transform::op(turbojpeg::TransformOp::Vflip);
-    /// transform.trim = true;
+    /// # Example
     ///
-    /// // initialize the output buffer
-    /// let mut flipped_data = turbojpeg::OutputBuf::new_owned();
+    /// ```
+    /// use turbojpeg::TransformOp;
+    /// assert_eq!(TransformOp::from_exif_orientation(6), Some(TransformOp::Rot90));
+    /// assert_eq!(TransformOp::from_exif_orientation(5), Some(TransformOp::Transpose));
+    /// assert_eq!(TransformOp::from_exif_orientation(0), None);
+    /// ```
+    pub fn from_exif_orientation(orientation: u16) -> Option<TransformOp> {
+        match orientation {
+            1 => Some(TransformOp::None),
+            2 => Some(TransformOp::Hflip),
+            3 => Some(TransformOp::Rot180),
+            4 => Some(TransformOp::Vflip),
+            5 => Some(TransformOp::Transpose),
+            6 => Some(TransformOp::Rot90),
+            7 => Some(TransformOp::Transverse),
+            8 => Some(TransformOp::Rot270),
+            _ => None,
+        }
+    }
+
+    /// Get the EXIF orientation of an image that is made upright by this operation.
     ///
-    /// // apply the transformation
-    /// transformer.transform(&transform, &jpeg_data, &mut flipped_data)?;
+    /// This is the inverse of [`from_exif_orientation()`][Self::from_exif_orientation].
     ///
-    /// // write the flipped JPEG back to disk
-    /// std::fs::write(std::env::temp_dir().join("flipped_parrots.jpg"), &flipped_data)?;
+    /// # Example
     ///
-    /// # Ok::<(), Box<dyn std::error::Error>>(())
     /// ```
-    #[doc(alias = "tj3Transform")]
-    pub fn transform(
-        &mut self,
-        transform: &Transform,
-        jpeg_data: &[u8],
-        output: &mut OutputBuf,
-    ) -> Result<()> {
-        let mut options = 0;
-        if transform.perfect {
-            options |= ffi::TJXOPT_PERFECT
-        }
-        if transform.trim {
-            options |= ffi::TJXOPT_TRIM
+    /// use turbojpeg::TransformOp;
+    /// assert_eq!(TransformOp::Rot90.to_exif_orientation(), 6);
+    /// assert_eq!(TransformOp::None.to_exif_orientation(), 1);
+    /// ```
+    pub fn to_exif_orientation(self) -> u16 {
+        match self {
+            TransformOp::None => 1,
+            TransformOp::Hflip => 2,
+            TransformOp::Rot180 => 3,
+            TransformOp::Vflip => 4,
+            TransformOp::Transpose => 5,
+            TransformOp::Rot90 => 6,
+            TransformOp::Transverse => 7,
+            TransformOp::Rot270 => 8,
         }
-        if transform.gray {
-            options |= ffi::TJXOPT_GRAY
+    }
+
+    /// Returns the operation that undoes `self`.
+    fn inverse(self) -> TransformOp {
+        match self {
+            TransformOp::Rot90 => TransformOp::Rot270,
+            TransformOp::Rot270 => TransformOp::Rot90,
+            other => other,
         }
-        if transform.progressive {
-            options |= ffi::TJXOPT_PROGRESSIVE
+    }
+
+    /// Decomposes the operation into an optional transposition followed by optional horizontal
+    /// and vertical flips.
+    pub(crate) fn to_flips(self) -> (bool, bool, bool) {
+        match self {
+            TransformOp::None => (false, false, false),
+            TransformOp::Hflip => (false, true, false),
+            TransformOp::Vflip => (false, false, true),
+            TransformOp::Rot180 => (false, true, true),
+            TransformOp::Transpose => (true, false, false),
+            TransformOp::Rot90 => (true, true, false),
+            TransformOp::Rot270 => (true, false, true),
+            TransformOp::Transverse => (true, true, true),
         }
-        if transform.optimize {
-            options |= ffi::TJXOPT_OPTIMIZE
+    }
+
+    fn from_flips(flips: (bool, bool, bool)) -> TransformOp {
+        match flips {
+            (false, false, false) => TransformOp::None,
+            (false, true, false) => TransformOp::Hflip,
+            (false, false, true) => TransformOp::Vflip,
+            (false, true, true) => TransformOp::Rot180,
+            (true, false, false) => TransformOp::Transpose,
+            (true, true, false) => TransformOp::Rot90,
+            (true, false, true) => TransformOp::Rot270,
+            (true, true, true) => TransformOp::Transverse,
+        }
+    }
+
+    /// Returns the operation that is equivalent to applying `self` and then `next`.
+    fn then(self, next: TransformOp) -> TransformOp {
+        let (t1, h1, v1) = self.to_flips();
+        let (t2, h2, v2) = next.to_flips();
+        if t2 {
+            // a flip followed by a transposition is a transposition followed by the other flip
+            TransformOp::from_flips((!t1, v1 ^ h2, h1 ^ v2))
+        } else {
+            TransformOp::from_flips((t1, h1 ^ h2, v1 ^ v2))
+        }
+    }
+}
+
+impl fmt::Display for TransformOp {
+    /// Formats the operation as its lowercase name, such as `rot90` or `hflip`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransformOp::None => "none",
+            TransformOp::Hflip => "hflip",
+            TransformOp::Vflip => "vflip",
+            TransformOp::Transpose => "transpose",
+            TransformOp::Transverse => "transverse",
+            TransformOp::Rot90 => "rot90",
+            TransformOp::Rot180 => "rot180",
+            TransformOp::Rot270 => "rot270",
+        })
+    }
+}
+
+impl FromStr for TransformOp {
+    type Err = Error;
+
+    /// Parses the name of an operation, as formatted by [`Display`][fmt::Display].
+    ///
+    /// The name is case insensitive. Returns [`Error::InvalidParam`] for unknown names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::TransformOp;
+    /// assert_eq!("rot90".parse::<TransformOp>()?, TransformOp::Rot90);
+    /// assert_eq!("HFlip".parse::<TransformOp>()?, TransformOp::Hflip);
+    /// assert_eq!(TransformOp::Transverse.to_string(), "transverse");
+    /// assert!("rot45".parse::<TransformOp>().is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<TransformOp> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(TransformOp::None),
+            "hflip" => Ok(TransformOp::Hflip),
+            "vflip" => Ok(TransformOp::Vflip),
+            "transpose" => Ok(TransformOp::Transpose),
+            "transverse" => Ok(TransformOp::Transverse),
+            "rot90" => Ok(TransformOp::Rot90),
+            "rot180" => Ok(TransformOp::Rot180),
+            "rot270" => Ok(TransformOp::Rot270),
+            _ => Err(Error::InvalidParam("op")),
+        }
+    }
+}
+
+/// Classes of markers that are kept by a transform, see [`Transform::keep_markers`].
+///
+/// The default instance keeps no extra markers. Markers that describe the encoding of the image
+/// (such as the JFIF and Adobe segments) are always kept.
+///
+/// # Example
+///
+/// Keep the ICC profile and the EXIF data, but remove the GPS position and the thumbnail:
+///
+/// ```
+/// # use turbojpeg::{KeepMarkers, Transform};
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let mut keep_markers = KeepMarkers::default();
+/// keep_markers.icc = true;
+/// keep_markers.exif = true;
+///
+/// let mut transform = Transform::default();
+/// transform.keep_markers = Some(keep_markers);
+/// let output = turbojpeg::transform(&transform, &jpeg_data)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct KeepMarkers {
+    /// Keep the EXIF segment (APP1).
+    pub exif: bool,
+    /// Keep the GPS data in the EXIF segment. If disabled, the GPS position is removed from the
+    /// EXIF data.
+    pub exif_gps: bool,
+    /// Keep the thumbnail image in the EXIF segment. If disabled, the thumbnail is removed from
+    /// the EXIF data.
+    pub exif_thumbnail: bool,
+    /// Keep the ICC color profile (APP2).
+    pub icc: bool,
+    /// Keep the XMP metadata (APP1), including extended XMP.
+    pub xmp: bool,
+    /// Keep the IPTC metadata, which is stored in Photoshop segments (APP13).
+    pub iptc: bool,
+    /// Keep the comments (COM).
+    pub comments: bool,
+    /// Keep all other application segments (APP0 to APP15).
+    pub other: bool,
+}
+
+impl KeepMarkers {
+    /// Keeps all markers.
+    pub fn all() -> KeepMarkers {
+        KeepMarkers {
+            exif: true,
+            exif_gps: true,
+            exif_thumbnail: true,
+            icc: true,
+            xmp: true,
+            iptc: true,
+            comments: true,
+            other: true,
+        }
+    }
+
+    fn intersection(self, other: KeepMarkers) -> KeepMarkers {
+        KeepMarkers {
+            exif: self.exif && other.exif,
+            exif_gps: self.exif_gps && other.exif_gps,
+            exif_thumbnail: self.exif_thumbnail && other.exif_thumbnail,
+            icc: self.icc && other.icc,
+            xmp: self.xmp && other.xmp,
+            iptc: self.iptc && other.iptc,
+            comments: self.comments && other.comments,
+            other: self.other && other.other,
+        }
+    }
+
+    /// Removes the markers that should not be kept from a JPEG datastream.
+    fn apply(&self, jpeg: &[u8]) -> Result<Vec<u8>> {
+        let mut output = Vec::with_capacity(jpeg.len());
+        for segment in markers::segments(jpeg) {
+            let segment = segment?;
+            if segment.marker == markers::SOS {
+                output.extend_from_slice(&jpeg[segment.offset..]);
+                break;
+            }
+
+            let starts_with = |header| segment.payload.starts_with(header);
+            let keep = match segment.marker {
+                markers::COM => self.comments,
+                _ if exif::is_exif(&segment) => {
+                    if self.exif && !(self.exif_gps && self.exif_thumbnail) {
+                        let payload = exif::strip_exif(
+                            segment.payload,
+                            !self.exif_gps,
+                            !self.exif_thumbnail,
+                        )?;
+                        markers::write_segment(&mut output, segment.marker, &payload);
+                        continue;
+                    }
+                    self.exif
+                }
+                marker if marker == markers::APP0 + 1 => {
+                    if starts_with(markers::XMP_HEADER)
+                        || starts_with(markers::XMP_EXTENSION_HEADER)
+                    {
+                        self.xmp
+                    } else {
+                        self.other
+                    }
+                }
+                marker if marker == markers::APP0 + 2 && starts_with(markers::ICC_HEADER) => {
+                    self.icc
+                }
+                marker if marker == markers::APP0 + 13 && starts_with(markers::IPTC_HEADER) => {
+                    self.iptc
+                }
+                _ if segment.is_extra() => self.other,
+                _ => true,
+            };
+            if keep {
+                output.extend_from_slice(&jpeg[segment.offset..segment.offset + segment.len]);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the names of the marker classes that are kept, as used in the string form of
+    /// [`Transform`].
+    fn names(&self) -> impl Iterator<Item = &'static str> {
+        let classes = [
+            (self.exif, "exif"),
+            (self.exif_gps, "exif-gps"),
+            (self.exif_thumbnail, "exif-thumbnail"),
+            (self.icc, "icc"),
+            (self.xmp, "xmp"),
+            (self.iptc, "iptc"),
+            (self.comments, "comments"),
+            (self.other, "other"),
+        ];
+        classes
+            .into_iter()
+            .filter(|(keep, _)| *keep)
+            .map(|(_, name)| name)
+    }
+
+    /// Parses a `+`-separated list of marker classes, as used in the string form of [`Transform`].
+    fn parse(s: &str) -> Result<KeepMarkers> {
+        let mut keep = KeepMarkers::default();
+        for name in s.split('+').map(str::trim) {
+            match name {
+                "none" => {}
+                "all" => keep = KeepMarkers::all(),
+                "exif" => keep.exif = true,
+                "exif-gps" => keep.exif_gps = true,
+                "exif-thumbnail" => keep.exif_thumbnail = true,
+                "icc" => keep.icc = true,
+                "xmp" => keep.xmp = true,
+                "iptc" => keep.iptc = true,
+                "comments" => keep.comments = true,
+                "other" => keep.other = true,
+                _ => return Err(Error::InvalidParam("keep")),
+            }
+        }
+        Ok(keep)
+    }
+}
+
+/// Transform cropping region.
+///
+/// The [`x`][Self::x] and [`y`][Self::y] position of the region must be aligned on MCU boundaries.
+/// The size of the MCU depends on the chrominance subsampling option, which can be obtained using
+/// [`Decompressor::read_header()`][crate::Decompressor::read_header]. Use
+/// [`Transform::crop_rounding`] to let the transform align the region.
+///
+/// The default instance performs no cropping.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[doc(alias = "tjregion")]
+pub struct TransformCrop {
+    /// Left boundary of the region. This must be divisible by the MCU width (see
+    /// [`Subsamp::mcu_width()`][crate::Subsamp::mcu_width]).
+    pub x: usize,
+    /// Upper boundary of the region. This must be divisible by the MCU height (see
+    /// [`Subsamp::mcu_height()`][crate::Subsamp::mcu_height]).
+    pub y: usize,
+    /// Width of the region. If None is given, the region ends at the right boundary of the image.
+    pub width: Option<usize>,
+    /// Height of the region. If None is given, the region ends at the bottom boundary of the
+    /// image.
+    pub height: Option<usize>,
+}
+
+impl TransformCrop {
+    /// Creates a cropping region that covers the given rectangle and starts on MCU boundaries.
+    ///
+    /// The [`x`][Self::x] and [`y`][Self::y] position is rounded down to the MCU grid of
+    /// `subsamp` and the size is enlarged by the same amount, so the right and bottom boundaries
+    /// stay in place. Use [`align_to_mcu()`][Self::align_to_mcu] to choose a different rounding
+    /// policy or to find out how much the region moved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Subsamp, TransformCrop};
+    /// let crop = TransformCrop::aligned(21, 9, 100, 50, Subsamp::Sub2x2);
+    /// assert_eq!(crop, TransformCrop { x: 16, y: 0, width: Some(105), height: Some(59) });
+    /// ```
+    pub fn aligned(
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        subsamp: Subsamp,
+    ) -> TransformCrop {
+        assert!(width > 0 && height > 0, "crop region must not be empty");
+        let crop = TransformCrop {
+            x,
+            y,
+            width: Some(width),
+            height: Some(height),
+        };
+        match crop.align_to_mcu(subsamp, RoundMode::Down) {
+            Ok((crop, _)) => crop,
+            Err(_) => unreachable!("rounding down never shrinks the region"),
+        }
+    }
+
+    /// Moves the position of this region to the MCU grid of `subsamp`.
+    ///
+    /// The [`x`][Self::x] and [`y`][Self::y] position is rounded according to `mode`, and the
+    /// [`width`][Self::width] and [`height`][Self::height] are adjusted so that the right and
+    /// bottom boundaries of the region stay in place (TurboJPEG does not require them to be
+    /// aligned). Returns the aligned region together with the distance by which its upper left
+    /// corner moved.
+    ///
+    /// Returns [`Error::InvalidParam`] if rounding up would leave an empty region.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{CropAdjustment, RoundMode, Subsamp, TransformCrop};
+    /// let crop = TransformCrop { x: 13, y: 30, width: Some(40), height: None };
+    /// let (aligned, adjustment) = crop.align_to_mcu(Subsamp::Sub2x2, RoundMode::Nearest)?;
+    /// assert_eq!(aligned, TransformCrop { x: 16, y: 32, width: Some(37), height: None });
+    /// assert_eq!(adjustment, CropAdjustment { x: 3, y: 2 });
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn align_to_mcu(
+        &self,
+        subsamp: Subsamp,
+        mode: RoundMode,
+    ) -> Result<(TransformCrop, CropAdjustment)> {
+        let (mcu_width, mcu_height) = subsamp.mcu_size();
+        let (x, width) = align_span(self.x, self.width, mcu_width, mode)?;
+        let (y, height) = align_span(self.y, self.height, mcu_height, mode)?;
+        let crop = TransformCrop {
+            x,
+            y,
+            width,
+            height,
+        };
+        let adjustment = CropAdjustment {
+            x: x as isize - self.x as isize,
+            y: y as isize - self.y as isize,
+        };
+        Ok((crop, adjustment))
+    }
+
+    /// Computes the largest MCU-aligned region of an image with the given aspect ratio.
+    ///
+    /// The region spans the full width or height of an image of size `image_width` x
+    /// `image_height` and has the aspect ratio `aspect_width` : `aspect_height` (rounded down to
+    /// whole pixels). Along the other axis, the region is placed according to `gravity` and its
+    /// position is moved to the nearest MCU boundary of `subsamp`, so the region can be used in
+    /// [`Transform::crop`] as is.
+    ///
+    /// See also [`Transformer::cover_crop()`], which applies this crop to a JPEG image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `aspect_width` or `aspect_height` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Gravity, Subsamp, TransformCrop};
+    /// let crop = TransformCrop::cover(1000, 600, 1, 1, Gravity::Center, Subsamp::Sub2x2);
+    /// assert_eq!(crop, TransformCrop { x: 208, y: 0, width: Some(600), height: Some(600) });
+    /// ```
+    pub fn cover(
+        image_width: usize,
+        image_height: usize,
+        aspect_width: usize,
+        aspect_height: usize,
+        gravity: Gravity,
+        subsamp: Subsamp,
+    ) -> TransformCrop {
+        assert!(
+            aspect_width > 0 && aspect_height > 0,
+            "aspect ratio must not be zero"
+        );
+        let (mcu_width, mcu_height) = subsamp.mcu_size();
+        let place = |image_size: usize, size: usize, mcu_size: usize| {
+            let slack = image_size - size;
+            let pos = match gravity {
+                Gravity::Center => slack / 2,
+                Gravity::Top => 0,
+                Gravity::GoldenRatio => slack * 1000 / 2618,
+            };
+            let aligned = (pos + mcu_size / 2) / mcu_size * mcu_size;
+            if aligned > slack {
+                aligned - mcu_size
+            } else {
+                aligned
+            }
+        };
+
+        if image_width * aspect_height > image_height * aspect_width {
+            let width = image_height * aspect_width / aspect_height;
+            TransformCrop {
+                x: place(image_width, width, mcu_width),
+                y: 0,
+                width: Some(width),
+                height: Some(image_height),
+            }
+        } else {
+            let height = image_width * aspect_height / aspect_width;
+            TransformCrop {
+                x: 0,
+                y: place(image_height, height, mcu_height),
+                width: Some(image_width),
+                height: Some(height),
+            }
+        }
+    }
+}
+
+impl fmt::Display for TransformCrop {
+    /// Formats the region as `WxH+X+Y`, like the `-crop` option of `jpegtran`. The width and
+    /// height are omitted if they are None.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(width) = self.width {
+            write!(f, "{}", width)?;
+        }
+        if let Some(height) = self.height {
+            write!(f, "x{}", height)?;
+        }
+        write!(f, "+{}+{}", self.x, self.y)
+    }
+}
+
+impl FromStr for TransformCrop {
+    type Err = Error;
+
+    /// Parses a region in the form `WxH+X+Y`, like the `-crop` option of `jpegtran`.
+    ///
+    /// The width and height may be omitted (`W`, `xH`, `WxH` or `+X+Y`), the position defaults to
+    /// (0, 0). Returns [`Error::InvalidParam`] if the string is malformed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::TransformCrop;
+    /// let crop: TransformCrop = "200x100+16+32".parse()?;
+    /// assert_eq!(crop, TransformCrop { x: 16, y: 32, width: Some(200), height: Some(100) });
+    /// let crop: TransformCrop = "x100".parse()?;
+    /// assert_eq!(crop, TransformCrop { x: 0, y: 0, width: None, height: Some(100) });
+    /// assert_eq!(crop.to_string(), "x100+0+0");
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<TransformCrop> {
+        let parse = |s: &str| s.parse::<usize>().map_err(|_| Error::InvalidParam("crop"));
+        let parse_size = |s: &str| match s {
+            "" => Ok(None),
+            s => parse(s).map(Some),
+        };
+
+        let (size, pos) = match s.find('+') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let (width, height) = match size.split_once('x') {
+            Some((width, height)) => (parse_size(width)?, parse_size(height)?),
+            None => (parse_size(size)?, None),
+        };
+        let (x, y) = match pos.map(|pos| pos.split_once('+')) {
+            Some(Some((x, y))) => (parse(x)?, parse(y)?),
+            Some(None) => return Err(Error::InvalidParam("crop")),
+            None => (0, 0),
+        };
+        Ok(TransformCrop {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Aligns the start of a one-dimensional span, keeping its end in place.
+fn align_span(
+    pos: usize,
+    size: Option<usize>,
+    mcu_size: usize,
+    mode: RoundMode,
+) -> Result<(usize, Option<usize>)> {
+    let down = pos - pos % mcu_size;
+    let aligned = match mode {
+        RoundMode::Down => down,
+        RoundMode::Up if down == pos => down,
+        RoundMode::Up => down + mcu_size,
+        RoundMode::Nearest if pos - down < mcu_size / 2 => down,
+        RoundMode::Nearest => down + mcu_size,
+    };
+    let size = match size {
+        Some(size) => {
+            let end = pos + size;
+            if aligned >= end {
+                return Err(Error::InvalidParam("crop"));
+            }
+            Some(end - aligned)
+        }
+        None => None,
+    };
+    Ok((aligned, size))
+}
+
+/// Factor `M/N` by which a [`Transform`] downscales the image, see [`Transform::scale`].
+///
+/// Like the `-scale M/N` option of `jpegtran`, the numerator and denominator are between 1 and
+/// 16, but only downscaling is supported (`M <= N`). The factor is stored in lowest terms, so
+/// `2/4` is equal to [`HALF`][Self::HALF].
+///
+/// The same factors are used to downscale YUV images with
+/// [`YuvImage::downscale()`][crate::YuvImage::downscale].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TransformScale {
+    num: usize,
+    denom: usize,
+}
+
+impl TransformScale {
+    /// Keep the original size.
+    pub const ONE: TransformScale = TransformScale { num: 1, denom: 1 };
+    /// Scale the image to 1/2 of its size.
+    pub const HALF: TransformScale = TransformScale { num: 1, denom: 2 };
+    /// Scale the image to 1/4 of its size.
+    pub const QUARTER: TransformScale = TransformScale { num: 1, denom: 4 };
+    /// Scale the image to 1/8 of its size.
+    pub const EIGHTH: TransformScale = TransformScale { num: 1, denom: 8 };
+
+    /// Create the scaling factor `num / denom`.
+    ///
+    /// Returns [`Error::InvalidParam`] unless `1 <= num <= denom <= 16`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::TransformScale;
+    /// let scale = TransformScale::new(6, 8)?;
+    /// assert_eq!((scale.numerator(), scale.denominator()), (3, 4));
+    /// assert_eq!(TransformScale::new(2, 4)?, TransformScale::HALF);
+    /// assert!(TransformScale::new(3, 2).is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn new(num: usize, denom: usize) -> Result<TransformScale> {
+        if num == 0 || num > denom || denom > 16 {
+            return Err(Error::InvalidParam("scale"));
+        }
+        let (mut a, mut b) = (num, denom);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        Ok(TransformScale {
+            num: num / a,
+            denom: denom / a,
+        })
+    }
+
+    /// Get the numerator of the scaling factor.
+    pub fn numerator(self) -> usize {
+        self.num
+    }
+
+    /// Get the denominator of the scaling factor.
+    pub fn denominator(self) -> usize {
+        self.denom
+    }
+
+    /// Scale a width or height, rounding up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::TransformScale;
+    /// assert_eq!(TransformScale::QUARTER.apply(100), 25);
+    /// assert_eq!(TransformScale::EIGHTH.apply(100), 13);
+    /// assert_eq!(TransformScale::new(3, 4)?.apply(101), 76);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn apply(self, size: usize) -> usize {
+        (size * self.num).div_ceil(self.denom)
+    }
+}
+
+impl Default for TransformScale {
+    fn default() -> TransformScale {
+        TransformScale::ONE
+    }
+}
+
+impl fmt::Display for TransformScale {
+    /// Formats the factor as `M/N`, such as `1/2` or `3/4`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.denom)
+    }
+}
+
+impl FromStr for TransformScale {
+    type Err = Error;
+
+    /// Parses a factor in the form `M/N` or `M`, as formatted by [`Display`][fmt::Display].
+    ///
+    /// Returns [`Error::InvalidParam`] for malformed or unsupported factors.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::TransformScale;
+    /// assert_eq!("1/2".parse::<TransformScale>()?, TransformScale::HALF);
+    /// assert_eq!("5/8".parse::<TransformScale>()?.to_string(), "5/8");
+    /// assert!("2".parse::<TransformScale>().is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    fn from_str(s: &str) -> Result<TransformScale> {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Error::InvalidParam("scale"))
+        };
+        match s.split_once('/') {
+            Some((num, denom)) => TransformScale::new(parse(num)?, parse(denom)?),
+            None => TransformScale::new(parse(s)?, 1),
+        }
+    }
+}
+
+/// Rounding policy used when aligning a [`TransformCrop`] to the MCU grid.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum RoundMode {
+    /// Move the position up and to the left. The aligned region contains the original one.
+    #[default]
+    Down,
+    /// Move the position down and to the right. The aligned region is contained in the original
+    /// one.
+    Up,
+    /// Move the position to the closest MCU boundary (halfway positions move down and to the
+    /// right).
+    Nearest,
+}
+
+/// Placement of a [cover crop][TransformCrop::cover] along the axis that is cropped.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Gravity {
+    /// Remove the same amount from both sides of the image.
+    #[default]
+    Center,
+    /// Keep the top edge of the image (or the left edge if the image is cropped horizontally).
+    Top,
+    /// Split the removed area in the golden ratio, removing the smaller part (about 38%) from the
+    /// top or left. This keeps the region of interest of typical photos better than centering.
+    GoldenRatio,
+}
+
+/// Distance by which the upper left corner of a [`TransformCrop`] moved during alignment.
+///
+/// Negative values mean that the corner moved up or to the left.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct CropAdjustment {
+    /// Horizontal movement in pixels.
+    pub x: isize,
+    /// Vertical movement in pixels.
+    pub y: isize,
+}
+
+impl Transformer {
+    /// Create a new transformer instance.
+    #[doc(alias = "tj3Init")]
+    pub fn new() -> Result<Transformer> {
+        let handle = Handle::new(ffi::TJINIT_TJINIT_TRANSFORM)?;
+        Ok(Self { handle })
+    }
+
+    /// Reads the header of `jpeg_data` with the handle of this transformer.
+    ///
+    /// TurboJPEG stores the entropy coding of the image in the parameters of the handle, where it
+    /// would also apply to the outputs of the following transforms, so these parameters are reset.
+    fn read_header(&mut self, jpeg_data: &[u8]) -> Result<DecompressHeader> {
+        let header = read_header_with(&mut self.handle, jpeg_data)?;
+        self.handle.set(ffi::TJPARAM_TJPARAM_PROGRESSIVE, 0)?;
+        self.handle.set(ffi::TJPARAM_TJPARAM_ARITHMETIC, 0)?;
+        Ok(header)
+    }
+
+    /// Apply a transformation to the compressed JPEG.
+    ///
+    /// This is the main transformation method, which gives you full control of the output buffer. If
+    /// you don't need this level of control, you can use one of the convenience wrappers below.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// // read JPEG data from file
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    ///
+    /// // initialize the transformer
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    ///
+    /// // define the transformation: flip vertically, trim partial MCU blocks on the bottom edge
+    /// let mut transform = turbojpeg::Transform::op(turbojpeg::TransformOp::Vflip);
+    /// transform.trim = true;
+    ///
+    /// // initialize the output buffer
+    /// let mut flipped_data = turbojpeg::OutputBuf::new_owned();
+    ///
+    /// // apply the transformation
+    /// transformer.transform(&transform, &jpeg_data, &mut flipped_data)?;
+    ///
+    /// // write the flipped JPEG back to disk
+    /// std::fs::write(std::env::temp_dir().join("flipped_parrots.jpg"), &flipped_data)?;
+    ///
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3Transform")]
+    pub fn transform(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        output: &mut OutputBuf,
+    ) -> Result<()> {
+        self.transform_multi(
+            std::slice::from_ref(transform),
+            jpeg_data,
+            std::slice::from_mut(output),
+        )
+    }
+
+    /// Apply a transformation to the compressed JPEG and return statistics about it.
+    ///
+    /// This is the same as [`transform()`][Self::transform], but it also reports the sizes of the
+    /// input and output, the partial MCU blocks that were [trimmed][Transform::trim] and the size
+    /// of the markers in the output, which is useful for auditing lossless operations.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{OutputBuf, Transform, TransformOp};
+    /// let image = turbojpeg::Image::mandelbrot(100, 64, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    ///
+    /// let mut transform = Transform::op(TransformOp::Hflip);
+    /// transform.trim = true;
+    /// let mut output = OutputBuf::new_owned();
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let stats = transformer.transform_with_stats(&transform, &jpeg_data, &mut output)?;
+    ///
+    /// // 100 is not divisible by the MCU width 16, so 4 columns are trimmed
+    /// assert!(stats.trimmed);
+    /// assert_eq!(stats.trimmed_columns, 4);
+    /// assert_eq!(stats.trimmed_pixels, 4 * 64);
+    /// assert_eq!(stats.output_len, output.len());
+    ///
+    /// // with scaling, the edges are trimmed from the scaled image
+    /// let image = turbojpeg::Image::mandelbrot(200, 128, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// transform.scale = turbojpeg::TransformScale::HALF;
+    /// let stats = transformer.transform_with_stats(&transform, &jpeg_data, &mut output)?;
+    /// assert_eq!(stats.trimmed_columns, 4);
+    /// assert_eq!(stats.trimmed_pixels, 4 * 64);
+    ///
+    /// let header = turbojpeg::read_header(&output)?;
+    /// assert_eq!((header.width, header.height), (96, 64));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn transform_with_stats(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        output: &mut OutputBuf,
+    ) -> Result<TransformStats> {
+        self.transform(transform, jpeg_data, output)?;
+
+        let header = self.read_header(jpeg_data)?;
+        let (trimmed_columns, trimmed_rows, trimmed_pixels) = if transform.trim {
+            let (columns, rows) = transform.imperfect_edges(&header);
+            let (width, height) = (
+                transform.scale.apply(header.width),
+                transform.scale.apply(header.height),
+            );
+            (
+                columns,
+                rows,
+                columns * height + rows * width - columns * rows,
+            )
+        } else {
+            (0, 0, 0)
+        };
+        let mut marker_len = 0;
+        for segment in markers::segments(output) {
+            let segment = segment?;
+            if segment.marker == markers::SOS {
+                break;
+            } else if segment.is_extra() {
+                marker_len += segment.len;
+            }
+        }
+
+        Ok(TransformStats {
+            input_len: jpeg_data.len(),
+            output_len: output.len(),
+            trimmed: trimmed_pixels > 0,
+            trimmed_columns,
+            trimmed_rows,
+            trimmed_pixels,
+            marker_len,
+            crop: transform.effective_crop(header.subsamp)?,
+        })
+    }
+
+    /// Apply multiple transformations to the compressed JPEG in a single pass.
+    ///
+    /// The JPEG image is parsed only once and the result of `transforms[i]` is stored into
+    /// `outputs[i]`. This is faster than calling [`transform()`][Self::transform] for every
+    /// transformation separately, for example when producing all rotations of an image or several
+    /// crops of it.
+    ///
+    /// If the `outputs` are borrowed and one of them is too small, this method returns
+    /// [`Error::OutputBufTooSmall`] with the size that is required for the first output that did
+    /// not fit, and none of the outputs is written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of `transforms` and `outputs` differs, or if some of the `outputs`
+    /// are owned and some are borrowed (outputs created by [`OutputBuf::vec()`] count as
+    /// borrowed).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::{OutputBuf, Transform, TransformOp};
+    ///
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    ///
+    /// let transforms = [
+    ///     Transform::op(TransformOp::Rot90),
+    ///     Transform::op(TransformOp::Rot180),
+    ///     Transform::op(TransformOp::Rot270),
+    /// ];
+    /// let mut outputs = [OutputBuf::new_owned(), OutputBuf::new_owned(), OutputBuf::new_owned()];
+    /// transformer.transform_multi(&transforms, &jpeg_data, &mut outputs)?;
+    ///
+    /// let header = turbojpeg::read_header(&outputs[0])?;
+    /// assert_eq!((header.width, header.height), (256, 384));
+    /// let header = turbojpeg::read_header(&outputs[1])?;
+    /// assert_eq!((header.width, header.height), (384, 256));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// Owned outputs may use different allocators; memory of an output with a custom
+    /// [`BufAllocator`][crate::BufAllocator] is never allocated or freed by TurboJPEG:
+    ///
+    /// ```
+    /// use std::alloc::Layout;
+    /// use std::collections::HashSet;
+    /// use std::sync::{Arc, Mutex};
+    /// use turbojpeg::{OutputBuf, Transform, TransformOp};
+    ///
+    /// #[derive(Default)]
+    /// struct TrackingAllocator {
+    ///     live: Mutex<HashSet<usize>>,
+    /// }
+    ///
+    /// unsafe impl turbojpeg::BufAllocator for TrackingAllocator {
+    ///     fn allocate(&self, len: usize) -> *mut u8 {
+    ///         let ptr = unsafe { std::alloc::alloc(Layout::array::<u8>(len).unwrap()) };
+    ///         self.live.lock().unwrap().insert(ptr as usize);
+    ///         ptr
+    ///     }
+    ///
+    ///     unsafe fn deallocate(&self, ptr: *mut u8, len: usize) {
+    ///         assert!(self.live.lock().unwrap().remove(&(ptr as usize)));
+    ///         std::alloc::dealloc(ptr, Layout::array::<u8>(len).unwrap());
+    ///     }
+    /// }
+    ///
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let allocator = Arc::new(TrackingAllocator::default());
+    ///
+    /// let transforms = [Transform::op(TransformOp::Rot90), Transform::op(TransformOp::Rot180)];
+    /// let mut outputs = [OutputBuf::new_owned(), OutputBuf::new_owned_in(allocator.clone())];
+    /// transformer.transform_multi(&transforms, &jpeg_data, &mut outputs)?;
+    ///
+    /// let header = turbojpeg::read_header(&outputs[1])?;
+    /// assert_eq!((header.width, header.height), (384, 256));
+    /// drop(outputs);
+    /// assert!(allocator.live.lock().unwrap().is_empty());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "tj3Transform")]
+    pub fn transform_multi(
+        &mut self,
+        transforms: &[Transform],
+        jpeg_data: &[u8],
+        outputs: &mut [OutputBuf],
+    ) -> Result<()> {
+        self.transform_filtered(transforms, jpeg_data, outputs, None)
+    }
+
+    /// Apply a transformation to the compressed JPEG and pass the transformed DCT coefficients to
+    /// a custom `filter`.
+    ///
+    /// The `filter` is called for every row of DCT blocks of every component of the output image,
+    /// after the transformation was applied but before the coefficients are compressed, and it can
+    /// inspect or modify the coefficients (see [`DctBlocks`]). This can be used to implement
+    /// watermarking or selective blurring without recompressing the image.
+    ///
+    /// If the `filter` panics, the transformation is aborted and the panic is resumed after
+    /// TurboJPEG returns.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let transform = turbojpeg::Transform::default();
+    ///
+    /// // remove all color information by zeroing the chrominance coefficients (except DC)
+    /// let mut output = turbojpeg::OutputBuf::new_owned();
+    /// transformer.transform_with_filter(&transform, &jpeg_data, &mut output, |blocks| {
+    ///     if blocks.component > 0 {
+    ///         for block in blocks.coefficients.chunks_exact_mut(64) {
+    ///             block[1..].fill(0);
+    ///         }
+    ///     }
+    /// })?;
+    ///
+    /// assert!(output.len() < jpeg_data.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "customFilter")]
+    pub fn transform_with_filter<F>(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        output: &mut OutputBuf,
+        mut filter: F,
+    ) -> Result<()>
+    where
+        F: FnMut(DctBlocks<'_>),
+    {
+        self.transform_filtered(
+            std::slice::from_ref(transform),
+            jpeg_data,
+            std::slice::from_mut(output),
+            Some(&mut filter),
+        )
+    }
+
+    fn transform_filtered(
+        &mut self,
+        transforms: &[Transform],
+        jpeg_data: &[u8],
+        outputs: &mut [OutputBuf],
+        mut filter: Option<&mut dyn FnMut(DctBlocks<'_>)>,
+    ) -> Result<()> {
+        assert_eq!(
+            transforms.len(),
+            outputs.len(),
+            "the number of transforms and outputs must be the same"
+        );
+        if transforms.is_empty() {
+            return Ok(());
+        }
+        let is_owned = outputs[0].is_owned;
+        assert!(
+            outputs.iter().all(|output| output.is_owned == is_owned),
+            "the outputs must be either all owned or all borrowed"
+        );
+
+        if transforms
+            .iter()
+            .any(|transform| transform.scale != TransformScale::ONE)
+        {
+            // scale the image first and apply the rest of the transform to the scaled image
+            let scale = transforms[0].scale;
+            if transforms.iter().any(|transform| transform.scale != scale) {
+                for (transform, output) in transforms.iter().zip(outputs.iter_mut()) {
+                    self.transform_filtered(
+                        std::slice::from_ref(transform),
+                        jpeg_data,
+                        std::slice::from_mut(output),
+                        match filter {
+                            Some(ref mut filter) => Some(&mut **filter),
+                            None => None,
+                        },
+                    )?;
+                }
+                return Ok(());
+            }
+            let scaled = self.downscale(jpeg_data, scale)?;
+            let transforms = transforms
+                .iter()
+                .map(|transform| Transform {
+                    scale: TransformScale::ONE,
+                    ..transform.clone()
+                })
+                .collect::<Vec<_>>();
+            return self.transform_filtered(&transforms, &scaled, outputs, filter);
+        }
+
+        // with NOREALLOC, TurboJPEG may write up to the maximal size of the output into every
+        // buffer, so it never gets a borrowed buffer that is smaller, and the limits below this
+        // size are checked before a borrowed buffer is written
+        let max_len = max_transformed_len(jpeg_data)?;
+        if !is_owned
+            && outputs.iter().zip(transforms).any(|(output, transform)| {
+                output.is_too_small(max_len)
+                    || transform
+                        .max_output_len
+                        .is_some_and(|limit| limit < max_len)
+            })
+        {
+            return self.transform_via_owned(transforms, jpeg_data, outputs, filter);
+        }
+
+        let n = transforms
+            .len()
+            .try_into()
+            .map_err(|_| Error::IntegerOverflow("transforms"))?;
+        // the subsampling is needed only to align the crops and redactions
+        let subsamp = if transforms.iter().any(|transform| {
+            !transform.redact.is_empty()
+                || (transform.crop.is_some() && transform.crop_rounding.is_some())
+        }) {
+            Some(self.read_header(jpeg_data)?.subsamp)
+        } else {
+            None
+        };
+        let mut c_transforms = transforms
+            .iter()
+            .map(|transform| match subsamp {
+                Some(subsamp) => transform
+                    .with_crop(transform.effective_crop(subsamp)?)
+                    .to_ffi(),
+                None => transform.to_ffi(),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let redactions = if let Some(subsamp) = subsamp.filter(|_| {
+            transforms
+                .iter()
+                .any(|transform| !transform.redact.is_empty())
+        }) {
+            transforms
+                .iter()
+                .map(|transform| transform.redaction(subsamp))
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            Vec::new()
+        };
+        let mut filter_state = if filter.is_some() || !redactions.is_empty() {
+            Some(FilterState {
+                filter: filter.as_mut().map(|filter| &mut **filter as _),
+                redactions,
+                panic: None,
+            })
+        } else {
+            None
+        };
+        if let Some(ref mut filter_state) = filter_state {
+            for c_transform in c_transforms.iter_mut() {
+                c_transform.data = filter_state as *mut FilterState as *mut libc::c_void;
+                c_transform.customFilter = Some(filter_callback);
+            }
+        }
+
+        // owned outputs are written in place only if all of them have enough memory (see
+        // `OutputBuf::no_realloc()`); if one of them must never be allocated by TurboJPEG, all of
+        // them are allocated by us; the vectors of outputs created by `OutputBuf::vec()` are grown
+        // up front as well
+        if is_owned || outputs.iter().any(|output| output.vec.is_some()) {
+            let in_place = outputs.iter().any(OutputBuf::allocates_itself);
+            for output in outputs.iter_mut() {
+                if in_place {
+                    output.prepare_in_place(max_len);
+                } else {
+                    output.prepare(max_len);
+                }
+            }
+        }
+        let no_realloc = outputs.iter().all(OutputBuf::no_realloc);
+        if !no_realloc {
+            for output in outputs.iter_mut().filter(|output| !output.ptr.is_null()) {
+                output.release();
+            }
+        }
+        self.handle
+            .set(ffi::TJPARAM_TJPARAM_NOREALLOC, no_realloc as libc::c_int)?;
+        let capacities = outputs.iter().map(OutputBuf::capacity).collect::<Vec<_>>();
+        let mut output_ptrs = outputs.iter().map(|output| output.ptr).collect::<Vec<_>>();
+        let mut output_lens = capacities
+            .iter()
+            .map(|&capacity| capacity as ffi::size_t)
+            .collect::<Vec<_>>();
+        let res = unsafe {
+            ffi::tj3Transform(
+                self.handle.as_ptr(),
+                jpeg_data.as_ptr(),
+                jpeg_data.len() as ffi::size_t,
+                n,
+                output_ptrs.as_mut_ptr(),
+                output_lens.as_mut_ptr(),
+                c_transforms.as_mut_ptr(),
+            )
+        };
+        for (output, ptr) in outputs.iter_mut().zip(output_ptrs) {
+            output.ptr = ptr;
+        }
+        if let Some(panic) = filter_state.and_then(|filter_state| filter_state.panic) {
+            std::panic::resume_unwind(panic);
+        }
+        if res != 0 {
+            return Err(self.handle.get_error());
+        }
+        for (output, len) in outputs.iter_mut().zip(output_lens) {
+            if output.ptr.is_null() {
+                output.len = 0;
+                return Err(Error::Null);
+            }
+            output.set_written(len as usize);
+        }
+        for ((transform, output), capacity) in transforms.iter().zip(outputs).zip(capacities) {
+            let limit = transform.max_output_len.unwrap_or(usize::MAX);
+            match self.finish_output(transform, jpeg_data, output, capacity.min(limit)) {
+                Err(Error::OutputBufTooSmall(len)) if len > limit => {
+                    return Err(Error::OutputTooLarge(len))
+                }
+                res => res?,
+            }
+            if output.len > limit {
+                return Err(Error::OutputTooLarge(output.len));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Transforms into scratch owned buffers, then copies the results into the borrowed
+    /// `outputs`.
+    ///
+    /// Nothing is written into the `outputs` unless all results fit into them and none of them
+    /// exceeds its limit.
+    fn transform_via_owned(
+        &mut self,
+        transforms: &[Transform],
+        jpeg_data: &[u8],
+        outputs: &mut [OutputBuf],
+        filter: Option<&mut dyn FnMut(DctBlocks<'_>)>,
+    ) -> Result<()> {
+        let mut owned_outputs = transforms
+            .iter()
+            .map(|_| OutputBuf::new_owned())
+            .collect::<Vec<_>>();
+        self.transform_filtered(transforms, jpeg_data, &mut owned_outputs, filter)?;
+        let too_small = outputs
+            .iter()
+            .zip(&owned_outputs)
+            .find(|(output, owned_output)| {
+                output.vec.is_none() && owned_output.len() > output.capacity()
+            });
+        if let Some((_, owned_output)) = too_small {
+            return Err(Error::OutputBufTooSmall(owned_output.len()));
+        }
+        for (output, owned_output) in outputs.iter_mut().zip(&owned_outputs) {
+            output.clear();
+            output.grow_vec(owned_output.len());
+            output.write_data(owned_output);
+        }
+        Ok(())
+    }
+
+    /// Applies the parts of the `transform` that are not implemented by TurboJPEG to the
+    /// `output`.
+    ///
+    /// A borrowed `output` may grow up to its `capacity`, an owned `output` is reallocated if
+    /// needed.
+    fn finish_output(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        output: &mut OutputBuf,
+        capacity: usize,
+    ) -> Result<()> {
+        let mut data: Option<ScratchVec> = None;
+        if !transform.copy_none {
+            if let Some(keep_markers) = transform.keep_markers {
+                data = Some(scratch_vec(keep_markers.apply(output)?));
+            }
+            if transform.update_exif {
+                let input = data.as_ref().map_or(&output[..], |data| &data[..]);
+                if let Some(updated) = self.update_exif(transform, jpeg_data, input)? {
+                    data = Some(scratch_vec(updated));
+                }
+            }
+        }
+        if let Some(ref xmp) = transform.xmp {
+            let input = data.as_ref().map_or(&output[..], |data| &data[..]);
+            data = Some(scratch_vec(xmp::replace_xmp(input, Some(xmp))?));
+        }
+
+        if let Some(data) = data {
+            let capacity = if output.is_owned {
+                output.capacity()
+            } else {
+                capacity
+            };
+            if data.len() <= output.len || data.len() <= capacity {
+                output.write_data(&data);
+            } else if output.is_owned {
+                let mut buf = output.allocate_like(data.len());
+                buf.copy_from_slice(&data);
+                output.set_owned(buf);
+            } else if output.vec.is_some() {
+                output.clear();
+                output.grow_vec(data.len());
+                output.write_data(&data);
+            } else {
+                return Err(Error::OutputBufTooSmall(data.len()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites the orientation and the thumbnail in the EXIF data of the `output` of the
+    /// `transform`.
+    ///
+    /// Returns `None` if the output has no EXIF data.
+    fn update_exif(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        output: &[u8],
+    ) -> Result<Option<Vec<u8>>> {
+        let mut exif_segment = None;
+        for segment in markers::segments(output) {
+            let segment = segment?;
+            if segment.marker == markers::SOS {
+                break;
+            } else if exif::is_exif(&segment) {
+                exif_segment = Some(segment);
+                break;
+            }
+        }
+        let segment = match exif_segment {
+            Some(segment) => segment,
+            None => return Ok(None),
+        };
+
+        let mut updated = output.to_vec();
+        if let Some(field) = exif::find_orientation(output)? {
+            if let Some(display_op) = TransformOp::from_exif_orientation(field.value) {
+                // the output is displayed as the input with the op applied after the display op
+                let op = transform.op.inverse().then(display_op).then(transform.op);
+                exif::write_orientation(&mut updated, field, op.to_exif_orientation());
+            }
+        }
+
+        let segment_end = segment.offset + segment.len;
+        let payload = &updated[segment_end - segment.payload.len()..segment_end];
+        let header = self.read_header(output)?;
+        let mut new_payload = exif::write_dimensions(payload, header.width, header.height)?;
+        if let Some(range) = exif::find_thumbnail(&new_payload)? {
+            let thumbnail = if transform.redact.is_empty() {
+                self.transform_thumbnail(transform, jpeg_data, &new_payload[range])
+                    .ok()
+            } else {
+                None
+            };
+            let with_thumbnail = match thumbnail {
+                Some(thumbnail) => exif::replace_thumbnail(&new_payload, &thumbnail)?,
+                None => exif::strip_exif(&new_payload, false, true)?,
+            };
+            new_payload = if with_thumbnail.len() > markers::MAX_PAYLOAD_LEN {
+                exif::strip_exif(&new_payload, false, true)?
+            } else {
+                with_thumbnail
+            };
+        }
+        let mut new_segment = Vec::with_capacity(new_payload.len() + 4);
+        markers::write_segment(&mut new_segment, segment.marker, &new_payload);
+        updated.splice(segment.offset..segment_end, new_segment);
+        Ok(Some(updated))
+    }
+
+    /// Applies the `transform` of the main image to its EXIF `thumbnail`.
+    fn transform_thumbnail(
+        &mut self,
+        transform: &Transform,
+        jpeg_data: &[u8],
+        thumbnail: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut thumbnail_transform = Transform {
+            op: transform.op,
+            trim: true,
+            gray: transform.gray,
+            optimize: transform.optimize,
+            ..Transform::default()
+        };
+        let header = self.read_header(jpeg_data)?;
+        if let Some(crop) = transform.effective_crop(header.subsamp)? {
+            // scale the crop from the size of the transformed image to the size of the transformed
+            // thumbnail
+            let thumbnail_header = self.read_header(thumbnail)?;
+            let transposed = transform.op.to_flips().0;
+            let size = |width, height| {
+                if transposed {
+                    (height, width)
+                } else {
+                    (width, height)
+                }
+            };
+            let (width, height) = size(header.width, header.height);
+            let (thumbnail_width, thumbnail_height) =
+                size(thumbnail_header.width, thumbnail_header.height);
+            let scale_x = |x: usize| x * thumbnail_width / width;
+            let scale_y = |y: usize| y * thumbnail_height / height;
+            let crop = TransformCrop {
+                x: scale_x(crop.x),
+                y: scale_y(crop.y),
+                width: crop.width.map(|crop_width| scale_x(crop_width).max(1)),
+                height: crop.height.map(|crop_height| scale_y(crop_height).max(1)),
+            };
+            let subsamp = thumbnail_transform.output_subsamp(thumbnail_header.subsamp);
+            thumbnail_transform.crop = Some(crop.align_to_mcu(subsamp, RoundMode::Nearest)?.0);
+        }
+        self.transform_to_vec(&thumbnail_transform, thumbnail)
+    }
+
+    /// Apply multiple transformations to the compressed JPEG in a single pass, storing the results
+    /// into owned buffers.
+    ///
+    /// See [`transform_multi()`][Self::transform_multi] for details.
+    pub fn transform_multi_to_owned(
+        &mut self,
+        transforms: &[Transform],
+        jpeg_data: &[u8],
+    ) -> Result<Vec<OwnedBuf>> {
+        let mut outputs = transforms
+            .iter()
+            .map(|_| OutputBuf::new_owned())
+            .collect::<Vec<_>>();
+        self.transform_multi(transforms, jpeg_data, &mut outputs)?;
+        Ok(outputs.into_iter().map(OutputBuf::into_owned).collect())
+    }
+
+    /// Rotate and flip the compressed JPEG according to its EXIF orientation tag.
+    ///
+    /// The orientation is read from the EXIF segment of the image and the corresponding lossless
+    /// transform is applied. Partial MCU blocks that cannot be transformed are
+    /// [trimmed][Transform::trim] and all markers are copied to the output. The EXIF data are
+    /// [updated][Transform::update_exif] for the transformed image (the thumbnail is rotated and
+    /// the width and height tags are set), and the orientation tag is reset to 1 (upright), so
+    /// that viewers do not rotate the image again.
+    ///
+    /// If the image has no EXIF orientation, or if the orientation is already 1 (or an invalid
+    /// value), a copy of the image is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::TransformOp;
+    ///
+    /// // EXIF data with orientation 6 (the camera was rotated by 90 degrees): a big-endian TIFF
+    /// // header and an IFD with one SHORT entry for the orientation tag (0x0112)
+    /// let tiff_header = b"MM\0\x2a\0\0\0\x08";
+    /// let ifd = b"\0\x01\x01\x12\0\x03\0\0\0\x01\0\x06\0\0\0\0\0\0";
+    /// let exif = [&b"Exif\0\0"[..], tiff_header, ifd].concat();
+    /// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.add_app_marker(1, &exif)?;
+    /// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+    /// assert_eq!(turbojpeg::exif_orientation(&jpeg_data)?, Some(TransformOp::Rot90));
+    ///
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let oriented = transformer.auto_orient(&jpeg_data)?;
+    ///
+    /// let header = turbojpeg::read_header(&oriented)?;
+    /// assert_eq!((header.width, header.height), (48, 64));
+    /// assert_eq!(turbojpeg::exif_orientation(&oriented)?, Some(TransformOp::None));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn auto_orient(&mut self, jpeg_data: &[u8]) -> Result<OwnedBuf> {
+        let op = match exif::exif_orientation(jpeg_data)? {
+            Some(op) if op != TransformOp::None => op,
+            _ => return Ok(OwnedBuf::copy_from_slice(jpeg_data)),
+        };
+
+        // all markers are copied, and the EXIF data are updated like for any other transform, but
+        // the transformed image is upright
+        let transform = Transform {
+            op,
+            trim: true,
+            update_exif: true,
+            ..Transform::default()
+        };
+        let mut output = self.transform_to_owned(&transform, jpeg_data)?;
+        if let Some(field) = exif::find_orientation(&output)? {
+            exif::write_orientation(&mut output, field, 1);
+        }
+        Ok(output)
+    }
+
+    /// Losslessly split the compressed JPEG into a grid of tiles.
+    ///
+    /// This is similar to `jpegtran -crop WxH`: the image is split into tiles of `tile_width` x
+    /// `tile_height` pixels (the tiles in the last column and row may be smaller), and every tile
+    /// is cropped from the DCT coefficients without recompression. All tiles are produced by a
+    /// single call to TurboJPEG (see [`transform_multi()`][Self::transform_multi]), so the image
+    /// is parsed only once.
+    ///
+    /// The tile size must be a multiple of the MCU size of the image (see
+    /// [`Subsamp::mcu_size()`]), otherwise [`Error::InvalidParam`] is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let grid = transformer.crop_grid(&jpeg_data, 128, 128)?;
+    ///
+    /// assert_eq!((grid.columns, grid.rows), (3, 2));
+    /// let tile = grid.tile(2, 1);
+    /// assert_eq!((tile.x, tile.y), (256, 128));
+    /// let header = turbojpeg::read_header(&tile.data)?;
+    /// assert_eq!((header.width, header.height), (128, 128));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn crop_grid(
+        &mut self,
+        jpeg_data: &[u8],
+        tile_width: usize,
+        tile_height: usize,
+    ) -> Result<TileGrid> {
+        let header = self.read_header(jpeg_data)?;
+        let (mcu_width, mcu_height) = header.subsamp.mcu_size();
+        if tile_width % mcu_width != 0 {
+            return Err(Error::InvalidParam("tile_width"));
+        }
+        if tile_height % mcu_height != 0 {
+            return Err(Error::InvalidParam("tile_height"));
+        }
+
+        let layout = TileLayout::new(header.width, header.height, tile_width, tile_height)?;
+        let transforms = layout
+            .tiles()
+            .map(|tile| Transform {
+                crop: Some(TransformCrop {
+                    x: tile.x,
+                    y: tile.y,
+                    width: Some(tile.width),
+                    height: Some(tile.height),
+                }),
+                ..Transform::default()
+            })
+            .collect::<Vec<_>>();
+        let tiles = self.transform_multi_to_owned(&transforms, jpeg_data)?;
+        Ok(layout.into_grid(tiles))
+    }
+
+    /// Losslessly crop the compressed JPEG to the aspect ratio `aspect_width` : `aspect_height`.
+    ///
+    /// The crop covers the full width or height of the image and is placed according to
+    /// `gravity` (see [`TransformCrop::cover()`] for details). This is the usual operation for
+    /// generating thumbnails of a fixed shape, without any recompression.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `aspect_width` or `aspect_height` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let square = transformer.cover_crop(&jpeg_data, 1, 1, turbojpeg::Gravity::Center)?;
+    ///
+    /// let header = turbojpeg::read_header(&square)?;
+    /// assert_eq!((header.width, header.height), (256, 256));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn cover_crop(
+        &mut self,
+        jpeg_data: &[u8],
+        aspect_width: usize,
+        aspect_height: usize,
+        gravity: Gravity,
+    ) -> Result<OwnedBuf> {
+        let header = self.read_header(jpeg_data)?;
+        let crop = TransformCrop::cover(
+            header.width,
+            header.height,
+            aspect_width,
+            aspect_height,
+            gravity,
+            header.subsamp,
+        );
+        let transform = Transform {
+            crop: Some(crop),
+            ..Transform::default()
+        };
+        self.transform_to_owned(&transform, jpeg_data)
+    }
+
+    /// Losslessly insert the `source` JPEG image into the `jpeg_data` image at position (`x`,
+    /// `y`).
+    ///
+    /// This is similar to `jpegtran -drop`: the DCT blocks of the destination image in the
+    /// covered area are replaced by the DCT blocks of the source image, and no other blocks of
+    /// either image are recompressed. The part of the source image that does not fit into the
+    /// destination image is discarded.
+    ///
+    /// The position must be aligned to the MCU size of the destination image (see
+    /// [`Subsamp::mcu_size()`]) and lie inside of the image, otherwise [`Error::InvalidParam`]
+    /// is returned. Both images must have the same chrominance subsampling, colorspace and
+    /// sample precision, otherwise [`Error::IncompatibleImages`] is returned. If the quantization tables of the
+    /// images differ, the blocks of the source image are requantized with the tables of the
+    /// destination image, which (like in `jpegtran -drop`) may lose some precision when the
+    /// destination image has coarser tables. If the width or height of the source image is not
+    /// a multiple of the MCU size, the padding of its partial MCU blocks is inserted as well.
+    ///
+    /// All DCT blocks of the source image are held in memory while the destination image is
+    /// transformed, so this is intended for sources that are small compared to the destination
+    /// image, such as logos or tiles.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    ///
+    /// // compress a logo with the same subsampling as the destination image
+    /// let logo = turbojpeg::Image::mandelbrot(64, 64, turbojpeg::PixelFormat::RGB);
+    /// let mut compressor = turbojpeg::Compressor::new()?;
+    /// compressor.set_quality(turbojpeg::estimate_quality(&jpeg_data)?.unwrap())?;
+    /// compressor.set_subsamp(header.subsamp)?;
+    /// let logo_data = compressor.compress_to_vec(logo.as_deref())?;
+    ///
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let output = transformer.drop_image(&jpeg_data, &logo_data, 32, 16)?;
+    /// let header = turbojpeg::read_header(&output)?;
+    /// assert_eq!((header.width, header.height), (384, 256));
+    ///
+    /// // a logo with a different quality is requantized
+    /// compressor.set_quality(40)?;
+    /// let logo_data = compressor.compress_to_vec(logo.as_deref())?;
+    /// let output = transformer.drop_image(&jpeg_data, &logo_data, 32, 16)?;
+    /// assert_eq!(turbojpeg::read_header(&output)?.width, 384);
+    ///
+    /// // a logo with a different subsampling is rejected
+    /// compressor.set_subsamp(turbojpeg::Subsamp::None)?;
+    /// let logo_data = compressor.compress_to_vec(logo.as_deref())?;
+    /// assert!(matches!(
+    ///     transformer.drop_image(&jpeg_data, &logo_data, 32, 16),
+    ///     Err(turbojpeg::Error::IncompatibleImages("chrominance subsampling")),
+    /// ));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[doc(alias = "drop")]
+    pub fn drop_image(
+        &mut self,
+        jpeg_data: &[u8],
+        source: &[u8],
+        x: usize,
+        y: usize,
+    ) -> Result<OwnedBuf> {
+        let header = self.read_header(jpeg_data)?;
+        let source_header = self.read_header(source)?;
+        if header.subsamp == Subsamp::Unknown || header.subsamp != source_header.subsamp {
+            return Err(Error::IncompatibleImages("chrominance subsampling"));
         }
-        if transform.copy_none {
-            options |= ffi::TJXOPT_COPYNONE
+        if header.colorspace != source_header.colorspace {
+            return Err(Error::IncompatibleImages("colorspaces"));
+        }
+        let precision = markers::frame_precision(jpeg_data)?;
+        if precision != markers::frame_precision(source)? {
+            return Err(Error::IncompatibleImages("sample precisions"));
+        }
+        let quant_tables = natural_quant_tables(jpeg_data)?;
+        let source_quant_tables = natural_quant_tables(source)?;
+        // largest magnitude of a DCT coefficient for the sample precision
+        let limit = (1 << (precision + 2)) - 1;
+        let (mcu_width, mcu_height) = header.subsamp.mcu_size();
+        if x % mcu_width != 0 || x >= header.width {
+            return Err(Error::InvalidParam("x"));
+        }
+        if y % mcu_height != 0 || y >= header.height {
+            return Err(Error::InvalidParam("y"));
         }
 
-        let mut region = ffi::tjregion {
-            x: 0,
-            y: 0,
-            w: 0,
-            h: 0,
-        };
-        if let Some(crop) = transform.crop {
-            region.x = crop
-                .x
-                .try_into()
-                .map_err(|_| Error::IntegerOverflow("crop.x"))?;
-            region.y = crop
-                .y
-                .try_into()
-                .map_err(|_| Error::IntegerOverflow("crop.y"))?;
-            if let Some(crop_w) = crop.width {
-                region.w = crop_w
-                    .try_into()
-                    .map_err(|_| Error::IntegerOverflow("crop.width"))?;
+        // rows of DCT blocks of every component of the source image, requantized with the
+        // quantization tables of the destination image
+        let mut source_rows: Vec<Vec<Vec<i16>>> = Vec::new();
+        self.read_coefficients(source, |blocks| {
+            if source_rows.len() <= blocks.component {
+                source_rows.resize_with(blocks.component + 1, Vec::new);
             }
-            if let Some(crop_h) = crop.height {
-                region.h = crop_h
-                    .try_into()
-                    .map_err(|_| Error::IntegerOverflow("crop.height"))?;
+            let mut row = blocks.coefficients.to_vec();
+            let from = &source_quant_tables[blocks.component];
+            let to = &quant_tables[blocks.component];
+            if from != to {
+                for block in row.chunks_exact_mut(64) {
+                    for (k, coef) in block.iter_mut().enumerate() {
+                        let value = (*coef as i32 * from[k] as i32).clamp(-limit, limit);
+                        *coef = (value as f64 / to[k] as f64).round() as i16;
+                    }
+                }
             }
-            options |= ffi::TJXOPT_CROP;
-        }
+            source_rows[blocks.component].push(row);
+        })?;
 
-        let mut transform = ffi::tjtransform {
-            r: region,
-            op: transform.op as libc::c_int,
-            options: options as libc::c_int,
-            data: ptr::null_mut(),
-            customFilter: None,
+        // the chrominance components (1 and 2) have one block per MCU, the other components have
+        // as many blocks as given by the subsampling factors
+        let (h_factor, v_factor) = header.subsamp.size();
+        let mut output = OutputBuf::new_owned();
+        self.transform_with_filter(&Transform::default(), jpeg_data, &mut output, |blocks| {
+            let (h, v) = match blocks.component {
+                1 | 2 => (1, 1),
+                _ => (h_factor, v_factor),
+            };
+            let (block_x, block_y) = (x / mcu_width * h, y / mcu_height * v);
+            let source_row = (blocks.y / 8)
+                .checked_sub(block_y)
+                .and_then(|row| source_rows.get(blocks.component)?.get(row));
+            if let Some(source_row) = source_row {
+                let dest_row = &mut blocks.coefficients[64 * block_x..];
+                let len = usize::min(source_row.len(), dest_row.len());
+                dest_row[..len].copy_from_slice(&source_row[..len]);
+            }
+        })?;
+        Ok(output.into_owned())
+    }
+
+    /// Downscales the image in the DCT domain (see [`Transform::scale`]).
+    fn downscale(&mut self, jpeg_data: &[u8], scale: TransformScale) -> Result<OwnedBuf> {
+        let header = self.read_header(jpeg_data)?;
+        let quant_tables = natural_quant_tables(jpeg_data)?;
+        // largest magnitude of a DCT coefficient for the sample precision
+        let limit = (1 << (markers::frame_precision(jpeg_data)? + 2)) - 1;
+
+        // rows of DCT blocks of every component of the input image
+        let mut rows: Vec<Vec<Vec<i16>>> = Vec::new();
+        self.read_coefficients(jpeg_data, |blocks| {
+            if rows.len() <= blocks.component {
+                rows.resize_with(blocks.component + 1, Vec::new);
+            }
+            rows[blocks.component].push(blocks.coefficients.to_vec());
+        })?;
+
+        let downscaler = DctDownscaler::new(scale.numerator(), scale.denominator());
+        let transform = Transform {
+            crop: Some(TransformCrop {
+                x: 0,
+                y: 0,
+                width: Some(scale.apply(header.width)),
+                height: Some(scale.apply(header.height)),
+            }),
+            ..Transform::default()
+        };
+        let mut output = OutputBuf::new_owned();
+        self.transform_with_filter(&transform, jpeg_data, &mut output, |blocks| {
+            let rows = &rows[blocks.component];
+            let quant_table = &quant_tables[blocks.component];
+            let row = blocks.y / 8;
+            for (column, block) in blocks.coefficients.chunks_exact_mut(64).enumerate() {
+                // blocks beyond the edge of the input image are replaced by the last block
+                let source = |i: usize, j: usize| {
+                    let source_row = &rows[usize::min(i, rows.len() - 1)];
+                    let source_column = usize::min(j, source_row.len() / 64 - 1);
+                    &source_row[64 * source_column..64 * (source_column + 1)]
+                };
+                downscaler.downscale(row, column, source, quant_table, limit, block);
+            }
+        })?;
+        Ok(output.into_owned())
+    }
+
+    /// Passes the DCT coefficients of the image to `filter` without producing any output.
+    fn read_coefficients<F>(&mut self, jpeg_data: &[u8], mut filter: F) -> Result<()>
+    where
+        F: FnMut(DctBlocks<'_>),
+    {
+        let mut filter_state = FilterState {
+            filter: Some(&mut filter),
+            redactions: Vec::new(),
+            panic: None,
         };
+        let mut c_transform = Transform::default().to_ffi()?;
+        c_transform.options |= ffi::TJXOPT_NOOUTPUT as libc::c_int;
+        c_transform.data = &mut filter_state as *mut FilterState as *mut libc::c_void;
+        c_transform.customFilter = Some(filter_callback);
 
-        self.handle.set(
-            ffi::TJPARAM_TJPARAM_NOREALLOC,
-            if output.is_owned { 0 } else { 1 } as libc::c_int,
-        )?;
-        let mut output_len = output.len as ffi::size_t;
+        let mut output_ptr = ptr::null_mut();
+        let mut output_len = 0;
         let res = unsafe {
             ffi::tj3Transform(
                 self.handle.as_ptr(),
                 jpeg_data.as_ptr(),
                 jpeg_data.len() as ffi::size_t,
                 1,
-                &mut output.ptr,
+                &mut output_ptr,
                 &mut output_len,
-                &mut transform,
+                &c_transform,
             )
         };
-        output.len = output_len as usize;
+        if let Some(panic) = filter_state.panic {
+            std::panic::resume_unwind(panic);
+        }
         if res != 0 {
             return Err(self.handle.get_error());
-        } else if output.ptr.is_null() {
-            output.len = 0;
-            return Err(Error::Null);
         }
-
         Ok(())
     }
 
+    /// Check whether the `transform` would be perfect for an image with the given `header`.
+    ///
+    /// This uses only the size and the chrominance subsampling of the image, so you can warn the
+    /// user before applying a transform that would fail with [`Transform::perfect`] or trim the
+    /// image with [`Transform::trim`]. The header can be obtained using
+    /// [`read_header()`][crate::read_header].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Transform, TransformOp, Transformer};
+    /// let image = turbojpeg::Image::mandelbrot(104, 64, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let header = turbojpeg::read_header(&jpeg_data)?;
+    ///
+    /// // the width is not divisible by 16, so the right edge cannot be moved
+    /// let mut transform = Transform::op(TransformOp::Hflip);
+    /// assert!(!Transformer::check_perfect(&transform, &header));
+    /// assert!(Transformer::check_perfect(&Transform::op(TransformOp::Vflip), &header));
+    ///
+    /// // grayscale output uses 8x8 blocks, so the width only needs to be divisible by 8
+    /// transform.gray = true;
+    /// transform.perfect = true;
+    /// assert!(Transformer::check_perfect(&transform, &header));
+    /// turbojpeg::transform(&transform, &jpeg_data)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn check_perfect(transform: &Transform, header: &DecompressHeader) -> bool {
+        transform.imperfect_edges(header) == (0, 0)
+    }
+
+    /// Losslessly reduce the size of the compressed JPEG by re-encoding its entropy-coded data.
+    ///
+    /// The image is encoded both with optimized Huffman tables ([`Transform::optimize`]) and with
+    /// progressive coding ([`Transform::progressive`]) in a single pass, and the smaller result is
+    /// returned. If neither result is smaller than `jpeg_data`, a copy of `jpeg_data` is returned.
+    /// The pixels and the markers of the image are not changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let optimized = transformer.losslessly_optimize(&jpeg_data)?;
+    /// assert!(optimized.len() <= jpeg_data.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn losslessly_optimize(&mut self, jpeg_data: &[u8]) -> Result<OwnedBuf> {
+        let transforms = [
+            Transform {
+                optimize: true,
+                ..Transform::default()
+            },
+            Transform {
+                progressive: true,
+                ..Transform::default()
+            },
+        ];
+        let outputs = self.transform_multi_to_owned(&transforms, jpeg_data)?;
+        match outputs.into_iter().min_by_key(|output| output.len()) {
+            Some(output) if output.len() < jpeg_data.len() => Ok(output),
+            _ => Ok(OwnedBuf::copy_from_slice(jpeg_data)),
+        }
+    }
+
     /// Transforms the `image` into an owned buffer.
     ///
     /// This method automatically allocates the memory and avoids needless copying.
@@ -371,12 +2615,35 @@ impl Transformer {
     /// Transform the `image` into the slice `output`.
     ///
     /// Returns the size of the transformed JPEG data. If the transformed image does not fit into
-    /// `dest`, this method returns an error.
+    /// `output`, this method returns [`Error::OutputBufTooSmall`] with the number of bytes that
+    /// are required, so that you can grow the buffer and try again.
     ///
     /// You can use [`compressed_buf_len()`][crate::compressed_buf_len] to determine buffer size that
     /// should be enough for the image, but there are some rare cases (such as transforming images
     /// with a large amount of embedded EXIF or ICC profile data) in which the output image will be
     /// larger than the size returned by [`compressed_buf_len()`][crate::compressed_buf_len].
+    /// TurboJPEG may write up to twice that size (plus the size of the markers) into the buffer, so
+    /// into a smaller `output`, the image is transformed through a temporary buffer and copied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let transform = turbojpeg::Transform::op(turbojpeg::TransformOp::Rot90);
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    ///
+    /// let mut output = vec![0; 100];
+    /// match transformer.transform_to_slice(&transform, &jpeg_data, &mut output) {
+    ///     Err(turbojpeg::Error::OutputBufTooSmall(required_len)) => {
+    ///         output.resize(required_len, 0);
+    ///         let len = transformer.transform_to_slice(&transform, &jpeg_data, &mut output)?;
+    ///         let header = turbojpeg::read_header(&output[..len])?;
+    ///         assert_eq!((header.width, header.height), (256, 384));
+    ///     }
+    ///     res => panic!("unexpected result {:?}", res),
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
     pub fn transform_to_slice(
         &mut self,
         transform: &Transform,
@@ -387,6 +2654,437 @@ impl Transformer {
         self.transform(transform, jpeg_data, &mut buf)?;
         Ok(buf.len())
     }
+
+    /// Read a JPEG image from `reader`, transform it and write the result into `writer`.
+    ///
+    /// Returns the number of bytes written. TurboJPEG can only transform complete images held in
+    /// memory, so the input is read until the end of `reader` and the output is produced in a
+    /// temporary buffer before it is written into `writer`; both buffers are freed before this
+    /// method returns. Reading and writing errors are returned as [`Error::Io`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Transform, TransformOp};
+    /// let input = std::fs::File::open("examples/parrots.jpg")?;
+    /// let mut output = Vec::new();
+    ///
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let transform = Transform::op(TransformOp::Rot180);
+    /// let written = transformer.transform_stream(&transform, input, &mut output)?;
+    ///
+    /// assert_eq!(written, output.len());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn transform_stream<R: std::io::Read, W: std::io::Write>(
+        &mut self,
+        transform: &Transform,
+        mut reader: R,
+        mut writer: W,
+    ) -> Result<usize> {
+        let mut jpeg_data = Vec::new();
+        reader.read_to_end(&mut jpeg_data)?;
+        let output = self.transform_to_owned(transform, &jpeg_data)?;
+        drop(jpeg_data);
+        writer.write_all(&output)?;
+        Ok(output.len())
+    }
+
+    /// Start a chain of transforms, in which the output of each transform is the input of the
+    /// next one.
+    ///
+    /// The chain alternates between two buffers owned by TurboJPEG, so that the intermediate
+    /// images are neither copied nor reallocated in every step (the buffers grow only if an
+    /// output does not fit). Unlike [`TransformPipeline`], the chain applies every transform
+    /// separately, and you can inspect the intermediate images between the steps.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use turbojpeg::{Transform, TransformOp};
+    /// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    ///
+    /// let mut chain = transformer.chain(&jpeg_data);
+    /// chain.apply(&Transform::op(TransformOp::Rot90))?;
+    /// let header = turbojpeg::read_header(chain.data())?;
+    /// assert_eq!((header.width, header.height), (256, 384));
+    ///
+    /// chain.apply(&Transform::op(TransformOp::Hflip))?;
+    /// let transposed = chain.finish();
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn chain<'a>(&'a mut self, jpeg_data: &'a [u8]) -> TransformChain<'a> {
+        TransformChain {
+            transformer: self,
+            jpeg_data,
+            bufs: [OwnedBuf::new(), OwnedBuf::new()],
+            current: None,
+        }
+    }
+}
+
+/// Statistics about a transform, see [`Transformer::transform_with_stats()`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub struct TransformStats {
+    /// Size of the input JPEG image in bytes.
+    pub input_len: usize,
+    /// Size of the output JPEG image in bytes.
+    pub output_len: usize,
+    /// Whether any pixels were trimmed (see [`Transform::trim`]).
+    pub trimmed: bool,
+    /// Number of columns trimmed from the right edge of the input image (after
+    /// [scaling][Transform::scale]).
+    pub trimmed_columns: usize,
+    /// Number of rows trimmed from the bottom edge of the input image (after
+    /// [scaling][Transform::scale]).
+    pub trimmed_rows: usize,
+    /// Total number of trimmed pixels.
+    ///
+    /// The trimmed edges are counted in the whole input image after [scaling][Transform::scale],
+    /// even if the transform also crops the image.
+    pub trimmed_pixels: usize,
+    /// Total size in bytes of the extra markers (such as EXIF, ICC profile and comments) in the
+    /// output image, including the marker codes and length fields.
+    pub marker_len: usize,
+    /// The crop that was applied, after the alignment requested by
+    /// [`Transform::crop_rounding`].
+    pub crop: Option<TransformCrop>,
+}
+
+/// Chain of transforms that reuses two output buffers, see [`Transformer::chain()`].
+#[derive(Debug)]
+pub struct TransformChain<'a> {
+    transformer: &'a mut Transformer,
+    jpeg_data: &'a [u8],
+    bufs: [OwnedBuf; 2],
+    /// Index of the buffer with the output of the last step and the length of the output.
+    current: Option<(usize, usize)>,
+}
+
+impl TransformChain<'_> {
+    /// Apply the `transform` to the current image.
+    ///
+    /// Returns the transformed image, which becomes the input of the next step.
+    pub fn apply(&mut self, transform: &Transform) -> Result<&[u8]> {
+        let (input, output) = match self.current {
+            Some((index, len)) => {
+                let [first, second] = &mut self.bufs;
+                if index == 0 {
+                    (&first[..len], second)
+                } else {
+                    (&second[..len], first)
+                }
+            }
+            None => (self.jpeg_data, &mut self.bufs[0]),
+        };
+
+        // the buffer is reused as an owned output, which TurboJPEG may grow if it is too small
+        let mut buf = OutputBuf::owned(std::mem::take(output));
+        self.transformer.transform(transform, input, &mut buf)?;
+        *output = buf.into_owned();
+        let len = output.len();
+
+        let index = match self.current {
+            Some((0, _)) => 1,
+            _ => 0,
+        };
+        self.current = Some((index, len));
+        Ok(&self.bufs[index][..len])
+    }
+
+    /// Returns the current image (the output of the last step, or the input if no transform was
+    /// applied yet).
+    pub fn data(&self) -> &[u8] {
+        match self.current {
+            Some((index, len)) => &self.bufs[index][..len],
+            None => self.jpeg_data,
+        }
+    }
+
+    /// Finishes the chain and returns the current image.
+    ///
+    /// If no transform was applied, this returns a copy of the input image.
+    pub fn finish(self) -> OwnedBuf {
+        match self.current {
+            Some((index, len)) => {
+                let [first, second] = self.bufs;
+                let mut buf = if index == 0 { first } else { second };
+                buf.truncate(len);
+                buf
+            }
+            None => OwnedBuf::copy_from_slice(self.jpeg_data),
+        }
+    }
+}
+
+/// Sequence of lossless transforms that are applied one after another.
+///
+/// Applying the steps one by one with [`Transformer::transform()`] would parse and encode the
+/// image once per step. The pipeline instead collapses adjacent steps into a single transform
+/// whenever the result is the same: rotations and flips are combined into one operation, and a
+/// step with [cropping][Transform::crop] can absorb the preceding rotations and flips. Steps are
+/// only collapsed when the image (after [scaling][Transform::scale]) consists of whole MCU blocks,
+/// so that every transform is [perfect][Transform::perfect]. The remaining steps are applied with
+/// a [`TransformChain`], which reuses two buffers for all steps.
+///
+/// # Example
+///
+/// ```
+/// use turbojpeg::{Transform, TransformCrop, TransformOp, TransformPipeline};
+///
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let mut crop = Transform::default();
+/// crop.crop = Some(TransformCrop { x: 0, y: 0, width: Some(128), height: Some(192) });
+///
+/// // rotate by 90 degrees twice, then crop (executed as a single transform)
+/// let pipeline = TransformPipeline::new()
+///     .then(Transform::op(TransformOp::Rot90))
+///     .then(Transform::op(TransformOp::Rot90))
+///     .then(crop);
+///
+/// let mut transformer = turbojpeg::Transformer::new()?;
+/// let output = pipeline.apply(&mut transformer, &jpeg_data)?;
+///
+/// let header = turbojpeg::read_header(&output)?;
+/// assert_eq!((header.width, header.height), (128, 192));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct TransformPipeline {
+    steps: Vec<Transform>,
+}
+
+impl TransformPipeline {
+    /// Create an empty pipeline.
+    pub fn new() -> TransformPipeline {
+        TransformPipeline::default()
+    }
+
+    /// Append a step to the pipeline.
+    pub fn then(mut self, transform: Transform) -> TransformPipeline {
+        self.steps.push(transform);
+        self
+    }
+
+    /// Append a step to the pipeline.
+    pub fn push(&mut self, transform: Transform) {
+        self.steps.push(transform);
+    }
+
+    /// Get the steps of the pipeline.
+    pub fn steps(&self) -> &[Transform] {
+        &self.steps
+    }
+
+    /// Apply all steps of the pipeline to the compressed JPEG.
+    ///
+    /// If the pipeline is empty, a copy of the image is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use turbojpeg::{Transform, TransformOp, TransformPipeline, TransformScale};
+    ///
+    /// // the scaled image (40x40) does not consist of whole 16x16 MCU blocks
+    /// let image = turbojpeg::Image::mandelbrot(64, 64, turbojpeg::PixelFormat::RGB);
+    /// let jpeg_data = turbojpeg::compress(image.as_deref(), 90, turbojpeg::Subsamp::Sub2x2)?;
+    /// let mut scale = Transform::default();
+    /// scale.scale = TransformScale::new(5, 8)?;
+    /// let rotate = Transform::op(TransformOp::Rot90);
+    ///
+    /// // the rotation is not merged into the scaling, so the result is the same as applying the
+    /// // steps one by one
+    /// let mut transformer = turbojpeg::Transformer::new()?;
+    /// let pipeline = TransformPipeline::new().then(scale.clone()).then(rotate.clone());
+    /// let output = pipeline.apply(&mut transformer, &jpeg_data)?;
+    ///
+    /// let scaled = transformer.transform_to_owned(&scale, &jpeg_data)?;
+    /// let rotated = transformer.transform_to_owned(&rotate, &scaled)?;
+    /// assert_eq!(&*output, &*rotated);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn apply(&self, transformer: &mut Transformer, jpeg_data: &[u8]) -> Result<OwnedBuf> {
+        let mut chain = transformer.chain(jpeg_data);
+        let mut steps = self.steps.iter().peekable();
+        while let Some(step) = steps.next() {
+            let mut merged = step.clone();
+            // the merged operations are applied to the scaled image
+            if steps.peek().is_some() && is_mcu_aligned(chain.data(), step.scale)? {
+                while merged.crop.is_none() && merged.redact.is_empty() {
+                    match steps.next_if(|next| next.scale == TransformScale::ONE) {
+                        Some(next) => merged = merged.then(next),
+                        None => break,
+                    }
+                }
+            }
+            chain.apply(&merged)?;
+        }
+        Ok(chain.finish())
+    }
+}
+
+/// Returns true if the image scaled by `scale` consists of whole MCU blocks, so that all
+/// transforms of the scaled image are perfect.
+fn is_mcu_aligned(jpeg_data: &[u8], scale: TransformScale) -> Result<bool> {
+    let header = crate::read_header(jpeg_data)?;
+    let (mcu_width, mcu_height) = header.subsamp.mcu_size();
+    Ok(header.subsamp != Subsamp::Unknown
+        && scale.apply(header.width) % mcu_width == 0
+        && scale.apply(header.height) % mcu_height == 0)
+}
+
+/// Reads the quantization table of every component of the frame in natural order, the order of
+/// the coefficients in [`DctBlocks`].
+fn natural_quant_tables(jpeg_data: &[u8]) -> Result<Vec<[u16; 64]>> {
+    let tables = markers::component_quant_tables(jpeg_data)?
+        .into_iter()
+        .map(|table| {
+            let mut natural = [0; 64];
+            for (zigzag, &index) in ZIGZAG_TO_NATURAL.iter().enumerate() {
+                natural[index] = table[zigzag];
+            }
+            natural
+        })
+        .collect();
+    Ok(tables)
+}
+
+/// Returns the maximal length of any transformation of `jpeg_data`.
+///
+/// The image data are bounded by the buffer size of TurboJPEG for the image padded to whole MCUs
+/// (so that rotations fit as well) without chrominance subsampling, doubled for images with four
+/// components or more than 8 bits per sample, and at most the segments before the first scan are
+/// copied.
+pub(crate) fn max_transformed_len(jpeg_data: &[u8]) -> Result<usize> {
+    let header = crate::read_header(jpeg_data)?;
+    let data_len = crate::compressed_buf_len(
+        header.width.next_multiple_of(16),
+        header.height.next_multiple_of(16),
+        Subsamp::None,
+    )?;
+    let mut markers_len = jpeg_data.len();
+    for segment in markers::segments(jpeg_data) {
+        let segment = segment?;
+        if segment.marker == markers::SOS {
+            markers_len = segment.offset;
+            break;
+        }
+    }
+    data_len
+        .checked_mul(2)
+        .and_then(|len| len.checked_add(markers_len))
+        .ok_or(Error::IntegerOverflow("buf len"))
+}
+
+/// Row of DCT blocks passed to the filter of [`Transformer::transform_with_filter()`].
+///
+/// The coordinates are given in samples of the component plane (the chrominance planes are
+/// smaller than the image if chrominance subsampling is used), and they are always multiples of
+/// 8, the size of a DCT block.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DctBlocks<'a> {
+    /// Index of the component (for YCbCr images, 0 is Y, 1 is Cb and 2 is Cr).
+    pub component: usize,
+    /// Left boundary of the blocks in the component plane.
+    pub x: usize,
+    /// Upper boundary of the blocks in the component plane.
+    pub y: usize,
+    /// Width of the blocks in samples.
+    pub width: usize,
+    /// Height of the blocks in samples.
+    pub height: usize,
+    /// Width of the component plane in samples (padded to whole DCT blocks).
+    pub plane_width: usize,
+    /// Height of the component plane in samples (padded to whole DCT blocks).
+    pub plane_height: usize,
+    /// Quantized DCT coefficients of the blocks.
+    ///
+    /// Every block is stored as 64 consecutive coefficients in natural (row-major) order, and the
+    /// blocks are stored from left to right.
+    pub coefficients: &'a mut [i16],
+}
+
+struct FilterState<'f> {
+    filter: Option<&'f mut dyn FnMut(DctBlocks<'_>)>,
+    /// Regions to blank for every transform (empty if no transform has any).
+    redactions: Vec<Redaction>,
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+/// Regions of [`Transform::redact`], converted to MCU blocks of the output image.
+#[derive(Debug)]
+struct Redaction {
+    /// Number of DCT blocks per MCU in the components other than chrominance.
+    factors: (usize, usize),
+    /// Regions as (left, top, right, bottom) MCU coordinates, exclusive on the right and bottom.
+    regions: Vec<[usize; 4]>,
+}
+
+impl Redaction {
+    /// Zeroes the blocks of `blocks` that are covered by the regions.
+    fn apply(&self, blocks: &mut DctBlocks<'_>) {
+        let (h, v) = match blocks.component {
+            1 | 2 => (1, 1),
+            _ => self.factors,
+        };
+        let row = blocks.y / 8;
+        let row_len = blocks.coefficients.len() / 64;
+        for &[left, top, right, bottom] in self.regions.iter() {
+            if top * v <= row && row < bottom.saturating_mul(v) {
+                let start = usize::min(left * h, row_len);
+                let end = usize::min(right.saturating_mul(h), row_len);
+                blocks.coefficients[64 * start..64 * end].fill(0);
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn filter_callback(
+    coeffs: *mut libc::c_short,
+    array_region: ffi::tjregion,
+    plane_region: ffi::tjregion,
+    component_id: libc::c_int,
+    transform_id: libc::c_int,
+    transform: *mut ffi::tjtransform,
+) -> libc::c_int {
+    let state = &mut *((*transform).data as *mut FilterState);
+    if state.panic.is_some() {
+        return -1;
+    }
+
+    let len = array_region.w as usize * array_region.h as usize;
+    let mut blocks = DctBlocks {
+        component: component_id as usize,
+        x: array_region.x as usize,
+        y: array_region.y as usize,
+        width: array_region.w as usize,
+        height: array_region.h as usize,
+        plane_width: plane_region.w as usize,
+        plane_height: plane_region.h as usize,
+        coefficients: std::slice::from_raw_parts_mut(coeffs, len),
+    };
+    if let Some(ref mut filter) = state.filter {
+        // unwinding across the FFI boundary is undefined behavior, so the panic is resumed after
+        // TurboJPEG returns
+        let blocks = &mut blocks;
+        let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            filter(DctBlocks {
+                coefficients: &mut *blocks.coefficients,
+                ..*blocks
+            })
+        }));
+        if let Err(panic) = res {
+            state.panic = Some(panic);
+            return -1;
+        }
+    }
+    if let Some(redaction) = state.redactions.get(transform_id as usize) {
+        redaction.apply(&mut blocks);
+    }
+    0
 }
 
 /// Losslessly transform a JPEG image without recompression.
@@ -419,3 +3117,78 @@ pub fn transform(transform: &Transform, jpeg_data: &[u8]) -> Result<OwnedBuf> {
     let mut transformer = Transformer::new()?;
     transformer.transform_to_owned(transform, jpeg_data)
 }
+
+/// Losslessly transform a JPEG image into the slice `output`.
+///
+/// Returns the size of the transformed JPEG data. If the transformed image does not fit into
+/// `output`, this function returns [`Error::OutputBufTooSmall`] with the number of bytes that are
+/// required. See [`Transformer::transform_to_slice()`] for details; if you transform many images,
+/// reusing a [`Transformer`] is more efficient.
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let transform = turbojpeg::Transform::op(turbojpeg::TransformOp::Hflip);
+///
+/// let mut output = vec![0; 2 * jpeg_data.len()];
+/// let len = turbojpeg::transform_to_slice(&transform, &jpeg_data, &mut output)?;
+/// let header = turbojpeg::read_header(&output[..len])?;
+/// assert_eq!((header.width, header.height), (384, 256));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn transform_to_slice(
+    transform: &Transform,
+    jpeg_data: &[u8],
+    output: &mut [u8],
+) -> Result<usize> {
+    let mut transformer = Transformer::new()?;
+    transformer.transform_to_slice(transform, jpeg_data, output)
+}
+
+/// Remove all metadata from a JPEG image without recompression.
+///
+/// This removes all extra markers (such as EXIF, XMP, IPTC and comments) from the image, which
+/// is the same as [`transform()`] with [`Transform::copy_none`]. If `keep_icc` is true, the ICC
+/// color profile is kept, because the colors of the image may be displayed incorrectly without
+/// it (see [`KeepMarkers`] for finer control).
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let stripped = turbojpeg::strip_metadata(&jpeg_data, true)?;
+///
+/// assert!(!stripped.windows(4).any(|w| w == b"Exif"));
+/// assert!(stripped.len() < jpeg_data.len());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn strip_metadata(jpeg_data: &[u8], keep_icc: bool) -> Result<OwnedBuf> {
+    let mut transform = Transform::default();
+    if keep_icc {
+        transform.keep_markers = Some(KeepMarkers {
+            icc: true,
+            ..KeepMarkers::default()
+        });
+    } else {
+        transform.copy_none = true;
+    }
+    self::transform(&transform, jpeg_data)
+}
+
+/// Losslessly reduce the size of a JPEG image by re-encoding its entropy-coded data.
+///
+/// See [`Transformer::losslessly_optimize()`] for details.
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let optimized = turbojpeg::losslessly_optimize(&jpeg_data)?;
+/// std::fs::write(std::env::temp_dir().join("optimized_parrots.jpg"), &optimized)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn losslessly_optimize(jpeg_data: &[u8]) -> Result<OwnedBuf> {
+    let mut transformer = Transformer::new()?;
+    transformer.losslessly_optimize(jpeg_data)
+}
@@ -0,0 +1,118 @@
+use crate::common::{Error, Result};
+use crate::exif;
+use crate::markers::{self, Segment, APP0, SOI, SOS, XMP_EXTENSION_HEADER, XMP_HEADER};
+
+/// Maximal length of an XMP packet that fits into a single APP1 segment.
+pub(crate) const MAX_XMP_LEN: usize = markers::MAX_PAYLOAD_LEN - XMP_HEADER.len();
+
+/// Returns true if the segment is an XMP segment (APP1 starting with the XMP namespace), or an
+/// extended XMP segment if `extended` is true.
+fn is_xmp(segment: &Segment<'_>, extended: bool) -> bool {
+    segment.marker == APP0 + 1
+        && (segment.payload.starts_with(XMP_HEADER)
+            || (extended && segment.payload.starts_with(XMP_EXTENSION_HEADER)))
+}
+
+/// Returns the payload of the APP1 segment that stores the XMP `packet`.
+pub(crate) fn xmp_payload(packet: &[u8]) -> Result<Vec<u8>> {
+    if packet.len() > MAX_XMP_LEN {
+        return Err(Error::InvalidParam("xmp"));
+    }
+    Ok([XMP_HEADER, packet].concat())
+}
+
+/// Read the XMP packet embedded in a JPEG image.
+///
+/// The packet is the payload of the APP1 segment that starts with the XMP namespace
+/// (`http://ns.adobe.com/xap/1.0/`), without the namespace. It is usually an UTF-8 encoded XML
+/// document. Returns `None` if the image has no XMP segment. Extended XMP segments (which store
+/// the parts of large packets that do not fit into a single segment) are not returned.
+///
+/// # Example
+///
+/// ```
+/// let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+/// let image = turbojpeg::Image::mandelbrot(64, 48, turbojpeg::PixelFormat::RGB);
+/// let mut compressor = turbojpeg::Compressor::new()?;
+/// compressor.set_xmp(xmp)?;
+/// let jpeg_data = compressor.compress_to_vec(image.as_deref())?;
+///
+/// assert_eq!(turbojpeg::read_xmp(&jpeg_data)?.as_deref(), Some(&xmp[..]));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn read_xmp(jpeg_data: &[u8]) -> Result<Option<Vec<u8>>> {
+    for segment in markers::segments(jpeg_data) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            break;
+        } else if is_xmp(&segment, false) {
+            return Ok(Some(segment.payload[XMP_HEADER.len()..].to_vec()));
+        }
+    }
+    Ok(None)
+}
+
+/// Replace the XMP packet of a JPEG image, returning the modified image.
+///
+/// All XMP segments (including extended XMP) are removed from the image, and if `xmp` is given,
+/// a new XMP segment with this packet is stored in place of the old one, or after the JFIF and
+/// EXIF segments if the image had no XMP. The image data are copied without being
+/// recompressed. Returns [`Error::InvalidParam`] if the packet is longer than 65504 bytes (the
+/// maximal payload of a JPEG segment minus the XMP namespace), or [`Error::InvalidJpeg`] if the
+/// image is malformed.
+///
+/// # Example
+///
+/// ```
+/// let jpeg_data = std::fs::read("examples/parrots.jpg")?;
+/// let old_xmp = turbojpeg::read_xmp(&jpeg_data)?.unwrap();
+/// assert!(old_xmp.starts_with(b"<?xpacket"));
+///
+/// let xmp = br#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#;
+/// let replaced = turbojpeg::replace_xmp(&jpeg_data, Some(xmp))?;
+/// assert_eq!(turbojpeg::read_xmp(&replaced)?.as_deref(), Some(&xmp[..]));
+///
+/// let removed = turbojpeg::replace_xmp(&replaced, None)?;
+/// assert_eq!(turbojpeg::read_xmp(&removed)?, None);
+/// // the image data are not modified
+/// let restored = turbojpeg::replace_xmp(&removed, Some(&old_xmp))?;
+/// assert_eq!(restored, jpeg_data);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn replace_xmp(jpeg_data: &[u8], xmp: Option<&[u8]>) -> Result<Vec<u8>> {
+    let payload = xmp.map(xmp_payload).transpose()?;
+    let payload_len = payload.as_ref().map_or(0, |payload| payload.len() + 4);
+    let mut output = Vec::with_capacity(jpeg_data.len() + payload_len);
+
+    // copy the segments before the first scan, except the XMP segments
+    let mut insert_at = None;
+    let mut header_end = 0;
+    let mut data_start = jpeg_data.len();
+    for segment in markers::segments(jpeg_data) {
+        let segment = segment?;
+        if segment.marker == SOS {
+            data_start = segment.offset;
+            break;
+        } else if is_xmp(&segment, true) {
+            insert_at.get_or_insert(output.len());
+            continue;
+        }
+
+        let is_header = segment.marker == SOI
+            || (segment.marker == APP0 && segment.is_encoder_header())
+            || exif::is_exif(&segment);
+        output.extend_from_slice(&jpeg_data[segment.offset..segment.offset + segment.len]);
+        if is_header && header_end + segment.len == output.len() {
+            header_end = output.len();
+        }
+    }
+
+    if let Some(payload) = payload {
+        let mut segment = Vec::with_capacity(payload_len);
+        markers::write_segment(&mut segment, APP0 + 1, &payload);
+        let insert_at = insert_at.unwrap_or(header_end);
+        output.splice(insert_at..insert_at, segment);
+    }
+    output.extend_from_slice(&jpeg_data[data_start..]);
+    Ok(output)
+}
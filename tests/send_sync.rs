@@ -0,0 +1,133 @@
+//! Thread safety of the handles and buffers.
+//!
+//! The buffers only hold raw pointers because of the FFI, so they implement `Send` (and
+//! `OwnedBuf` also `Sync`) with `unsafe impl`s. The tests that do not call into TurboJPEG use a
+//! custom [`BufAllocator`] and also run under [Miri](https://github.com/rust-lang/miri), which
+//! checks that the buffers are moved and shared between threads without undefined behavior:
+//!
+//! ```text
+//! cargo +nightly miri test --test send_sync
+//! ```
+//!
+//! Miri cannot call into TurboJPEG, so the tests that use the handles are ignored under Miri.
+
+use std::alloc::Layout;
+use std::sync::Arc;
+use std::thread;
+
+use turbojpeg::{
+    BufAllocator, Compressor, Decompressor, Image, OutputBuf, OwnedBuf, PixelFormat, Transformer,
+};
+
+fn assert_send<T: Send>() {}
+fn assert_sync<T: Sync>() {}
+
+#[test]
+fn buffers_and_handles_are_send() {
+    assert_send::<OwnedBuf>();
+    assert_sync::<OwnedBuf>();
+    assert_send::<OutputBuf<'static>>();
+    assert_send::<Compressor>();
+    assert_send::<Decompressor>();
+    assert_send::<Transformer>();
+}
+
+/// Allocates zeroed memory with the global allocator, without calling into TurboJPEG.
+struct GlobalAllocator;
+
+unsafe impl BufAllocator for GlobalAllocator {
+    fn allocate(&self, len: usize) -> *mut u8 {
+        unsafe { std::alloc::alloc_zeroed(Layout::array::<u8>(len).unwrap()) }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, len: usize) {
+        std::alloc::dealloc(ptr, Layout::array::<u8>(len).unwrap());
+    }
+}
+
+#[test]
+fn buffers_move_across_threads() {
+    let allocator = Arc::new(GlobalAllocator);
+
+    // the buffer is written on another thread, moved back and freed on this thread
+    let buf = OwnedBuf::allocate_in(64, allocator.clone());
+    let buf = thread::spawn(move || {
+        let mut buf = buf;
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        buf
+    })
+    .join()
+    .unwrap();
+    assert!(buf.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+
+    // an owned output buffer is converted on another thread
+    let output: OutputBuf<'static> = OutputBuf::owned(buf);
+    let buf = thread::spawn(move || output.into_owned()).join().unwrap();
+    assert_eq!(buf.len(), 64);
+
+    // the buffer is shared between threads
+    let buf = Arc::new(buf);
+    let threads = (0..4)
+        .map(|_| {
+            let buf = Arc::clone(&buf);
+            thread::spawn(move || buf.iter().map(|&byte| byte as usize).sum::<usize>())
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), (0..64).sum::<usize>());
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn handles_and_buffers_move_across_threads() {
+    let image = Image::mandelbrot(64, 48, PixelFormat::RGB);
+
+    // the compressor and the buffer allocated by TurboJPEG are moved to another thread, and the
+    // buffer is moved back and freed on this thread
+    let mut compressor = Compressor::new().unwrap();
+    let (compressor, jpeg_data) = thread::spawn(move || {
+        let jpeg_data = compressor.compress_to_owned(image.as_deref()).unwrap();
+        (compressor, jpeg_data)
+    })
+    .join()
+    .unwrap();
+    drop(compressor);
+
+    // an owned output buffer is filled on another thread and dropped on this one
+    let mut compressor = Compressor::new().unwrap();
+    let image = Image::mandelbrot(64, 48, PixelFormat::RGB);
+    let mut output: OutputBuf<'static> = OutputBuf::new_owned();
+    let output = thread::spawn(move || {
+        compressor.compress(image.as_deref(), &mut output).unwrap();
+        output
+    })
+    .join()
+    .unwrap();
+    assert_eq!(&*output, &*jpeg_data);
+    drop(output);
+
+    // the buffer is shared between threads, each of which uses its own handles
+    let jpeg_data = Arc::new(jpeg_data);
+    let threads = (0..4)
+        .map(|_| {
+            let jpeg_data = Arc::clone(&jpeg_data);
+            let mut decompressor = Decompressor::new().unwrap();
+            let mut transformer = Transformer::new().unwrap();
+            thread::spawn(move || {
+                let header = decompressor.read_header(&jpeg_data).unwrap();
+                let transformed = transformer
+                    .transform_to_owned(&Default::default(), &jpeg_data)
+                    .unwrap();
+                (header.width, header.height, transformed)
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        let (width, height, transformed) = thread.join().unwrap();
+        assert_eq!((width, height), (64, 48));
+        assert_eq!(turbojpeg::read_header(&transformed).unwrap().width, 64);
+    }
+}